@@ -0,0 +1,393 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : chart_bridge.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Bridges the SCXML statechart and the live game, in both directions.
+
+    Game facts are sent to the chart as events whenever they change, named
+    'fact.<fact>.<value>', e.g. 'fact.weather.element.fire' or
+    'fact.selection.actor'. The chart need only register those it uses.
+
+    The chart drives the game through its <log> output, as the statechart
+    library does not yet support <send>. Lines logged with the label 'command'
+    are queued as game commands, in which '$<fact>' placeholders are replaced
+    with the current facts. Lines logged with a label of 'data.<id>' mirror the
+    datamodel member <id>, so that the game can read it:
+        <log label="command" expr="'spawn actor $selection'"/>
+        <log label="data.in_combat" expr="in_combat"/>
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    io::{self, Write},
+    rc::Rc,
+};
+
+use cast_iron::{coords, element::Element, mechanics::resource::State};
+
+use dd_statechart::StateChart;
+
+use crate::map_file;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Label of chart log lines carrying game commands
+const COMMAND_LOG_LABEL: &str = "command: ";
+
+/// Label prefix of chart log lines mirroring datamodel members
+const DATA_LOG_LABEL_PREFIX: &str = "data.";
+
+/// Prefix of the IDs of the events sent when a fact changes
+pub const FACT_EVENT_PREFIX: &str = "fact.";
+
+/// Resource states reported to the chart, in order of the resource counts
+const RESOURCE_STATES: [State; 6] = [
+    State::Depleted,
+    State::Low,
+    State::Partial,
+    State::High,
+    State::Full,
+    State::Overflow,
+];
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Snapshot of the game facts exposed to the chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartFacts {
+    weather_element: Element,                 // Element of the active weather
+    weather_intensity: f64,                   // Current weather intensity
+    weather_severity: &'static str,           // Band of the weather intensity
+    selected_actor: Option<coords::Position>, // Position of the selected actor, if any
+    actor_count: usize,                       // Number of actors in the world
+    resource_counts: [usize; 6],              // Number of resources in each of the resource states
+}
+
+/// Output received from the chart, awaiting collection by the game
+#[derive(Default)]
+struct BridgeInbox {
+    commands: VecDeque<String>, // Commands issued by the chart, oldest first
+    data: BTreeMap<String, String>, // Latest mirrored value of each datamodel member
+}
+
+/// Writer for the chart's output, picking out commands and data before passing it on
+pub struct ChartOutput<W: Write> {
+    writer: W,                       // Writer receiving all of the chart's output
+    inbox: Rc<RefCell<BridgeInbox>>, // Inbox shared with the bridge
+    line: Vec<u8>,                   // Partial line written so far
+}
+
+/// Statechart whose output passes through the bridge
+pub type BridgedChart<W> = StateChart<ChartOutput<W>>;
+
+pub struct ChartBridge {
+    inbox: Rc<RefCell<BridgeInbox>>, // Inbox shared with the output of every chart created
+    facts: Option<ChartFacts>,       // Facts last sent to the chart
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ChartFacts {
+    /// Fully-qualified constructor
+    pub fn new(
+        weather: (Element, f64),
+        max_weather_intensity: f64,
+        selected_actor: Option<coords::Position>,
+        actor_count: usize,
+        resource_states: &[State],
+    ) -> Self {
+        let (weather_element, weather_intensity) = weather;
+
+        let mut resource_counts = [0; 6];
+        for state in resource_states {
+            if let Some(idx) = RESOURCE_STATES.iter().position(|s| s == state) {
+                resource_counts[idx] += 1;
+            }
+        }
+
+        Self {
+            weather_element,
+            weather_intensity,
+            weather_severity: severity(weather_intensity / max_weather_intensity),
+            selected_actor,
+            actor_count,
+            resource_counts,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the value of each fact as reported to the chart, keyed by fact name
+    pub fn values(&self) -> Vec<(String, &'static str)> {
+        let mut values = vec![
+            (
+                String::from("weather.element"),
+                match self.weather_element {
+                    Element::Unset => "none",
+                    element => map_file::element_to_str(element),
+                },
+            ),
+            (String::from("weather.severity"), self.weather_severity),
+            (
+                String::from("selection"),
+                if self.selected_actor.is_some() {
+                    "actor"
+                } else {
+                    "none"
+                },
+            ),
+            (String::from("actors"), presence(self.actor_count)),
+        ];
+
+        for (state, count) in RESOURCE_STATES.iter().zip(&self.resource_counts) {
+            values.push((
+                format!("resources.{}", map_file::state_to_str(*state)),
+                presence(*count),
+            ));
+        }
+
+        values
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the IDs of the events for each fact differing from the given previous facts
+    pub fn changed_event_ids(&self, previous: Option<&ChartFacts>) -> Vec<String> {
+        let previous_values = previous.map(ChartFacts::values);
+
+        self.values()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, value)| {
+                previous_values
+                    .as_ref()
+                    .map_or(true, |previous_values| previous_values[*i] != *value)
+            })
+            .map(|(_i, (fact, value))| format!("{}{}.{}", FACT_EVENT_PREFIX, fact, value))
+            .collect()
+    }
+
+    /// Replaces the fact placeholders in the given command with the current facts
+    ///
+    /// A selection placeholder is left in place when nothing is selected, so the command fails to
+    /// parse rather than acting on the wrong position.
+    pub fn expand(&self, command: &str) -> String {
+        let mut expanded = command
+            .replace(
+                "$weather.element",
+                map_file::element_to_str(self.weather_element),
+            )
+            .replace(
+                "$weather.intensity",
+                &format!("{:.0}", self.weather_intensity),
+            )
+            .replace("$actors", &self.actor_count.to_string());
+
+        if let Some(position) = self.selected_actor {
+            expanded = expanded.replace(
+                "$selection",
+                &format!("{} {} {}", position.x(), position.y(), position.z()),
+            );
+        }
+
+        expanded
+    }
+}
+
+impl<W: Write> ChartOutput<W> {
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Collects any command or data in the completed line, then passes it on to the writer
+    fn route_line(&mut self) -> io::Result<()> {
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+
+        let mut inbox = self.inbox.borrow_mut();
+        if let Some(command) = line.strip_prefix(COMMAND_LOG_LABEL) {
+            inbox.commands.push_back(String::from(command.trim()));
+        } else if let Some(data) = line.strip_prefix(DATA_LOG_LABEL_PREFIX) {
+            if let Some((id, value)) = data.split_once(": ") {
+                inbox
+                    .data
+                    .insert(String::from(id), String::from(value.trim()));
+            }
+        }
+
+        writeln!(self.writer, "{}", line)
+    }
+}
+
+impl ChartBridge {
+    /// Generic Constructor - creates a bridge which has yet to send any facts
+    pub fn new() -> Self {
+        Self {
+            inbox: Rc::new(RefCell::new(BridgeInbox::default())),
+            facts: None,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the facts last sent to the chart, if any
+    pub fn facts(&self) -> Option<&ChartFacts> {
+        self.facts.as_ref()
+    }
+
+    /// Returns the mirrored value of the given datamodel member, if the chart has reported it
+    pub fn data(&self, id: &str) -> Option<String> {
+        self.inbox.borrow().data.get(id).cloned()
+    }
+
+    /// Returns the mirrored value of every datamodel member the chart has reported, by ID
+    pub fn data_members(&self) -> Vec<(String, String)> {
+        self.inbox
+            .borrow()
+            .data
+            .iter()
+            .map(|(id, value)| (id.clone(), value.clone()))
+            .collect()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Takes all commands issued by the chart since the last call, with placeholders expanded
+    pub fn take_commands(&mut self) -> Vec<String> {
+        let commands: Vec<String> = self.inbox.borrow_mut().commands.drain(..).collect();
+
+        match &self.facts {
+            Some(facts) => commands
+                .iter()
+                .map(|command| facts.expand(command))
+                .collect(),
+            None => commands,
+        }
+    }
+
+    /// Discards all commands issued by the chart, such as those repeated by replaying events
+    pub fn discard_commands(&mut self) {
+        self.inbox.borrow_mut().commands.clear();
+    }
+
+    /// Returns the IDs of the events for every current fact, to bring a new chart up to date
+    pub fn fact_event_ids(&self) -> Vec<String> {
+        self.facts
+            .as_ref()
+            .map_or_else(Vec::new, |facts| facts.changed_event_ids(None))
+    }
+
+    /// Records the given facts, returning the IDs of the events for each fact that changed
+    pub fn update_facts(&mut self, facts: ChartFacts) -> Vec<String> {
+        let changed_event_ids = facts.changed_event_ids(self.facts.as_ref());
+        self.facts = Some(facts);
+
+        changed_event_ids
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Wraps the given writer to receive the output of a newly created chart
+    pub fn output<W: Write>(&self, writer: W) -> ChartOutput<W> {
+        ChartOutput {
+            writer,
+            inbox: Rc::clone(&self.inbox),
+            line: Vec::new(),
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for ChartBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Write for ChartOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for byte in buf {
+            if *byte == b'\n' {
+                self.route_line()?;
+            } else {
+                self.line.push(*byte);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the band of a weather intensity, given as a fraction of the maximum intensity
+fn severity(intensity_fraction: f64) -> &'static str {
+    if intensity_fraction <= 0.0 {
+        "calm"
+    } else if intensity_fraction < 1.0 / 3.0 {
+        "light"
+    } else if intensity_fraction < 2.0 / 3.0 {
+        "moderate"
+    } else {
+        "severe"
+    }
+}
+
+/// Returns whether any of something is present
+fn presence(count: usize) -> &'static str {
+    if count > 0 {
+        "some"
+    } else {
+        "none"
+    }
+}
@@ -0,0 +1,461 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : chart_layout.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Reads the structure of an SCXML statechart - its states and the
+    transitions between them - for display in the statechart overlay.
+
+    The statechart itself does not expose its structure, so the chart file is
+    scanned separately. Only the <state>, <parallel>, <final> and <transition>
+    elements are of interest; everything else is skipped over, so this is no
+    substitute for validating the chart.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{error::Error, fmt, fs, io, path::Path};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A single state of the chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartNode {
+    id: String,            // ID of the state
+    parent: Option<usize>, // Index of the enclosing state, if any
+    depth: usize,          // Number of states enclosing this one
+}
+
+/// A single transition of the chart, to at most one target
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartEdge {
+    source: usize,         // Index of the state the transition leaves
+    target: Option<usize>, // Index of the state the transition enters, if any
+    events: Vec<String>,   // IDs of the events which trigger the transition
+}
+
+/// States and transitions of a chart, in document order
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChartLayout {
+    nodes: Vec<ChartNode>,
+    edges: Vec<ChartEdge>,
+}
+
+#[derive(Debug)]
+pub enum ChartLayoutError {
+    // Wrappers
+    IoError(io::Error),
+
+    // Parsing
+    MalformedTag(usize, String),
+    MissingAttribute(usize, &'static str, String),
+    TransitionOutsideState(usize),
+    UnbalancedTag(usize, String),
+    UnknownTarget(usize, String),
+}
+
+/// Transition read from the chart, whose targets are yet to be resolved
+struct PendingEdge {
+    line_num: usize,
+    source: usize,
+    target_ids: Vec<String>,
+    events: Vec<String>,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ChartNode {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl ChartEdge {
+    pub fn source(&self) -> usize {
+        self.source
+    }
+
+    pub fn target(&self) -> Option<usize> {
+        self.target
+    }
+
+    pub fn events(&self) -> &Vec<String> {
+        &self.events
+    }
+
+    /// Returns true if the transition is triggered by the event with the given ID
+    pub fn accepts(&self, event_id: &str) -> bool {
+        self.events.iter().any(|event| event == event_id)
+    }
+}
+
+impl ChartLayout {
+    /// Loads and scans the chart file at the given path
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ChartLayoutError> {
+        let contents = fs::read_to_string(path).map_err(ChartLayoutError::IoError)?;
+
+        Self::parse(&contents)
+    }
+
+    /// Scans chart file contents
+    pub fn parse(contents: &str) -> Result<Self, ChartLayoutError> {
+        let mut nodes: Vec<ChartNode> = Vec::new();
+        let mut pending_edges: Vec<PendingEdge> = Vec::new();
+
+        // Name of each open element, with its node index if it is a state
+        let mut open_elements: Vec<(String, Option<usize>)> = Vec::new();
+
+        // Line numbers are 1-based for error reporting, and counted up to each tag as it is reached
+        let mut line_num = 1;
+        let mut line_counted_to = 0;
+
+        let mut offset = 0;
+        while let Some(tag_start) = contents[offset..].find('<').map(|idx| offset + idx) {
+            line_num += contents[line_counted_to..tag_start].matches('\n').count();
+            line_counted_to = tag_start;
+
+            // Skip comments, character data, declarations and processing instructions
+            let tag_rest = &contents[tag_start..];
+            let skip_to = if tag_rest.starts_with("<!--") {
+                Some("-->")
+            } else if tag_rest.starts_with("<![CDATA[") {
+                Some("]]>")
+            } else if tag_rest.starts_with("<?") || tag_rest.starts_with("<!") {
+                Some(">")
+            } else {
+                None
+            };
+            if let Some(terminator) = skip_to {
+                offset = tag_rest
+                    .find(terminator)
+                    .map(|idx| tag_start + idx + terminator.len())
+                    .ok_or_else(|| {
+                        ChartLayoutError::MalformedTag(line_num, String::from("unterminated tag"))
+                    })?;
+                continue;
+            }
+
+            let tag_end = find_tag_end(tag_rest)
+                .map(|idx| tag_start + idx)
+                .ok_or_else(|| {
+                    ChartLayoutError::MalformedTag(line_num, String::from("unterminated tag"))
+                })?;
+            let tag = &contents[tag_start + 1..tag_end];
+            offset = tag_end + 1;
+
+            // Closing tags must match the innermost open element
+            if let Some(closing_name) = tag.strip_prefix('/') {
+                let closing_name = closing_name.trim();
+                match open_elements.pop() {
+                    Some((open_name, _node_idx)) if open_name == closing_name => {}
+                    _ => {
+                        return Err(ChartLayoutError::UnbalancedTag(
+                            line_num,
+                            String::from(closing_name),
+                        ))
+                    }
+                }
+                continue;
+            }
+
+            let self_closing = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let name = tag.split_whitespace().next().unwrap_or_default();
+            let attributes = parse_attributes(&tag[name.len()..], line_num)?;
+            let attribute = |attr_name: &str| {
+                attributes
+                    .iter()
+                    .find(|(key, _value)| *key == attr_name)
+                    .map(|(_key, value)| *value)
+            };
+
+            // Innermost state enclosing this element, if any
+            let enclosing_state = open_elements
+                .iter()
+                .rev()
+                .find_map(|(_name, node_idx)| *node_idx);
+
+            let mut node_idx = None;
+            match name {
+                "state" | "parallel" | "final" => {
+                    let id = attribute("id").ok_or_else(|| {
+                        ChartLayoutError::MissingAttribute(line_num, "id", String::from(name))
+                    })?;
+
+                    let depth = enclosing_state.map_or(0, |parent| nodes[parent].depth + 1);
+                    nodes.push(ChartNode {
+                        id: String::from(id),
+                        parent: enclosing_state,
+                        depth,
+                    });
+                    node_idx = Some(nodes.len() - 1);
+                }
+                "transition" => {
+                    let source = enclosing_state
+                        .ok_or(ChartLayoutError::TransitionOutsideState(line_num))?;
+
+                    pending_edges.push(PendingEdge {
+                        line_num,
+                        source,
+                        target_ids: split_ids(attribute("target")),
+                        events: split_ids(attribute("event")),
+                    });
+                }
+                _ => {}
+            }
+
+            if !self_closing {
+                open_elements.push((String::from(name), node_idx));
+            }
+        }
+
+        if let Some((open_name, _node_idx)) = open_elements.pop() {
+            let line_num = line_num + contents[line_counted_to..].matches('\n').count();
+            return Err(ChartLayoutError::UnbalancedTag(line_num, open_name));
+        }
+
+        // Targets may refer to states later in the document, so are only resolved once all are known
+        let mut edges = Vec::new();
+        for pending_edge in pending_edges {
+            if pending_edge.target_ids.is_empty() {
+                edges.push(ChartEdge {
+                    source: pending_edge.source,
+                    target: None,
+                    events: pending_edge.events,
+                });
+                continue;
+            }
+
+            for target_id in &pending_edge.target_ids {
+                let target = nodes
+                    .iter()
+                    .position(|node| node.id == *target_id)
+                    .ok_or_else(|| {
+                        ChartLayoutError::UnknownTarget(pending_edge.line_num, target_id.clone())
+                    })?;
+
+                edges.push(ChartEdge {
+                    source: pending_edge.source,
+                    target: Some(target),
+                    events: pending_edge.events.clone(),
+                });
+            }
+        }
+
+        Ok(Self { nodes, edges })
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn nodes(&self) -> &Vec<ChartNode> {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &Vec<ChartEdge> {
+        &self.edges
+    }
+
+    /// Returns the index of the state with the given ID, if any
+    pub fn node_index(&self, id: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == id)
+    }
+
+    /// Returns the IDs of the events accepted anywhere in the chart, sorted and without repeats
+    pub fn event_ids(&self) -> Vec<String> {
+        let mut event_ids: Vec<String> = self
+            .edges
+            .iter()
+            .flat_map(|edge| edge.events.iter().cloned())
+            .collect();
+        event_ids.sort();
+        event_ids.dedup();
+
+        event_ids
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the IDs of the events accepted by the given active states, sorted and without repeats
+    ///
+    /// Transitions of the states enclosing an active state are included, as they also apply to it.
+    pub fn accepted_events(&self, active_state_ids: &[&str]) -> Vec<String> {
+        let mut accepting_nodes = Vec::new();
+        for state_id in active_state_ids {
+            let mut node_idx = self.node_index(state_id);
+            while let Some(idx) = node_idx {
+                accepting_nodes.push(idx);
+                node_idx = self.nodes[idx].parent;
+            }
+        }
+
+        let mut accepted_events: Vec<String> = self
+            .edges
+            .iter()
+            .filter(|edge| accepting_nodes.contains(&edge.source))
+            .flat_map(|edge| edge.events.iter().cloned())
+            .collect();
+        accepted_events.sort();
+        accepted_events.dedup();
+
+        accepted_events
+    }
+
+    /// Returns the indices of the transitions taken for the given event from the given active states
+    ///
+    /// As in the chart itself, each active state takes the first transition accepting the event
+    /// from the nearest of itself and the states enclosing it. Conditions are not evaluated.
+    pub fn transitions_taken(&self, active_state_ids: &[String], event_id: &str) -> Vec<usize> {
+        let mut transitions_taken = Vec::new();
+        for state_id in active_state_ids {
+            let mut node_idx = self.node_index(state_id);
+            while let Some(idx) = node_idx {
+                let accepting_edge = self
+                    .edges
+                    .iter()
+                    .position(|edge| edge.source == idx && edge.accepts(event_id));
+
+                if let Some(edge_idx) = accepting_edge {
+                    // Parallel states may share the enclosing state that takes the transition
+                    if !transitions_taken.contains(&edge_idx) {
+                        transitions_taken.push(edge_idx);
+                    }
+                    break;
+                }
+
+                node_idx = self.nodes[idx].parent;
+            }
+        }
+        transitions_taken.sort_unstable();
+
+        transitions_taken
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Error for ChartLayoutError {}
+
+impl fmt::Display for ChartLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IoError(io_err) => {
+                write!(f, "IoError '{}' encountered", io_err)
+            }
+            Self::MalformedTag(line_num, reason) => {
+                write!(f, "Line {}: Malformed tag, {}", line_num, reason)
+            }
+            Self::MissingAttribute(line_num, attr_name, element_name) => {
+                write!(
+                    f,
+                    "Line {}: <{}> is missing its '{}' attribute",
+                    line_num, element_name, attr_name
+                )
+            }
+            Self::TransitionOutsideState(line_num) => {
+                write!(f, "Line {}: <transition> outside of any state", line_num)
+            }
+            Self::UnbalancedTag(line_num, element_name) => {
+                write!(f, "Line {}: Unbalanced <{}> tag", line_num, element_name)
+            }
+            Self::UnknownTarget(line_num, target_id) => {
+                write!(
+                    f,
+                    "Line {}: Transition targets unknown state '{}'",
+                    line_num, target_id
+                )
+            }
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the index of the '>' ending the tag at the start of the given string, skipping any
+/// within quoted attribute values
+fn find_tag_end(tag_str: &str) -> Option<usize> {
+    let mut quote = None;
+    for (idx, c) in tag_str.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open_quote), _) if c == open_quote => quote = None,
+            (None, '>') => return Some(idx),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Parses the 'name="value"' attributes of a tag
+fn parse_attributes(
+    attr_str: &str,
+    line_num: usize,
+) -> Result<Vec<(&str, &str)>, ChartLayoutError> {
+    let malformed = || {
+        ChartLayoutError::MalformedTag(line_num, format!("bad attributes '{}'", attr_str.trim()))
+    };
+
+    let mut attributes = Vec::new();
+    let mut rest = attr_str.trim_start();
+    while !rest.is_empty() {
+        let (name, after_name) = rest.split_once('=').ok_or_else(malformed)?;
+        let after_name = after_name.trim_start();
+
+        // Values may be quoted with either single or double quotes
+        let quote = after_name
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(malformed)?;
+        let (value, after_value) = after_name[1..].split_once(quote).ok_or_else(malformed)?;
+
+        attributes.push((name.trim(), value));
+        rest = after_value.trim_start();
+    }
+
+    Ok(attributes)
+}
+
+/// Splits a whitespace-separated list of IDs, as used by the 'event' and 'target' attributes
+fn split_ids(ids_str: Option<&str>) -> Vec<String> {
+    ids_str
+        .map(|ids_str| ids_str.split_whitespace().map(String::from).collect())
+        .unwrap_or_default()
+}
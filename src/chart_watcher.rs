@@ -0,0 +1,281 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : chart_watcher.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Watches the SCXML statechart file for changes while the game runs, so that
+    the chart may be re-parsed and swapped in without a restart.
+
+    The file's modification time is polled at a fixed interval. Alongside it, a
+    journal of the events accepted by the chart is kept, so that a freshly
+    parsed chart may be brought back to the same active states by replaying
+    them. Fact events are left out, as the current facts are sent to a reloaded
+    chart anyway. The journal is capped; once it overflows, the configuration
+    can no longer be restored, and a reloaded chart is only given the facts.
+
+    Also describes where the chart is loaded from: either a path given on the
+    command line, or the default chart alongside the executable. A copy of the
+    default chart is built in, for when that chart is missing or invalid.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    collections::VecDeque,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime},
+};
+
+use dd_statechart::event::Event;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Minimum real time between checks of the chart file's modification time
+const CHART_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Maximum number of accepted events kept for replay onto a reloaded chart, beyond which the
+/// journal is abandoned
+const CHART_JOURNAL_LENGTH: usize = 256;
+
+/// Location of the default chart, relative to the executable or working directory
+const DEFAULT_CHART_RELATIVE_PATH: &str = "res/default.scxml";
+
+/// Built-in copy of the default chart, used when the chart file is missing or invalid
+pub const EMBEDDED_CHART: &str = include_str!("../res/default.scxml");
+
+/// Stem of the filename under which the built-in chart is written out for parsing
+const EMBEDDED_CHART_FILE_STEM: &str = "sand_casting_default";
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Location of the statechart, and how to create the writer for its output
+pub struct ChartSource<W: Write> {
+    path: PathBuf,         // Path of the chart file
+    explicit: bool,        // Whether the path was given by the player, rather than the default
+    new_writer: fn() -> W, // Creates the writer for each (re)loaded chart
+}
+
+pub struct ChartWatcher {
+    path: PathBuf,                // Path of the watched chart file
+    modified: Option<SystemTime>, // Modification time of the chart file when last loaded
+    last_poll: Duration,          // Real time at which the file was last checked
+    journal: VecDeque<Event>,     // Events accepted by the chart since it was loaded
+    journal_overflowed: bool,     // Flag indicating the journal was too short to hold every event
+    reload_error: Option<String>, // Error from the last reload, if it failed
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl<W: Write> ChartSource<W> {
+    /// Fully-qualified constructor - uses the given path, or the default chart if none is given
+    pub fn new(path: Option<PathBuf>, new_writer: fn() -> W) -> Self {
+        let explicit = path.is_some();
+
+        Self {
+            path: path.unwrap_or_else(default_chart_path),
+            explicit,
+            new_writer,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if the path was given by the player, rather than being the default
+    pub fn is_explicit(&self) -> bool {
+        self.explicit
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Creates a writer for the output of a newly loaded chart
+    pub fn new_writer(&self) -> W {
+        (self.new_writer)()
+    }
+}
+
+impl ChartWatcher {
+    /// Fully-qualified constructor - watches the chart file at the given path for further changes
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            modified: modified_time(path.as_ref()),
+            last_poll: Duration::default(),
+            journal: VecDeque::with_capacity(CHART_JOURNAL_LENGTH),
+            journal_overflowed: false,
+            reload_error: None,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the events accepted by the chart, oldest first
+    ///
+    /// The journal is empty once it has overflowed, as it could only restore the wrong states.
+    pub fn journal(&self) -> &VecDeque<Event> {
+        &self.journal
+    }
+
+    /// Returns true if more events were accepted than the journal holds, so it cannot be replayed
+    pub fn journal_overflowed(&self) -> bool {
+        self.journal_overflowed
+    }
+
+    /// Returns the error from the last reload, if it failed
+    pub fn reload_error(&self) -> Option<&String> {
+        self.reload_error.as_ref()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Records an event accepted by the chart, abandoning the journal if it is full
+    pub fn record(&mut self, event: &Event) {
+        if self.journal_overflowed {
+            return;
+        }
+
+        // Replaying only the most recent events would not lead back to the same states
+        if self.journal.len() == CHART_JOURNAL_LENGTH {
+            self.journal.clear();
+            self.journal_overflowed = true;
+            return;
+        }
+
+        self.journal.push_back(event.clone());
+    }
+
+    /// Empties the journal, for a chart which has been loaded without replaying it
+    pub fn reset_journal(&mut self) {
+        self.journal.clear();
+        self.journal_overflowed = false;
+    }
+
+    pub fn set_reload_error(&mut self, reload_error: String) {
+        self.reload_error = Some(reload_error);
+    }
+
+    pub fn clear_reload_error(&mut self) {
+        self.reload_error = None;
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Checks the chart file if the poll interval has passed, returning true if it has changed
+    ///
+    /// A missing file is not considered a change, as editors often replace files in several steps.
+    pub fn poll(&mut self, real_time: Duration) -> bool {
+        if real_time.checked_sub(self.last_poll).unwrap_or_default() < CHART_POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = real_time;
+
+        match modified_time(&self.path) {
+            Some(modified) if self.modified != Some(modified) => {
+                self.modified = Some(modified);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the path of the default resource file at the given relative path
+///
+/// The file is looked for alongside the executable, so the game may be launched from anywhere,
+/// then in the working directory, which covers running from the source tree.
+pub fn default_resource_path(relative_path: &str) -> PathBuf {
+    let exe_relative_path = env::current_exe()
+        .ok()
+        .and_then(|exe_path| exe_path.parent().map(Path::to_path_buf))
+        .map(|exe_dir| exe_dir.join(relative_path));
+
+    match exe_relative_path {
+        Some(exe_relative_path) if exe_relative_path.exists() => exe_relative_path,
+        _ => PathBuf::from(relative_path),
+    }
+}
+
+/// Returns the path of the default chart
+fn default_chart_path() -> PathBuf {
+    default_resource_path(DEFAULT_CHART_RELATIVE_PATH)
+}
+
+/// Returns the path to which this process writes out the built-in chart for parsing
+///
+/// The filename includes the process ID, so that running instances do not share the file.
+pub fn embedded_chart_path() -> PathBuf {
+    env::temp_dir().join(format!(
+        "{}_{}.scxml",
+        EMBEDDED_CHART_FILE_STEM,
+        process::id()
+    ))
+}
+
+/// Writes the built-in chart out to a new file at the given path
+///
+/// An existing file is never written through, as it may be a link planted in its place.
+pub fn write_embedded_chart(path: &Path) -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(EMBEDDED_CHART.as_bytes())
+}
+
+/// Returns the modification time of the file at the given path, if it can be read
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,359 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : console.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines the in-game command console, an overlay taking typed game
+    commands and statechart event IDs without blocking the game loop.
+
+    The console only edits and recalls command lines. Submitted lines are
+    carried out by the game state, which prints the outcome back to the
+    console.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::collections::VecDeque;
+
+use ggez::{
+    graphics as ggez_gfx, input::keyboard as ggez_kb, mint as ggez_mint, Context as GgEzContext,
+};
+
+use crate::{game_assets::colors, game_command};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Number of output lines shown above the command line
+const CONSOLE_LINES_SHOWN: usize = 10;
+
+/// Number of submitted command lines kept for recall
+const CONSOLE_HISTORY_LENGTH: usize = 50;
+
+/// Vertical distance between consecutive console lines
+const CONSOLE_LINE_SPACING: f32 = 20.0;
+
+/// Space between the edge of the console and its text
+const CONSOLE_PADDING: f32 = 6.0;
+
+/// Background color of the console
+const CONSOLE_BACKGROUND_COLOR: ggez_gfx::Color = ggez_gfx::Color {
+    r: 0.000,
+    g: 0.000,
+    b: 0.000,
+    a: 0.850,
+};
+
+/// Prompt shown before the command line
+const CONSOLE_PROMPT: &str = "> ";
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LineKind {
+    Input,
+    Output,
+    Error,
+}
+
+#[derive(Default)]
+pub struct Console {
+    open: bool,                           // Flag indicating if the console is shown
+    skip_text: bool,                      // Flag indicating if text input is ignored until updated
+    input: String,                        // Command line being typed
+    history: VecDeque<String>,            // Submitted command lines, oldest first
+    history_idx: Option<usize>,           // Index of the history entry being recalled, if any
+    draft: String,                        // Command line typed before recalling the history
+    output: VecDeque<(String, LineKind)>, // Lines above the command line, oldest first
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Console {
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Shows the console and starts taking input
+    ///
+    /// The key opening the console also types a character, so text is ignored until the next update.
+    pub fn open(&mut self) {
+        self.open = true;
+        self.skip_text = true;
+    }
+
+    /// Hides the console, keeping any partially-typed command line
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Prints a line of output
+    pub fn print<S: Into<String>>(&mut self, line: S) {
+        self.push_line(line.into(), LineKind::Output);
+    }
+
+    /// Prints a line of output reporting an error
+    pub fn print_error<S: Into<String>>(&mut self, line: S) {
+        self.push_line(line.into(), LineKind::Error);
+    }
+
+    /// Handles a typed character, appending it to the command line
+    pub fn handle_text(&mut self, character: char) {
+        if self.open && !self.skip_text && !character.is_control() {
+            self.input.push(character);
+        }
+    }
+
+    /// Handles console key bindings, returning the command line if one was submitted
+    ///
+    /// Event IDs are offered when completing the command line, along with the command keywords.
+    pub fn handle_key(
+        &mut self,
+        keycode: ggez_kb::KeyCode,
+        event_ids: &[String],
+    ) -> Option<String> {
+        match keycode {
+            ggez_kb::KeyCode::Return | ggez_kb::KeyCode::NumpadEnter => return self.submit(),
+            ggez_kb::KeyCode::Escape => self.close(),
+            ggez_kb::KeyCode::Back => {
+                self.input.pop();
+            }
+            ggez_kb::KeyCode::Tab => self.complete(event_ids),
+
+            // History recall
+            ggez_kb::KeyCode::Up => self.recall_older(),
+            ggez_kb::KeyCode::Down => self.recall_newer(),
+
+            _ => {}
+        }
+
+        None
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Resumes taking text input after the key which opened the console
+    pub fn update(&mut self) {
+        self.skip_text = false;
+    }
+
+    pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        if !self.open {
+            return;
+        }
+
+        // Fill the bottom of the window, with room for the output and the command line
+        let (window_width, window_height) = ggez_gfx::size(ggez_ctx);
+        let console_height =
+            (CONSOLE_LINES_SHOWN + 1) as f32 * CONSOLE_LINE_SPACING + 2.0 * CONSOLE_PADDING;
+        let console_top = window_height - console_height;
+
+        let background_mesh = ggez_gfx::Mesh::new_rectangle(
+            ggez_ctx,
+            ggez_gfx::DrawMode::fill(),
+            ggez_gfx::Rect::new(0.0, console_top, window_width, console_height),
+            CONSOLE_BACKGROUND_COLOR,
+        )
+        .unwrap();
+        ggez_gfx::draw(ggez_ctx, &background_mesh, ggez_gfx::DrawParam::default()).unwrap();
+
+        // Draw the output, newest at the bottom
+        let output_top = console_top
+            + CONSOLE_PADDING
+            + (CONSOLE_LINES_SHOWN - self.output.len()) as f32 * CONSOLE_LINE_SPACING;
+        for (i, (line, line_kind)) in self.output.iter().enumerate() {
+            let line_color = match line_kind {
+                LineKind::Input => colors::GREY,
+                LineKind::Output => colors::WHITE,
+                LineKind::Error => colors::RED,
+            };
+            let line_pos = ggez_mint::Point2 {
+                x: CONSOLE_PADDING,
+                y: output_top + (i as f32 * CONSOLE_LINE_SPACING),
+            };
+
+            draw_line(line, line_pos, line_color, ggez_ctx);
+        }
+
+        // Draw the command line, with a cursor at its end
+        let input_pos = ggez_mint::Point2 {
+            x: CONSOLE_PADDING,
+            y: window_height - CONSOLE_PADDING - CONSOLE_LINE_SPACING,
+        };
+        draw_line(
+            &format!("{}{}_", CONSOLE_PROMPT, self.input),
+            input_pos,
+            colors::CYAN,
+            ggez_ctx,
+        );
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Adds a line to the output, dropping the oldest lines once there are more than can be shown
+    fn push_line(&mut self, line: String, line_kind: LineKind) {
+        self.output.push_back((line, line_kind));
+
+        while self.output.len() > CONSOLE_LINES_SHOWN {
+            self.output.pop_front();
+        }
+    }
+
+    /// Takes the command line, echoing it and adding it to the history
+    fn submit(&mut self) -> Option<String> {
+        let command_str = self.input.trim().to_string();
+        self.input.clear();
+        self.draft.clear();
+        self.history_idx = None;
+
+        if command_str.is_empty() {
+            return None;
+        }
+
+        self.push_line(
+            format!("{}{}", CONSOLE_PROMPT, command_str),
+            LineKind::Input,
+        );
+
+        // Don't fill the history with repeats of the same command
+        if self.history.back() != Some(&command_str) {
+            self.history.push_back(command_str.clone());
+            if self.history.len() > CONSOLE_HISTORY_LENGTH {
+                self.history.pop_front();
+            }
+        }
+
+        Some(command_str)
+    }
+
+    /// Replaces the command line with the previous history entry, keeping what was typed
+    fn recall_older(&mut self) {
+        let recalled_idx = match self.history_idx {
+            Some(idx) => idx.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.input.clone();
+                self.history.len() - 1
+            }
+        };
+
+        self.history_idx = Some(recalled_idx);
+        self.input = self.history[recalled_idx].clone();
+    }
+
+    /// Replaces the command line with the next history entry, or what was typed before recalling
+    fn recall_newer(&mut self) {
+        match self.history_idx {
+            Some(idx) if idx + 1 < self.history.len() => {
+                self.history_idx = Some(idx + 1);
+                self.input = self.history[idx + 1].clone();
+            }
+            Some(_idx) => {
+                self.history_idx = None;
+                self.input = std::mem::take(&mut self.draft);
+            }
+            None => {}
+        }
+    }
+
+    /// Completes the last word of the command line from the command keywords and event IDs
+    ///
+    /// A word with several completions is extended as far as they agree, and the completions are
+    /// listed.
+    fn complete(&mut self, event_ids: &[String]) {
+        let input = self.input.clone();
+        let word_start = input
+            .rfind(char::is_whitespace)
+            .map_or(0, |space_idx| space_idx + 1);
+        let (leading_words, word) = input.split_at(word_start);
+
+        // The first word may be a command or a bare event ID, later words only after 'event'
+        let candidates: Vec<&str> = match leading_words.split_whitespace().collect::<Vec<_>>()[..] {
+            [] => game_command::COMMAND_KEYWORDS
+                .iter()
+                .copied()
+                .chain(event_ids.iter().map(String::as_str))
+                .collect(),
+            ["event"] => event_ids.iter().map(String::as_str).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut completions: Vec<&str> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+        completions.sort_unstable();
+        completions.dedup();
+
+        let completed_word = match completions.as_slice() {
+            [] => return,
+            [completion] => format!("{} ", completion),
+            [first, rest @ ..] => {
+                let common_len = rest.iter().fold(first.len(), |common_len, completion| {
+                    first
+                        .char_indices()
+                        .zip(completion.chars())
+                        .take_while(|((_idx, a), b)| a == b)
+                        .last()
+                        .map_or(0, |((idx, a), _b)| idx + a.len_utf8())
+                        .min(common_len)
+                });
+                self.print(completions.join("  "));
+
+                String::from(&first[..common_len])
+            }
+        };
+
+        self.input = format!("{}{}", leading_words, completed_word);
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+fn draw_line(
+    line: &str,
+    line_pos: ggez_mint::Point2<f32>,
+    line_color: ggez_gfx::Color,
+    ggez_ctx: &mut GgEzContext,
+) {
+    let line_text =
+        ggez_gfx::Text::new((line, ggez_gfx::Font::default(), crate::DEFAULT_TEXT_SIZE));
+    ggez_gfx::draw(ggez_ctx, &line_text, (line_pos, 0.0, line_color)).unwrap();
+}
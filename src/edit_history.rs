@@ -0,0 +1,206 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : edit_history.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides a capped undo/redo history of reversible commands.
+
+    The history is generic over both the command type and the target it is
+    applied to, so it carries no dependency on ggez and can be exercised
+    without a window.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{collections::VecDeque, fmt};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct History<C> {
+    undo_stack: VecDeque<C>, // Executed commands, oldest first
+    redo_stack: Vec<C>,      // Undone commands, most recently undone last
+    capacity: usize,         // Maximum number of commands retained for undo
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Declarations
+///////////////////////////////////////////////////////////////////////////////
+
+/// A reversible operation on a target of type T
+pub trait Command<T> {
+    /// Implementor-defined type indicating an error
+    type Error;
+
+    /// Applies the command to the target
+    fn execute(&mut self, target: &mut T) -> Result<(), Self::Error>;
+
+    /// Reverts a previous execution of the command on the target
+    fn undo(&mut self, target: &mut T) -> Result<(), Self::Error>;
+
+    /// Returns true if executing the command would leave the target unchanged
+    fn is_noop(&self) -> bool {
+        false
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl<C> History<C> {
+    /// Creates an empty history retaining at most `capacity` undoable commands
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::with_capacity(capacity),
+            redo_stack: Vec::new(),
+            capacity,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Returns the undoable commands, oldest first
+    pub fn undo_entries(&self) -> impl Iterator<Item = &C> {
+        self.undo_stack.iter()
+    }
+
+    /// Returns the redoable commands, next-to-redo first
+    pub fn redo_entries(&self) -> impl Iterator<Item = &C> {
+        self.redo_stack.iter().rev()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Executes the command and records it, discarding any redoable commands
+    ///
+    /// A command that fails to execute, or that would do nothing, is not recorded.
+    pub fn execute<T>(&mut self, mut command: C, target: &mut T) -> Result<(), C::Error>
+    where
+        C: Command<T>,
+    {
+        if command.is_noop() {
+            return Ok(());
+        }
+
+        command.execute(target)?;
+
+        self.redo_stack.clear();
+        self.push_undo(command);
+
+        Ok(())
+    }
+
+    /// Undoes the most recent command, returning false if there was nothing to undo
+    pub fn undo<T>(&mut self, target: &mut T) -> Result<bool, C::Error>
+    where
+        C: Command<T>,
+    {
+        let mut command = match self.undo_stack.pop_back() {
+            Some(command) => command,
+            None => return Ok(false),
+        };
+
+        // Put the command back where it was if it cannot be undone
+        if let Err(e) = command.undo(target) {
+            self.undo_stack.push_back(command);
+            return Err(e);
+        }
+
+        self.redo_stack.push(command);
+        Ok(true)
+    }
+
+    /// Redoes the most recently undone command, returning false if there was nothing to redo
+    pub fn redo<T>(&mut self, target: &mut T) -> Result<bool, C::Error>
+    where
+        C: Command<T>,
+    {
+        let mut command = match self.redo_stack.pop() {
+            Some(command) => command,
+            None => return Ok(false),
+        };
+
+        // Put the command back where it was if it cannot be redone
+        if let Err(e) = command.execute(target) {
+            self.redo_stack.push(command);
+            return Err(e);
+        }
+
+        self.push_undo(command);
+        Ok(true)
+    }
+
+    /// Discards all recorded commands
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Returns a description of each entry for display in a history panel, oldest first
+    ///
+    /// The boolean in each pair indicates whether the entry is currently applied.
+    pub fn describe(&self) -> Vec<(String, bool)>
+    where
+        C: fmt::Display,
+    {
+        self.undo_entries()
+            .map(|command| (command.to_string(), true))
+            .chain(
+                self.redo_entries()
+                    .map(|command| (command.to_string(), false)),
+            )
+            .collect()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    fn push_undo(&mut self, command: C) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // Drop the oldest command once the cap is reached
+        if self.undo_stack.len() >= self.capacity {
+            self.undo_stack.pop_front();
+        }
+
+        self.undo_stack.push_back(command);
+    }
+}
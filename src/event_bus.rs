@@ -0,0 +1,201 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : event_bus.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides an in-process bus for typed game events.
+
+    Anything holding a Publisher may publish events, which are queued until
+    the owner of the bus dispatches them, once per update. Dispatched events
+    are returned to the owner, which feeds them to the statechart, profiler,
+    logger and UI.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{fmt, sync::mpsc};
+
+use cast_iron::{coords, mechanics::resource::State};
+
+use mt_logger::{mt_log, Level};
+
+use crate::{
+    game_managers::{
+        entity::{EntityId, InstanceKind},
+        weather_event::WeatherEvent,
+    },
+    map_file,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A domain event occurring in the game world
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    /// A new weather event began
+    WeatherChanged(WeatherEvent),
+    /// A resource changed from the first state to the second
+    ResourceStateChanged(EntityId, State, State),
+    /// An actor moved from the first position to the second
+    ActorMoved(EntityId, coords::Position, coords::Position),
+    /// An actor cast the named ability
+    AbilityCast(EntityId, String),
+    /// An entity was added to the world at the given origin
+    EntitySpawned(EntityId, InstanceKind, coords::Position),
+    /// An entity was removed from the world
+    EntityRemoved(EntityId, InstanceKind),
+    /// A grid cell was selected by the player
+    CellSelected(coords::Position),
+}
+
+/// Handle used to publish events onto the bus
+#[derive(Clone)]
+pub struct Publisher {
+    event_tx: mpsc::Sender<GameEvent>,
+}
+
+pub struct EventBus {
+    event_tx: mpsc::Sender<GameEvent>, // Sender cloned into each publisher
+    event_rx: mpsc::Receiver<GameEvent>, // Queue of published events awaiting dispatch
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl GameEvent {
+    /// Returns the ID of the statechart event corresponding to this event
+    pub fn statechart_event_id(&self) -> &'static str {
+        match self {
+            Self::WeatherChanged(..) => "weather.changed",
+            Self::ResourceStateChanged(..) => "resource.state.changed",
+            Self::ActorMoved(..) => "actor.moved",
+            Self::AbilityCast(..) => "ability.cast",
+            Self::EntitySpawned(..) => "entity.spawned",
+            Self::EntityRemoved(..) => "entity.removed",
+            Self::CellSelected(..) => "cell.selected",
+        }
+    }
+
+    /// Returns the label under which this event is marked in profiler output
+    pub fn marker_label(&self) -> String {
+        self.statechart_event_id().replace('.', "_").to_uppercase()
+    }
+
+    /// Returns the labels marking the start and end of a stage of handling this event in profiler output
+    pub fn marker_span_labels(&self, stage: &str) -> (String, String) {
+        let stage_label = format!("{}_{}", self.marker_label(), stage.to_uppercase());
+
+        (
+            format!("{}_START", stage_label),
+            format!("{}_STOP", stage_label),
+        )
+    }
+
+    /// Returns true if the event is worth surfacing to the player as a toast
+    pub fn is_notable(&self) -> bool {
+        matches!(
+            self,
+            Self::WeatherChanged(..) | Self::ResourceStateChanged(..) | Self::AbilityCast(..)
+        )
+    }
+}
+
+impl Publisher {
+    /// Publishes an event, to be dispatched on the next update
+    pub fn publish(&self, event: GameEvent) {
+        if let Err(e) = self.event_tx.send(event) {
+            mt_log!(
+                Level::Warning,
+                "Event '{}' published after bus shut down",
+                e.0
+            );
+        }
+    }
+}
+
+impl EventBus {
+    /// Generic Constructor - creates a bus with no queued events
+    pub fn new() -> Self {
+        let (event_tx, event_rx) = mpsc::channel();
+
+        Self { event_tx, event_rx }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns a new handle for publishing events onto the bus
+    pub fn publisher(&self) -> Publisher {
+        Publisher {
+            event_tx: self.event_tx.clone(),
+        }
+    }
+
+    /// Publishes an event, to be dispatched on the next update
+    pub fn publish(&self, event: GameEvent) {
+        // The bus holds its own receiver, so sending cannot fail
+        self.event_tx.send(event).unwrap();
+    }
+
+    /// Drains all queued events, returning them in order
+    pub fn dispatch(&mut self) -> Vec<GameEvent> {
+        self.event_rx.try_iter().collect()
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for GameEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WeatherChanged(weather_event) => {
+                write!(f, "Weather changed to {}", weather_event)
+            }
+            Self::ResourceStateChanged(id, from, to) => write!(
+                f,
+                "Resource {} changed from {} to {}",
+                id,
+                map_file::state_to_str(*from),
+                map_file::state_to_str(*to)
+            ),
+            Self::ActorMoved(id, from, to) => {
+                write!(f, "Actor {} moved from {} to {}", id, from, to)
+            }
+            Self::AbilityCast(id, ability_name) => {
+                write!(f, "Actor {} cast {}", id, ability_name)
+            }
+            Self::EntitySpawned(id, kind, origin) => {
+                write!(f, "Spawned {} {} at {}", kind, id, origin)
+            }
+            Self::EntityRemoved(id, kind) => write!(f, "Removed {} {}", kind, id),
+            Self::CellSelected(position) => write!(f, "Selected cell {}", position),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : fixed_timestep.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides a fixed-timestep accumulator, which decouples the rate at which the
+    simulation is stepped from the rate at which frames are rendered.
+
+    Real time passed between frames is accumulated and spent in whole
+    simulation steps. The number of steps per frame is capped, so a slow frame
+    cannot snowball into ever more steps (the 'spiral of death'); time beyond
+    the cap is dropped. The time left over after the last step gives the
+    fraction by which rendering should interpolate towards the next step.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::time::Duration;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct FixedTimestep {
+    step: Duration,        // Real time covered by a single simulation step
+    max_steps: u32,        // Maximum number of steps taken in a single frame
+    accumulator: Duration, // Real time passed but not yet spent in steps
+    capped: bool,          // Whether the step cap was hit on the last frame
+    dropped: Duration,     // Total real time dropped by the step cap
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl FixedTimestep {
+    /// Fully-qualified constructor
+    pub fn new(rate: u32, max_steps: u32) -> Self {
+        Self {
+            step: step_for_rate(rate),
+            max_steps,
+            accumulator: Duration::default(),
+            capped: false,
+            dropped: Duration::default(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the real time covered by a single simulation step
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Returns the number of simulation steps per second
+    pub fn rate(&self) -> f64 {
+        1.0 / self.step.as_secs_f64()
+    }
+
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// Returns true if the step cap was hit on the last frame
+    pub fn capped(&self) -> bool {
+        self.capped
+    }
+
+    /// Returns the total real time dropped by the step cap
+    pub fn dropped(&self) -> Duration {
+        self.dropped
+    }
+
+    /// Returns the fraction of a step accumulated towards the next one, for render interpolation
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()) as f32
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Sets the number of simulation steps per second
+    pub fn set_rate(&mut self, rate: u32) {
+        self.step = step_for_rate(rate);
+        self.accumulator = Duration::default();
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Accumulates the real time passed since the last frame, returning the number of steps to take
+    pub fn accumulate(&mut self, frame_delta: Duration) -> u32 {
+        self.accumulator += frame_delta;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        // Drop whole steps beyond the cap, keeping the partial step so interpolation stays smooth
+        self.capped = self.accumulator >= self.step;
+        if self.capped {
+            let remainder =
+                Duration::from_nanos((self.accumulator.as_nanos() % self.step.as_nanos()) as u64);
+            self.dropped += self.accumulator - remainder;
+            self.accumulator = remainder;
+        }
+
+        steps
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(crate::DEFAULT_SIM_RATE, crate::MAX_SIM_STEPS_PER_FRAME)
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the length of a step at the given number of steps per second, treating zero as one
+fn step_for_rate(rate: u32) -> Duration {
+    Duration::from_secs_f64(1.0 / f64::from(rate.max(1)))
+}
@@ -0,0 +1,402 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : chart_overlay.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides an overlay drawing the statechart as a node graph, for authoring
+    and debugging charts.
+
+    States are laid out around a ring, with nested states on smaller rings
+    inside, and joined by arrows for each transition. Active states are
+    highlighted, and the transitions taken for the last event briefly flash.
+    Below the graph, the events accepted by the active states are listed.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{f32::consts::PI, time::Duration};
+
+use ggez::{graphics as ggez_gfx, mint as ggez_mint, timer as ggez_timer, Context as GgEzContext};
+
+use crate::{chart_layout::ChartLayout, game_assets::colors};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Position of the top-left corner of the overlay in the window
+const OVERLAY_POS: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 20.0, y: 120.0 };
+
+/// Width and height of the graph area of the overlay
+const OVERLAY_GRAPH_SIZE: f32 = 420.0;
+
+/// Height of the text area below the graph
+const OVERLAY_TEXT_HEIGHT: f32 = 60.0;
+
+/// Background color of the overlay
+const OVERLAY_BACKGROUND_COLOR: ggez_gfx::Color = ggez_gfx::Color {
+    r: 0.000,
+    g: 0.000,
+    b: 0.000,
+    a: 0.800,
+};
+
+/// Size of each state's box
+const NODE_WIDTH: f32 = 110.0;
+const NODE_HEIGHT: f32 = 30.0;
+
+/// Fraction by which each level of nesting shrinks the ring on which states are placed
+const NESTED_RING_SCALE: f32 = 0.55;
+
+/// Distance by which each transition is moved to its right, so opposing transitions stay apart
+const EDGE_OFFSET: f32 = 6.0;
+
+/// Size of the head of each transition's arrow
+const ARROW_HEAD_SIZE: f32 = 8.0;
+
+/// Line widths of transitions, normally and while flashing
+const EDGE_LINE_WIDTH: f32 = 1.5;
+const FLASH_LINE_WIDTH: f32 = 4.0;
+
+/// Time for which the transitions taken for an event flash
+const TRANSITION_FLASH_DURATION: Duration = Duration::from_millis(1500);
+
+/// Size of the text labelling transitions
+const EDGE_TEXT_SIZE: f32 = 12.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+pub struct ChartOverlay {
+    layout: ChartLayout,                       // States and transitions of the chart
+    node_centers: Vec<ggez_mint::Point2<f32>>, // Center of each state's box
+    last_event: Option<String>,                // ID of the last event accepted by the chart
+    last_transitions: Vec<usize>,              // Transitions taken for the last event
+    flash_pending: bool,                       // Whether the last event is yet to flash
+    flash_start: Duration,                     // Real time at which the last flash began
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ChartOverlay {
+    /// Fully-qualified constructor
+    pub fn new(layout: ChartLayout) -> Self {
+        let mut chart_overlay = Self::default();
+        chart_overlay.set_layout(layout);
+
+        chart_overlay
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn layout(&self) -> &ChartLayout {
+        &self.layout
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Replaces the chart shown, such as when it is reloaded
+    pub fn set_layout(&mut self, layout: ChartLayout) {
+        self.node_centers = ring_layout(&layout);
+        self.layout = layout;
+        self.last_transitions.clear();
+    }
+
+    /// Records an event accepted by the chart while the given states were active, to flash its transitions
+    pub fn record_event(&mut self, prev_active_state_ids: &[String], event_id: &str) {
+        self.last_transitions = self
+            .layout
+            .transitions_taken(prev_active_state_ids, event_id);
+        self.last_event = Some(String::from(event_id));
+        self.flash_pending = true;
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Starts the flash of the transitions taken for any event recorded since the last update
+    pub fn update(&mut self, ggez_ctx: &GgEzContext) {
+        if self.flash_pending {
+            self.flash_start = ggez_timer::time_since_start(ggez_ctx);
+            self.flash_pending = false;
+        }
+    }
+
+    pub fn draw(&self, active_state_ids: &[&str], ggez_ctx: &mut GgEzContext) {
+        // Brightness of flashing transitions, fading to nothing over the flash duration
+        let flash_elapsed = ggez_timer::time_since_start(ggez_ctx)
+            .checked_sub(self.flash_start)
+            .unwrap_or_default();
+        let flash_fraction =
+            1.0 - (flash_elapsed.as_secs_f32() / TRANSITION_FLASH_DURATION.as_secs_f32()).min(1.0);
+
+        // The background means the mesh is never empty
+        let mut overlay_mesh_builder = ggez_gfx::MeshBuilder::new();
+        let background_rect = ggez_gfx::Rect::new(
+            OVERLAY_POS.x,
+            OVERLAY_POS.y,
+            OVERLAY_GRAPH_SIZE,
+            OVERLAY_GRAPH_SIZE + OVERLAY_TEXT_HEIGHT,
+        );
+        overlay_mesh_builder
+            .rectangle(
+                ggez_gfx::DrawMode::fill(),
+                background_rect,
+                OVERLAY_BACKGROUND_COLOR,
+            )
+            .unwrap()
+            .rectangle(
+                ggez_gfx::DrawMode::stroke(1.0),
+                background_rect,
+                colors::GREY,
+            )
+            .unwrap();
+
+        // Draw transitions first, so that states are drawn over their ends
+        let mut edge_labels = Vec::new();
+        for (edge_idx, edge) in self.layout.edges().iter().enumerate() {
+            // Transitions without a target, or back to their source, are only listed as accepted events
+            let target = match edge.target() {
+                Some(target) if target != edge.source() => target,
+                _ => continue,
+            };
+
+            let flashing = flash_fraction > 0.0 && self.last_transitions.contains(&edge_idx);
+            let (edge_color, edge_width) = if flashing {
+                let mut flash_color = colors::YELLOW;
+                flash_color.a = 0.3 + 0.7 * flash_fraction;
+                (flash_color, FLASH_LINE_WIDTH)
+            } else {
+                (colors::GREY, EDGE_LINE_WIDTH)
+            };
+
+            let (start, end, direction) =
+                edge_endpoints(self.node_centers[edge.source()], self.node_centers[target]);
+            overlay_mesh_builder
+                .line(&[start, end], edge_width, edge_color)
+                .unwrap()
+                .polygon(
+                    ggez_gfx::DrawMode::fill(),
+                    &arrow_head(end, direction),
+                    edge_color,
+                )
+                .unwrap();
+
+            edge_labels.push((
+                edge.events().join(" "),
+                ggez_mint::Point2 {
+                    x: (start.x + end.x) / 2.0,
+                    y: (start.y + end.y) / 2.0,
+                },
+                if flashing {
+                    colors::YELLOW
+                } else {
+                    colors::GREY
+                },
+            ));
+        }
+
+        // Draw each state's box, filled if active
+        for (node, center) in self.layout.nodes().iter().zip(&self.node_centers) {
+            let node_rect = ggez_gfx::Rect::new(
+                center.x - NODE_WIDTH / 2.0,
+                center.y - NODE_HEIGHT / 2.0,
+                NODE_WIDTH,
+                NODE_HEIGHT,
+            );
+            let fill_color = if active_state_ids.contains(&node.id()) {
+                colors::GREEN
+            } else {
+                colors::DARKGREY
+            };
+
+            overlay_mesh_builder
+                .rectangle(ggez_gfx::DrawMode::fill(), node_rect, fill_color)
+                .unwrap()
+                .rectangle(ggez_gfx::DrawMode::stroke(1.0), node_rect, colors::WHITE)
+                .unwrap();
+        }
+
+        let overlay_mesh = overlay_mesh_builder.build(ggez_ctx).unwrap();
+        ggez_gfx::draw(ggez_ctx, &overlay_mesh, ggez_gfx::DrawParam::default()).unwrap();
+
+        // Label transitions with their events, and states with their IDs
+        for (label, midpoint, label_color) in edge_labels {
+            let label_text =
+                ggez_gfx::Text::new((label, ggez_gfx::Font::default(), EDGE_TEXT_SIZE));
+            let label_pos = ggez_mint::Point2 {
+                x: midpoint.x - label_text.width(ggez_ctx) / 2.0,
+                y: midpoint.y - label_text.height(ggez_ctx) / 2.0,
+            };
+            ggez_gfx::draw(ggez_ctx, &label_text, (label_pos, 0.0, label_color)).unwrap();
+        }
+        for (node, center) in self.layout.nodes().iter().zip(&self.node_centers) {
+            let id_text = ggez_gfx::Text::new((
+                node.id(),
+                ggez_gfx::Font::default(),
+                crate::DEFAULT_TEXT_SIZE,
+            ));
+            let id_pos = ggez_mint::Point2 {
+                x: center.x - id_text.width(ggez_ctx) / 2.0,
+                y: center.y - id_text.height(ggez_ctx) / 2.0,
+            };
+            ggez_gfx::draw(ggez_ctx, &id_text, (id_pos, 0.0, colors::WHITE)).unwrap();
+        }
+
+        // List the accepted events, and the last event accepted
+        let accepted_events = self.layout.accepted_events(active_state_ids);
+        let info_text = ggez_gfx::Text::new((
+            format!(
+                "Accepts: {}\nLast Event: {}",
+                if accepted_events.is_empty() {
+                    String::from("(none)")
+                } else {
+                    accepted_events.join(", ")
+                },
+                self.last_event.as_deref().unwrap_or("(none)")
+            ),
+            ggez_gfx::Font::default(),
+            crate::DEFAULT_TEXT_SIZE,
+        ));
+        let info_pos = ggez_mint::Point2 {
+            x: OVERLAY_POS.x + 10.0,
+            y: OVERLAY_POS.y + OVERLAY_GRAPH_SIZE + 10.0,
+        };
+        ggez_gfx::draw(ggez_ctx, &info_text, (info_pos, 0.0, colors::WHITE)).unwrap();
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Places the states of the chart around rings, one per level of nesting, in document order
+fn ring_layout(layout: &ChartLayout) -> Vec<ggez_mint::Point2<f32>> {
+    let graph_center = ggez_mint::Point2 {
+        x: OVERLAY_POS.x + OVERLAY_GRAPH_SIZE / 2.0,
+        y: OVERLAY_POS.y + OVERLAY_GRAPH_SIZE / 2.0,
+    };
+    let outer_radius = (OVERLAY_GRAPH_SIZE - NODE_WIDTH) / 2.0 - 10.0;
+
+    let max_depth = layout
+        .nodes()
+        .iter()
+        .map(|node| node.depth())
+        .max()
+        .unwrap_or_default();
+    let mut node_centers = vec![graph_center; layout.nodes().len()];
+    for depth in 0..=max_depth {
+        let ring: Vec<usize> = (0..layout.nodes().len())
+            .filter(|idx| layout.nodes()[*idx].depth() == depth)
+            .collect();
+        let ring_radius = outer_radius * NESTED_RING_SCALE.powi(depth as i32);
+
+        // A lone state at the top level sits in the middle, rather than at the top of the ring
+        if ring.len() == 1 && depth == 0 {
+            continue;
+        }
+
+        for (ring_idx, node_idx) in ring.iter().enumerate() {
+            let angle = 2.0 * PI * ring_idx as f32 / ring.len() as f32 - PI / 2.0;
+            node_centers[*node_idx] = ggez_mint::Point2 {
+                x: graph_center.x + ring_radius * angle.cos(),
+                y: graph_center.y + ring_radius * angle.sin(),
+            };
+        }
+    }
+
+    node_centers
+}
+
+/// Returns the start and end of a transition between the given state centers, with its direction
+///
+/// The transition runs between the edges of the states' boxes, moved to its right so that the
+/// transitions in either direction between two states do not overlap.
+fn edge_endpoints(
+    source_center: ggez_mint::Point2<f32>,
+    target_center: ggez_mint::Point2<f32>,
+) -> (
+    ggez_mint::Point2<f32>,
+    ggez_mint::Point2<f32>,
+    ggez_mint::Vector2<f32>,
+) {
+    let (dx, dy) = (
+        target_center.x - source_center.x,
+        target_center.y - source_center.y,
+    );
+    let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+    let direction = ggez_mint::Vector2 {
+        x: dx / length,
+        y: dy / length,
+    };
+    let offset = ggez_mint::Vector2 {
+        x: -direction.y * EDGE_OFFSET,
+        y: direction.x * EDGE_OFFSET,
+    };
+
+    // Distance from a box's center to its edge along the direction of the transition
+    let box_extent = (NODE_WIDTH / 2.0 / direction.x.abs().max(f32::EPSILON))
+        .min(NODE_HEIGHT / 2.0 / direction.y.abs().max(f32::EPSILON));
+
+    let start = ggez_mint::Point2 {
+        x: source_center.x + direction.x * box_extent + offset.x,
+        y: source_center.y + direction.y * box_extent + offset.y,
+    };
+    let end = ggez_mint::Point2 {
+        x: target_center.x - direction.x * box_extent + offset.x,
+        y: target_center.y - direction.y * box_extent + offset.y,
+    };
+
+    (start, end, direction)
+}
+
+/// Returns the points of an arrow head with its tip at the given point, facing the given direction
+fn arrow_head(
+    tip: ggez_mint::Point2<f32>,
+    direction: ggez_mint::Vector2<f32>,
+) -> [ggez_mint::Point2<f32>; 3] {
+    let back = ggez_mint::Point2 {
+        x: tip.x - direction.x * ARROW_HEAD_SIZE,
+        y: tip.y - direction.y * ARROW_HEAD_SIZE,
+    };
+    let half_width = ARROW_HEAD_SIZE / 2.0;
+
+    [
+        tip,
+        ggez_mint::Point2 {
+            x: back.x - direction.y * half_width,
+            y: back.y + direction.x * half_width,
+        },
+        ggez_mint::Point2 {
+            x: back.x + direction.y * half_width,
+            y: back.y - direction.x * half_width,
+        },
+    ]
+}
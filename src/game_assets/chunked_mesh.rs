@@ -0,0 +1,185 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_assets/chunked_mesh.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides a mesh split into fixed-size chunks of items, where only chunks
+    whose items have changed are rebuilt.
+
+    Changes are only marked when they happen; the rebuild itself is deferred
+    until update() so that any number of changes within a frame cost at most
+    one rebuild per affected chunk.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use ggez::{graphics as ggez_gfx, Context as GgEzContext};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default number of items in each chunk of the mesh
+pub const DEFAULT_CHUNK_SIZE: usize = 64;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct ChunkedMesh {
+    chunk_size: usize,                   // Number of items in each chunk
+    chunks: Vec<Option<ggez_gfx::Mesh>>, // Mesh for each chunk, None if the chunk has no geometry
+    dirty: Vec<bool>,                    // Flag for each chunk indicating it must be rebuilt
+    item_count: usize,                   // Number of items as of the last change
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ChunkedMesh {
+    /// Creates an empty mesh with the given number of items per chunk
+    pub fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            chunks: Vec::new(),
+            dirty: Vec::new(),
+            item_count: 0,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if any chunk must be rebuilt before it is drawn
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.iter().any(|dirty| *dirty)
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Marks the chunk containing the given item as requiring a rebuild
+    ///
+    /// The item count is updated to the given total number of items.
+    pub fn mark_dirty(&mut self, item_index: usize, item_count: usize) {
+        self.resize(item_count);
+
+        let chunk_index = item_index / self.chunk_size;
+        if let Some(dirty) = self.dirty.get_mut(chunk_index) {
+            *dirty = true;
+        }
+    }
+
+    /// Marks the chunk containing the given item and all following chunks as requiring a rebuild
+    ///
+    /// Used when items have shifted, such as after a removal from the middle of a list.
+    pub fn mark_dirty_from(&mut self, item_index: usize, item_count: usize) {
+        self.resize(item_count);
+
+        let chunk_index = item_index / self.chunk_size;
+        for dirty in self.dirty.iter_mut().skip(chunk_index) {
+            *dirty = true;
+        }
+    }
+
+    /// Marks every chunk as requiring a rebuild
+    pub fn mark_all_dirty(&mut self, item_count: usize) {
+        self.mark_dirty_from(0, item_count);
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Rebuilds all dirty chunks, returning the number of chunks rebuilt
+    ///
+    /// The given function is called once for each item in a dirty chunk, and must add that item
+    /// to the given mesh builder.
+    pub fn update<F>(&mut self, ggez_ctx: &mut GgEzContext, mut add_item: F) -> usize
+    where
+        F: FnMut(usize, &mut ggez_gfx::MeshBuilder, &mut GgEzContext),
+    {
+        let mut rebuilt_count = 0;
+
+        for chunk_index in 0..self.chunks.len() {
+            if !self.dirty[chunk_index] {
+                continue;
+            }
+
+            let first_item = chunk_index * self.chunk_size;
+            let last_item = (first_item + self.chunk_size).min(self.item_count);
+
+            let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+            for item_index in first_item..last_item {
+                add_item(item_index, &mut mesh_builder, ggez_ctx);
+            }
+
+            // Building fails if no geometry was added, which simply leaves nothing to draw
+            self.chunks[chunk_index] = mesh_builder.build(ggez_ctx).ok();
+            self.dirty[chunk_index] = false;
+            rebuilt_count += 1;
+        }
+
+        rebuilt_count
+    }
+
+    /// Draws all chunks in the given context
+    pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        for mesh in self.chunks.iter().flatten() {
+            ggez_gfx::draw(ggez_ctx, mesh, ggez_gfx::DrawParam::default()).unwrap();
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Resizes the chunk list to fit the given number of items
+    fn resize(&mut self, item_count: usize) {
+        let chunk_count = (item_count + self.chunk_size - 1) / self.chunk_size;
+
+        self.chunks.resize_with(chunk_count, || None);
+        self.dirty.resize(chunk_count, true);
+        self.item_count = item_count;
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for ChunkedMesh {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHUNK_SIZE)
+    }
+}
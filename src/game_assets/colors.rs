@@ -133,6 +133,12 @@ pub const HILITE_STD: ggez_gfx::Color = ggez_gfx::Color {
     a: 0.333,
 };
 
+/* Influence */
+/// Alpha of a composited influence cell per unit of combined influence weight
+pub const INFLUENCE_ALPHA_PER_WEIGHT: f32 = 0.200;
+/// Maximum alpha of a composited influence cell
+pub const INFLUENCE_ALPHA_MAX: f32 = 0.600;
+
 ///////////////////////////////////////////////////////////////////////////////
 //  Utility Functions
 ///////////////////////////////////////////////////////////////////////////////
@@ -168,3 +174,38 @@ pub fn from_resource(res: &Resource) -> ggez_gfx::Color {
 
     res_color
 }
+
+/// Blends the colors of the given elements, weighted by their influence
+///
+/// Opacity grows with the combined weight, so overlapping influence reads as stronger.
+pub fn from_contributions(contributions: &[(Element, f32)]) -> ggez_gfx::Color {
+    let total_weight: f32 = contributions.iter().map(|(_elem, weight)| weight).sum();
+
+    let mut blended_color = blend_elements(contributions);
+    blended_color.a = (total_weight * INFLUENCE_ALPHA_PER_WEIGHT).min(INFLUENCE_ALPHA_MAX);
+
+    blended_color
+}
+
+/// Blends the colors of the given elements in proportion to their weights, into an opaque color
+///
+/// Returns TRANSPARENT if the weights sum to zero.
+pub fn blend_elements(weighted_elements: &[(Element, f32)]) -> ggez_gfx::Color {
+    let total_weight: f32 = weighted_elements.iter().map(|(_elem, weight)| weight).sum();
+    if total_weight <= 0.0 {
+        return TRANSPARENT;
+    }
+
+    let mut blended_color = TRANSPARENT;
+    for (elem, weight) in weighted_elements {
+        let elem_color = from_element(*elem);
+        let share = weight / total_weight;
+
+        blended_color.r += elem_color.r * share;
+        blended_color.g += elem_color.g * share;
+        blended_color.b += elem_color.b * share;
+    }
+    blended_color.a = 1.0;
+
+    blended_color
+}
@@ -0,0 +1,219 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_assets/hex_batch.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides instanced rendering of hex cells.
+
+    A single white hex template is drawn once per cell using hardware
+    instancing, with each instance supplying its own position and colour.
+    Fills and outlines are separate templates so that each may be coloured
+    independently.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::fmt;
+
+use ggez::{graphics as ggez_gfx, mint as ggez_mint, Context as GgEzContext};
+
+use crate::game_assets::{colors, hex_grid_cell::HexGridCell};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Method used to draw hex-based geometry
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderMode {
+    Mesh,      // Each cell is tessellated into a combined mesh
+    Instanced, // One hex template is drawn many times via instancing
+}
+
+#[derive(Default)]
+pub struct HexBatch {
+    fill_params: Vec<ggez_gfx::DrawParam>, // Per-instance position and colour of each fill
+    outline_params: Vec<ggez_gfx::DrawParam>, // Per-instance position and colour of each outline
+    batches: Option<(ggez_gfx::MeshBatch, ggez_gfx::MeshBatch)>, // Fill and outline batches, built on first draw
+    params_dirty: bool, // Flag indicating the params must be sent to the batches
+    dirty: bool,        // Flag indicating the owner must rebuild the hex cells
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl RenderMode {
+    /// Returns the other render mode
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Mesh => Self::Instanced,
+            Self::Instanced => Self::Mesh,
+        }
+    }
+}
+
+impl HexBatch {
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the number of hex cells in the batch
+    pub fn len(&self) -> usize {
+        self.fill_params.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fill_params.is_empty()
+    }
+
+    /// Returns true if the owner of the batch must rebuild its hex cells
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Marks the batch as requiring a rebuild of its hex cells
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Removes all hex cells from the batch, leaving it clean for a rebuild
+    pub fn clear(&mut self) {
+        self.fill_params.clear();
+        self.outline_params.clear();
+        self.params_dirty = true;
+        self.dirty = false;
+    }
+
+    /// Adds a hex cell to the batch, returning its index
+    pub fn add_hex(
+        &mut self,
+        hex_cell: &HexGridCell,
+        fill_color: ggez_gfx::Color,
+        outline_color: ggez_gfx::Color,
+    ) -> usize {
+        self.fill_params
+            .push(Self::instance_param(hex_cell, fill_color));
+        self.outline_params
+            .push(Self::instance_param(hex_cell, outline_color));
+        self.params_dirty = true;
+
+        self.len() - 1
+    }
+
+    /// Changes the fill colour of the hex cell at the given index
+    pub fn set_fill_color(&mut self, index: usize, fill_color: ggez_gfx::Color) {
+        if let Some(param) = self.fill_params.get_mut(index) {
+            param.color = fill_color;
+            self.params_dirty = true;
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    pub fn draw(&mut self, ggez_ctx: &mut GgEzContext) {
+        // Nothing to draw if the batch is empty
+        if self.is_empty() {
+            return;
+        }
+
+        // Build the templates on first use
+        let (fill_batch, outline_batch) = self
+            .batches
+            .get_or_insert_with(|| Self::build_batches(ggez_ctx));
+
+        // Send any changed params to the batches, which upload them on their next draw
+        if self.params_dirty {
+            fill_batch.clear();
+            outline_batch.clear();
+            for (fill_param, outline_param) in self.fill_params.iter().zip(&self.outline_params) {
+                fill_batch.add(*fill_param);
+                outline_batch.add(*outline_param);
+            }
+
+            self.params_dirty = false;
+        }
+
+        fill_batch
+            .draw(ggez_ctx, ggez_gfx::DrawParam::default())
+            .unwrap();
+        outline_batch
+            .draw(ggez_ctx, ggez_gfx::DrawParam::default())
+            .unwrap();
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Helper Functions  *
+    \*  *  *  *  *  *  *  */
+
+    /// Builds the fill and outline batches from white hex templates
+    fn build_batches(ggez_ctx: &mut GgEzContext) -> (ggez_gfx::MeshBatch, ggez_gfx::MeshBatch) {
+        // Templates are centered on the origin, and translated to each instance's position
+        let template_hex = HexGridCell::new_from_pixel_coords(
+            ggez_mint::Point2 { x: 0.0, y: 0.0 },
+            crate::HEX_RADIUS_VERTEX,
+        );
+
+        let fill_template = ggez_gfx::Mesh::new_polygon(
+            ggez_ctx,
+            ggez_gfx::DrawMode::fill(),
+            &template_hex.vertices(),
+            colors::WHITE,
+        )
+        .unwrap();
+        let outline_template = ggez_gfx::Mesh::new_polygon(
+            ggez_ctx,
+            ggez_gfx::DrawMode::stroke(crate::DEFAULT_LINE_WIDTH),
+            &template_hex.vertices(),
+            colors::WHITE,
+        )
+        .unwrap();
+
+        (
+            ggez_gfx::MeshBatch::new(fill_template).unwrap(),
+            ggez_gfx::MeshBatch::new(outline_template).unwrap(),
+        )
+    }
+
+    fn instance_param(hex_cell: &HexGridCell, color: ggez_gfx::Color) -> ggez_gfx::DrawParam {
+        ggez_gfx::DrawParam::default()
+            .dest(hex_cell.center())
+            .color(color)
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Mesh => write!(f, "Mesh"),
+            Self::Instanced => write!(f, "Instanced"),
+        }
+    }
+}
@@ -106,6 +106,20 @@ impl HexGridCell {
         Self::new_from_pixel_coords(pixel_center, radius)
     }
 
+    /// Hex-coords-based constructor for a grid centered on the given pixel coords
+    ///
+    /// Does not require a GGEZ context, so may be used for headless rendering.
+    pub fn new_from_hex_coords_about(
+        center: &coords::Position,
+        radius: f32,
+        grid_center: ggez_mint::Point2<f32>,
+    ) -> Self {
+        // Convert to pixel coords and use the pixel coords constructor
+        let pixel_center = Self::hex_to_pixel_coords_about(center, grid_center);
+
+        Self::new_from_pixel_coords(pixel_center, radius)
+    }
+
 
     /*  *  *  *  *  *  *  *\
      *  Accessor Methods  *
@@ -161,82 +175,17 @@ impl HexGridCell {
         }
     }
 
-    //OPT: *DESIGN* This should be a static helper function
-    pub fn add_radials_to_mesh(
-        &self,
-        fill_color: ggez_gfx::Color,
-        outline_color: ggez_gfx::Color,
-        radius: usize,
-        has_gradient: bool,
-        mesh_builder: &mut ggez_gfx::MeshBuilder,
-    ) {
-        // In order to reliably construct radiating hexes:
-        // 1. Take the origin hex cell
-        // 2. Rotate its vertices by PI/6
-        // 3. Inflate the hex based on current radial level
-        // 4. Construct the appropriate number of hexes to fit along the lines between those vertices
-
-        // Copy original fill color to allow for transparentization across levels
-        let mut cur_fill_color = fill_color;
-
-        // Get origin hex vertices
-        let origin_centerpoint = self.center();
-        let mut radial_vertices = [ggez_mint::Point2 { x: 0.0, y: 0.0 }; 6];
-
-        for level in 0..radius {
-            // Create an iterator starting at the East vertex and going COUNTER-CLOCKWISE as required by GGEZ draw calls
-            let direction_provider: hex_directions::Provider<hex_directions::Vertex> =
-                hex_directions::Provider::new(hex_directions::Vertex::East);
-            for (i, vertex) in direction_provider.enumerate() {
-                let theta: f32 = vertex.into();
-                // Add PI/6 to theta to rotate the standard flat-up hex to point-up
-                // This is important as all radial groups of hexes will effectively be large point-up hexes
-                let adj_theta = theta + PI / 6.0;
-
-                radial_vertices[i].x =
-                    origin_centerpoint.x + (crate::HEX_RADIUS_SIDE * 2.0 * adj_theta.cos());
-                radial_vertices[i].y =
-                    origin_centerpoint.y - (crate::HEX_RADIUS_SIDE * 2.0 * adj_theta.sin());
-
-                // Inflate the vertices based on level
-                radial_vertices[i].x +=
-                    (crate::HEX_RADIUS_SIDE * 2.0 * adj_theta.cos()) * level as f32;
-                radial_vertices[i].y -=
-                    (crate::HEX_RADIUS_SIDE * 2.0 * adj_theta.sin()) * level as f32;
-
-                // Create hex cells at each vertex
-                let vert_hex = HexGridCell::new_from_pixel_coords(
-                    radial_vertices[i],
-                    crate::HEX_RADIUS_VERTEX,
-                );
-                vert_hex.add_to_mesh(cur_fill_color, outline_color, mesh_builder);
-
-                // Create interstitial hex(es) if level requires
-                for j in 0..level {
-                    let inter_hex_theta = adj_theta + 4.0 * PI / 6.0;
-
-                    let inter_hex_center = ggez_mint::Point2 {
-                        x: radial_vertices[i].x
-                            + (crate::HEX_RADIUS_SIDE * 2.0 * inter_hex_theta.cos())
-                                * (j + 1) as f32,
-                        y: radial_vertices[i].y
-                            - (crate::HEX_RADIUS_SIDE * 2.0 * inter_hex_theta.sin())
-                                * (j + 1) as f32,
-                    };
-
-                    let inter_hex = HexGridCell::new_from_pixel_coords(
-                        inter_hex_center,
-                        crate::HEX_RADIUS_VERTEX,
-                    );
-                    inter_hex.add_to_mesh(cur_fill_color, outline_color, mesh_builder);
-                }
-            }
-
-            if has_gradient && cur_fill_color.a > MIN_ALPHA_VAL {
-                // Transparentize color such that we get to mostly transparent at the furthest level, but not fully transparent
-                cur_fill_color.a -= 1.0 / radius as f32;
+    /// Returns the alpha value of a resource's influence at the given distance (in cells) from its origin
+    pub fn radial_alpha(base_alpha: f32, radius: usize, level: usize) -> f32 {
+        // Transparentize such that we get to mostly transparent at the furthest level, but not fully transparent
+        let mut alpha = base_alpha;
+        for _ in 1..level {
+            if alpha > MIN_ALPHA_VAL {
+                alpha -= 1.0 / radius as f32;
             }
         }
+
+        alpha.max(0.0)
     }
 
 
@@ -282,6 +231,14 @@ impl HexGridCell {
             y: window_y / 2.0,
         };
 
+        Self::hex_to_pixel_coords_about(hex_pos, window_center)
+    }
+
+    /// Converts hex coords to pixel coords for a grid centered on the given pixel coords
+    pub fn hex_to_pixel_coords_about(
+        hex_pos: &coords::Position,
+        grid_center: ggez_mint::Point2<f32>,
+    ) -> ggez_mint::Point2<f32> {
         // Calculate x, y offsets
         let x_offset = hex_pos.x() as f32 * crate::HEX_RADIUS_VERTEX * 3.0 / 2.0;
         let y_offset = (-hex_pos.y() as f32
@@ -292,12 +249,75 @@ impl HexGridCell {
                 * (crate::HEX_RADIUS_SIDE * 2.0));
 
         ggez_mint::Point2 {
-            x: window_center.x + x_offset,
-            y: window_center.y + y_offset,
+            x: grid_center.x + x_offset,
+            y: grid_center.y + y_offset,
         }
     }
 
 
+    /// Returns the distance (in cells) between two hex positions
+    pub fn hex_distance(start: &coords::Position, end: &coords::Position) -> usize {
+        let dx = (start.x() - end.x()).abs();
+        let dy = (start.y() - end.y()).abs();
+        let dz = (start.z() - end.z()).abs();
+
+        ((dx + dy + dz) / 2) as usize
+    }
+
+    /// Returns all valid hex positions within the given radius (in cells) of the center, inclusive
+    pub fn hex_range(
+        center: &coords::Position,
+        radius: usize,
+        ci_ctx: &CastIronContext,
+    ) -> Vec<coords::Position> {
+        let radius = radius as i32;
+        let mut positions = Vec::new();
+
+        // Walk the cube-coordinate deltas that satisfy dx + dy + dz == 0
+        for dx in -radius..=radius {
+            for dy in (-radius).max(-dx - radius)..=radius.min(-dx + radius) {
+                let dz = -dx - dy;
+
+                if let Ok(position) =
+                    coords::Position::new(center.x() + dx, center.y() + dy, center.z() + dz, ci_ctx)
+                {
+                    positions.push(position);
+                }
+            }
+        }
+
+        positions
+    }
+
+    /// Returns the contiguous line of hex positions from start to end, inclusive of both
+    pub fn hex_line(
+        start: &coords::Position,
+        end: &coords::Position,
+        ci_ctx: &CastIronContext,
+    ) -> Vec<coords::Position> {
+        let distance = Self::hex_distance(start, end);
+        if distance == 0 {
+            return vec![*start];
+        }
+
+        // Linearly interpolate between the cube coordinates, rounding to the nearest hex at each step
+        // NOTE: A small nudge is applied to the start so that points landing exactly on an edge round consistently
+        let mut line = Vec::with_capacity(distance + 1);
+        for step in 0..=distance {
+            let t = step as f32 / distance as f32;
+            let x = lerp(start.x() as f32 + 1e-6, end.x() as f32, t);
+            let y = lerp(start.y() as f32 + 1e-6, end.y() as f32, t);
+            let z = lerp(start.z() as f32 - 2e-6, end.z() as f32, t);
+
+            if let Ok(position) = Self::hex_round(x, y, z, ci_ctx) {
+                line.push(position);
+            }
+        }
+
+        line
+    }
+
+
     /*  *  *  *  *  *  *  *\
      *  Helper Methods    *
     \*  *  *  *  *  *  *  */
@@ -328,7 +348,8 @@ impl HexGridCell {
             .unwrap();
     }
 
-    fn add_highlight_to_mesh(&self, mesh_builder: &mut ggez_gfx::MeshBuilder) {
+    /// Adds the highlight overlay of a hex cell to the given Mesh
+    pub fn add_highlight_to_mesh(&self, mesh_builder: &mut ggez_gfx::MeshBuilder) {
         mesh_builder
             .polygon(
                 ggez_gfx::DrawMode::fill(),
@@ -343,7 +364,7 @@ impl HexGridCell {
      *  Helper Functions  *
     \*  *  *  *  *  *  *  */
 
-    fn hex_round(
+    pub fn hex_round(
         x: f32,
         y: f32,
         z: f32,
@@ -379,3 +400,12 @@ impl HexGridCell {
         }
     }
 }
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+fn lerp(start: f32, end: f32, t: f32) -> f32 {
+    start + (end - start) * t
+}
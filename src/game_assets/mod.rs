@@ -19,5 +19,10 @@ Purpose:
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
+pub mod chart_overlay;
+pub mod chunked_mesh;
 pub mod colors;
+pub mod hex_batch;
 pub mod hex_grid_cell;
+pub mod toasts;
+pub mod weather_particles;
@@ -0,0 +1,101 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_assets/toasts.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides short-lived on-screen notifications.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{collections::VecDeque, time::Duration};
+
+use ggez::{graphics as ggez_gfx, mint as ggez_mint, timer as ggez_timer, Context as GgEzContext};
+
+use crate::game_assets::colors;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Position of the oldest toast in the window
+const TOAST_POS: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 60.0 };
+
+/// Vertical distance between consecutive toasts
+const TOAST_SPACING: f32 = 20.0;
+
+/// Time for which each toast is shown
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Maximum number of toasts shown at once, older toasts are dropped first
+const TOAST_MAX_SHOWN: usize = 5;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+pub struct ToastQueue {
+    toasts: VecDeque<(String, Duration)>, // Message of each toast and the game time at which it expires
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl ToastQueue {
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Shows a new toast with the given message
+    pub fn push(&mut self, message: String, ggez_ctx: &GgEzContext) {
+        let expiry = ggez_timer::time_since_start(ggez_ctx) + TOAST_DURATION;
+        self.toasts.push_back((message, expiry));
+
+        while self.toasts.len() > TOAST_MAX_SHOWN {
+            self.toasts.pop_front();
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Drops any toasts which have expired
+    pub fn update(&mut self, ggez_ctx: &GgEzContext) {
+        let now = ggez_timer::time_since_start(ggez_ctx);
+        self.toasts.retain(|(_message, expiry)| *expiry > now);
+    }
+
+    pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        for (i, (message, _expiry)) in self.toasts.iter().enumerate() {
+            let toast_text = ggez_gfx::Text::new((
+                message.as_str(),
+                ggez_gfx::Font::default(),
+                crate::DEFAULT_TEXT_SIZE,
+            ));
+            let toast_pos = ggez_mint::Point2 {
+                x: TOAST_POS.x,
+                y: TOAST_POS.y + (i as f32 * TOAST_SPACING),
+            };
+
+            ggez_gfx::draw(ggez_ctx, &toast_text, (toast_pos, 0.0, colors::WHITE)).unwrap();
+        }
+    }
+}
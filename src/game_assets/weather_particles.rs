@@ -0,0 +1,380 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_assets/weather_particles.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides element-specific particle effects for weather over the world.
+
+    Particles are spawned over every cell covered by weather, at a rate which
+    scales with the cell's intensity. Water falls as rain streaks, fire rises
+    as drifting embers, electric weather throws sparks, ice falls as snow,
+    wind blows in gusts, and light, dark and earth weather float as motes.
+    The total number of live particles is capped by a particle budget.
+
+    Particles are drawn interpolated between their positions on the last two
+    simulation steps, so their motion stays smooth at any render rate.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::collections::HashMap;
+
+use cast_iron::{coords, element::Element};
+
+use ggez::{graphics as ggez_gfx, mint as ggez_mint, Context as GgEzContext};
+
+use rand::Rng;
+
+use crate::{
+    game_assets::{colors, hex_grid_cell::HexGridCell},
+    game_managers::weather_fronts::CellWeather,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Particles spawned per cell per second, at the maximum weather intensity
+const PARTICLE_SPAWN_RATE: f64 = 3.0;
+
+/// Size of point-like particles (e.g. embers, snow, motes), in pixels
+const PARTICLE_POINT_SIZE: f32 = 3.0;
+
+/// Time covered by the trail of streak-like particles (e.g. rain, sparks), in seconds
+const PARTICLE_STREAK_TIME: f32 = 0.05;
+
+/// Width of streak-like particles, in pixels
+const PARTICLE_STREAK_WIDTH: f32 = 1.5;
+
+/// Frequency and amplitude of the side-to-side sway of falling snow
+const SNOW_SWAY_FREQUENCY: f32 = 3.0;
+const SNOW_SWAY_AMPLITUDE: f32 = 20.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Appearance and motion of a particle
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Style {
+    Streak, // Fast, straight-moving line
+    Point,  // Small, slow-moving square
+    Sway,   // Small square swaying side-to-side as it moves
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Particle {
+    style: Style,                      // Appearance and motion of the particle
+    color: ggez_gfx::Color,            // Color of the particle at the start of its life
+    pos: ggez_mint::Point2<f32>,       // Current position of the particle, in pixels
+    prev_pos: ggez_mint::Point2<f32>,  // Position of the particle before the last update, in pixels
+    velocity: ggez_mint::Vector2<f32>, // Distance travelled per second, in pixels
+    age: f32,                          // Time since the particle spawned, in seconds
+    lifetime: f32,                     // Time for which the particle lives, in seconds
+}
+
+pub struct WeatherParticles {
+    particles: Vec<Particle>,     // Live particles, oldest first
+    budget: usize,                // Maximum number of live particles
+    budget_reached: bool,         // Whether spawning was capped by the budget on the last update
+    mesh: Option<ggez_gfx::Mesh>, // Mesh of all live particles, None if there are none
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Particle {
+    /// Creates a particle of the given element, somewhere within the hex cell centered on the given point
+    fn rand<R: Rng>(element: Element, cell_center: ggez_mint::Point2<f32>, rng: &mut R) -> Self {
+        let pos = ggez_mint::Point2 {
+            x: cell_center.x + rng.gen_range(-crate::HEX_RADIUS_SIDE, crate::HEX_RADIUS_SIDE),
+            y: cell_center.y + rng.gen_range(-crate::HEX_RADIUS_SIDE, crate::HEX_RADIUS_SIDE),
+        };
+
+        let (style, color, velocity, lifetime) = match element {
+            // Rain streaks falling steeply
+            Element::Water => (
+                Style::Streak,
+                colors::from_element(element),
+                ggez_mint::Vector2 {
+                    x: rng.gen_range(-40.0, -20.0),
+                    y: rng.gen_range(350.0, 450.0),
+                },
+                rng.gen_range(0.2, 0.4),
+            ),
+            // Embers drifting upward
+            Element::Fire => (
+                Style::Point,
+                if rng.gen_bool(0.5) {
+                    colors::ORANGE
+                } else {
+                    colors::RED
+                },
+                ggez_mint::Vector2 {
+                    x: rng.gen_range(-15.0, 15.0),
+                    y: rng.gen_range(-40.0, -20.0),
+                },
+                rng.gen_range(0.8, 1.6),
+            ),
+            // Short-lived sparks flying in any direction
+            Element::Electric => {
+                let angle: f32 = rng.gen_range(0.0, std::f32::consts::PI * 2.0);
+                let speed: f32 = rng.gen_range(150.0, 300.0);
+                (
+                    Style::Streak,
+                    colors::from_element(element),
+                    ggez_mint::Vector2 {
+                        x: angle.cos() * speed,
+                        y: angle.sin() * speed,
+                    },
+                    rng.gen_range(0.05, 0.15),
+                )
+            }
+            // Snow drifting slowly down
+            Element::Ice => (
+                Style::Sway,
+                colors::WHITE,
+                ggez_mint::Vector2 {
+                    x: 0.0,
+                    y: rng.gen_range(20.0, 40.0),
+                },
+                rng.gen_range(1.0, 2.0),
+            ),
+            // Gusts blowing across the grid
+            Element::Wind => (
+                Style::Streak,
+                colors::from_element(element),
+                ggez_mint::Vector2 {
+                    x: rng.gen_range(250.0, 350.0),
+                    y: rng.gen_range(-10.0, 10.0),
+                },
+                rng.gen_range(0.1, 0.2),
+            ),
+            // Motes floating aimlessly
+            Element::Light | Element::Dark | Element::Earth | Element::Unset => (
+                Style::Point,
+                // Unset weather has no color of its own, so is shown as plain motes
+                match element {
+                    Element::Unset => colors::WHITE,
+                    _ => colors::from_element(element),
+                },
+                ggez_mint::Vector2 {
+                    x: rng.gen_range(-10.0, 10.0),
+                    y: rng.gen_range(-10.0, 10.0),
+                },
+                rng.gen_range(1.5, 3.0),
+            ),
+        };
+
+        Self {
+            style,
+            color,
+            pos,
+            prev_pos: pos,
+            velocity,
+            age: 0.0,
+            lifetime,
+        }
+    }
+
+    /// Advances the particle by the given time step
+    fn advance(&mut self, time_step: f32) {
+        self.prev_pos = self.pos;
+        self.age += time_step;
+        self.pos.x += self.velocity.x * time_step;
+        self.pos.y += self.velocity.y * time_step;
+    }
+
+    /// Returns true if the particle has not yet outlived its lifetime
+    fn alive(&self) -> bool {
+        self.age < self.lifetime
+    }
+
+    /// Returns the color of the particle, fading out over its lifetime
+    fn current_color(&self) -> ggez_gfx::Color {
+        let mut color = self.color;
+        color.a *= 1.0 - (self.age / self.lifetime).min(1.0);
+
+        color
+    }
+
+    /// Returns the position of the particle the given fraction of the way on from its last position
+    fn interpolated_pos(&self, alpha: f32) -> ggez_mint::Point2<f32> {
+        ggez_mint::Point2 {
+            x: self.prev_pos.x + (self.pos.x - self.prev_pos.x) * alpha,
+            y: self.prev_pos.y + (self.pos.y - self.prev_pos.y) * alpha,
+        }
+    }
+
+    fn add_to_mesh(&self, alpha: f32, mesh_builder: &mut ggez_gfx::MeshBuilder) {
+        let color = self.current_color();
+        let pos = self.interpolated_pos(alpha);
+
+        match self.style {
+            Style::Streak => {
+                let tail = ggez_mint::Point2 {
+                    x: pos.x - self.velocity.x * PARTICLE_STREAK_TIME,
+                    y: pos.y - self.velocity.y * PARTICLE_STREAK_TIME,
+                };
+                mesh_builder
+                    .line(&[tail, pos], PARTICLE_STREAK_WIDTH, color)
+                    .unwrap();
+            }
+            Style::Point | Style::Sway => {
+                let sway = match self.style {
+                    Style::Sway => (self.age * SNOW_SWAY_FREQUENCY).sin() * SNOW_SWAY_AMPLITUDE,
+                    _ => 0.0,
+                };
+                mesh_builder
+                    .rectangle(
+                        ggez_gfx::DrawMode::fill(),
+                        ggez_gfx::Rect::new(
+                            pos.x + sway,
+                            pos.y,
+                            PARTICLE_POINT_SIZE,
+                            PARTICLE_POINT_SIZE,
+                        ),
+                        color,
+                    )
+                    .unwrap();
+            }
+        }
+    }
+}
+
+impl WeatherParticles {
+    /// Fully-qualified constructor
+    pub fn new(budget: usize) -> Self {
+        Self {
+            particles: Vec::with_capacity(budget),
+            budget,
+            budget_reached: false,
+            mesh: None,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the number of live particles
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Returns true if spawning was capped by the budget on the last update
+    pub fn budget_reached(&self) -> bool {
+        self.budget_reached
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Sets the maximum number of live particles, dropping the oldest particles if over budget
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+
+        if self.particles.len() > budget {
+            let excess = self.particles.len() - budget;
+            self.particles.drain(..excess);
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Advances all particles by the given time step, and spawns new ones over the given weather cells
+    pub fn update(
+        &mut self,
+        cells: &HashMap<coords::Position, CellWeather>,
+        max_intensity: f64,
+        time_step: f32,
+        ggez_ctx: &GgEzContext,
+    ) {
+        for particle in self.particles.iter_mut() {
+            particle.advance(time_step);
+        }
+        self.particles.retain(Particle::alive);
+
+        let mut rng = rand::thread_rng();
+        self.budget_reached = false;
+        for (position, cell_weather) in cells {
+            // Density scales with intensity; spawn with the probability of one arriving this step
+            let spawn_chance = (PARTICLE_SPAWN_RATE
+                * (cell_weather.intensity() / max_intensity)
+                * f64::from(time_step))
+            .min(1.0);
+            if !rng.gen_bool(spawn_chance.max(0.0)) {
+                continue;
+            }
+
+            if self.particles.len() >= self.budget {
+                self.budget_reached = true;
+                break;
+            }
+
+            let cell_center = HexGridCell::hex_to_pixel_coords(position, ggez_ctx);
+            self.particles.push(Particle::rand(
+                cell_weather.element(),
+                cell_center,
+                &mut rng,
+            ));
+        }
+    }
+
+    /// Rebuilds the mesh of all live particles, the given fraction of a step beyond the last update
+    pub fn update_geometry(&mut self, alpha: f32, ggez_ctx: &mut GgEzContext) {
+        let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+        for particle in &self.particles {
+            particle.add_to_mesh(alpha, &mut mesh_builder);
+        }
+
+        // Building fails if no particles were added, which simply leaves nothing to draw
+        self.mesh = mesh_builder.build(ggez_ctx).ok();
+    }
+
+    pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        if let Some(mesh) = &self.mesh {
+            ggez_gfx::draw(ggez_ctx, mesh, ggez_gfx::DrawParam::default()).unwrap();
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for WeatherParticles {
+    fn default() -> Self {
+        Self::new(crate::DEFAULT_MAX_WEATHER_PARTICLES)
+    }
+}
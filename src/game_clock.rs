@@ -0,0 +1,188 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_clock.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides the game clock, which measures the game time read by all of the
+    simulation, independently of the real time passed.
+
+    The clock may be paused, run faster or slower than real time, and stepped
+    forward a single update at a time while paused.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{fmt, time::Duration};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Speed multipliers the clock may run at, slowest first
+const CLOCK_SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// Index of real-time speed within the clock speeds
+const REAL_TIME_SPEED_INDEX: usize = 2;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct GameClock {
+    time: Duration,      // Game time elapsed
+    speed_index: usize,  // Index of the current speed multiplier within the clock speeds
+    paused: bool,        // Whether game time is stopped
+    pending_steps: u32,  // Single updates requested while paused, not yet taken
+    last_step: Duration, // Game time advanced by the last tick
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl GameClock {
+    /// Creates a running clock at real-time speed, starting from zero
+    pub fn new() -> Self {
+        Self {
+            time: Duration::default(),
+            speed_index: REAL_TIME_SPEED_INDEX,
+            paused: false,
+            pending_steps: 0,
+            last_step: Duration::default(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the game time elapsed
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Returns the multiplier applied to real time while running
+    pub fn speed(&self) -> f64 {
+        CLOCK_SPEEDS[self.speed_index]
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns the game time advanced by the last tick
+    pub fn last_step(&self) -> Duration {
+        self.last_step
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Pauses a running clock, or resumes a paused one, returning true if now paused
+    pub fn toggle_pause(&mut self) -> bool {
+        self.paused = !self.paused;
+        self.pending_steps = 0;
+
+        self.paused
+    }
+
+    /// Moves up to the next faster speed, returning the new speed
+    pub fn speed_up(&mut self) -> f64 {
+        self.speed_index = (self.speed_index + 1).min(CLOCK_SPEEDS.len() - 1);
+
+        self.speed()
+    }
+
+    /// Moves down to the next slower speed, returning the new speed
+    pub fn slow_down(&mut self) -> f64 {
+        self.speed_index = self.speed_index.saturating_sub(1);
+
+        self.speed()
+    }
+
+    /// Sets the speed to the closest available multiplier to the one given, returning the new speed
+    pub fn set_speed(&mut self, speed: f64) -> f64 {
+        self.speed_index = (0..CLOCK_SPEEDS.len())
+            .min_by(|a, b| {
+                (CLOCK_SPEEDS[*a] - speed)
+                    .abs()
+                    .partial_cmp(&(CLOCK_SPEEDS[*b] - speed).abs())
+                    .unwrap()
+            })
+            .unwrap();
+
+        self.speed()
+    }
+
+    /// Requests a single update's worth of game time while paused, returning false if running
+    pub fn step(&mut self) -> bool {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+
+        self.paused
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Advances the clock for an update covering the given real time, returning the game time advanced
+    ///
+    /// A running clock advances by the real time scaled by its speed. A paused clock only advances
+    /// if a step was requested, in which case it advances by exactly one update of real time.
+    pub fn tick(&mut self, real_step: Duration) -> Duration {
+        self.last_step = if !self.paused {
+            real_step.mul_f64(self.speed())
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            real_step
+        } else {
+            Duration::default()
+        };
+        self.time += self.last_step;
+
+        self.last_step
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for GameClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}s @ {}x{}",
+            self.time.as_secs_f64(),
+            self.speed(),
+            if self.paused { " (Paused)" } else { "" }
+        )
+    }
+}
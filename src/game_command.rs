@@ -0,0 +1,226 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_command.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines the textual game commands which may be issued to the game from
+    outside of its own input handling, such as by the statechart or the
+    in-game console.
+
+    Commands are a keyword followed by whitespace-separated arguments:
+        spawn actor <x> <y> <z>
+        weather <element> <peak intensity> <duration in seconds>
+        background <red> <green> <blue> | background default
+        event <event ID>
+        seed [<seed>]
+        save
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{error::Error, fmt, time::Duration};
+
+use cast_iron::{context::Context as CastIronContext, coords, element::Element};
+
+use ggez::graphics as ggez_gfx;
+
+use crate::map_file;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Keyword of each command, for completion
+pub const COMMAND_KEYWORDS: [&str; 6] = ["spawn", "weather", "background", "event", "seed", "save"];
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A command to be carried out by the game
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameCommand {
+    /// Spawns an actor at the given position
+    SpawnActor(coords::Position),
+    /// Schedules a weather event of the given element, peak intensity and duration to occur next
+    Weather(Element, f64, Duration),
+    /// Sets the background color, or restores the default if None
+    Background(Option<ggez_gfx::Color>),
+    /// Sends the event with the given ID to the statechart
+    Event(String),
+    /// Replaces the world with one generated from the given seed
+    Seed(u64),
+    /// Saves the world to the map file
+    Save,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GameCommandError {
+    EmptyCommand,
+    UnknownCommand(String),
+    MalformedCommand(String, String),
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl GameCommand {
+    /// Parses a command string
+    ///
+    /// A seed command without a seed is given a random one, so that the world it generates can be
+    /// reproduced.
+    pub fn parse(command_str: &str, ci_ctx: &CastIronContext) -> Result<Self, GameCommandError> {
+        let fields: Vec<&str> = command_str.split_whitespace().collect();
+
+        match fields.as_slice() {
+            [] => Err(GameCommandError::EmptyCommand),
+            ["spawn", "actor", x, y, z] => {
+                let position = parse_position(&[x, y, z], ci_ctx).ok_or_else(|| {
+                    malformed("spawn", format!("invalid position '{} {} {}'", x, y, z))
+                })?;
+
+                Ok(Self::SpawnActor(position))
+            }
+            ["spawn", ..] => Err(malformed("spawn", "usage is 'spawn actor <x> <y> <z>'")),
+            ["weather", element_str, peak_str, duration_str] => {
+                let element = map_file::element_from_str(element_str).ok_or_else(|| {
+                    malformed("weather", format!("unknown element '{}'", element_str))
+                })?;
+                let peak_intensity = peak_str
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|peak| *peak >= 0.0 && *peak <= ci_ctx.max_weather_intensity())
+                    .ok_or_else(|| {
+                        malformed("weather", format!("invalid peak intensity '{}'", peak_str))
+                    })?;
+                let duration = duration_str
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|secs| secs.is_finite() && *secs > 0.0)
+                    .map(Duration::from_secs_f64)
+                    .ok_or_else(|| {
+                        malformed("weather", format!("invalid duration '{}'", duration_str))
+                    })?;
+
+                Ok(Self::Weather(element, peak_intensity, duration))
+            }
+            ["weather", ..] => Err(malformed(
+                "weather",
+                "usage is 'weather <element> <peak intensity> <duration>'",
+            )),
+            ["background", "default"] => Ok(Self::Background(None)),
+            ["background", red, green, blue] => {
+                let mut components = [0; 3];
+                for (component, field) in components.iter_mut().zip(&[red, green, blue]) {
+                    *component = field.parse::<u8>().map_err(|_e| {
+                        malformed("background", format!("invalid color component '{}'", field))
+                    })?;
+                }
+
+                Ok(Self::Background(Some(ggez_gfx::Color::from_rgb(
+                    components[0],
+                    components[1],
+                    components[2],
+                ))))
+            }
+            ["background", ..] => Err(malformed(
+                "background",
+                "usage is 'background <red> <green> <blue>' or 'background default'",
+            )),
+            ["event", event_id] => Ok(Self::Event(String::from(*event_id))),
+            ["event", ..] => Err(malformed("event", "usage is 'event <event ID>'")),
+            ["seed"] => Ok(Self::Seed(rand::random())),
+            ["seed", seed_str] => {
+                let seed = seed_str
+                    .parse::<u64>()
+                    .map_err(|_e| malformed("seed", format!("invalid seed '{}'", seed_str)))?;
+
+                Ok(Self::Seed(seed))
+            }
+            ["seed", ..] => Err(malformed("seed", "usage is 'seed [<seed>]'")),
+            ["save"] => Ok(Self::Save),
+            ["save", ..] => Err(malformed("save", "usage is 'save'")),
+            [keyword, ..] => Err(GameCommandError::UnknownCommand(String::from(*keyword))),
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Error for GameCommandError {}
+
+impl fmt::Display for GameCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SpawnActor(position) => write!(f, "spawn actor at {}", position),
+            Self::Weather(element, peak_intensity, duration) => write!(
+                f,
+                "weather {} peaking at {:.0} for {:.1}s",
+                map_file::element_to_str(*element),
+                peak_intensity,
+                duration.as_secs_f64()
+            ),
+            Self::Background(Some(color)) => {
+                let (red, green, blue) = color.to_rgb();
+                write!(f, "background {} {} {}", red, green, blue)
+            }
+            Self::Background(None) => write!(f, "background default"),
+            Self::Event(event_id) => write!(f, "event {}", event_id),
+            Self::Seed(seed) => write!(f, "seed {}", seed),
+            Self::Save => write!(f, "save"),
+        }
+    }
+}
+
+impl fmt::Display for GameCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::EmptyCommand => {
+                write!(f, "Empty command")
+            }
+            Self::UnknownCommand(keyword) => {
+                write!(f, "Unknown command '{}'", keyword)
+            }
+            Self::MalformedCommand(keyword, reason) => {
+                write!(f, "Malformed '{}' command, {}", keyword, reason)
+            }
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+fn malformed<S: Into<String>>(keyword: &str, reason: S) -> GameCommandError {
+    GameCommandError::MalformedCommand(String::from(keyword), reason.into())
+}
+
+/// Parses a position from its three components, returning None if any are invalid
+fn parse_position(fields: &[&&str; 3], ci_ctx: &CastIronContext) -> Option<coords::Position> {
+    let mut components = [0; 3];
+    for (component, field) in components.iter_mut().zip(fields) {
+        *component = field.parse::<i32>().ok()?;
+    }
+
+    coords::Position::new(components[0], components[1], components[2], ci_ctx).ok()
+}
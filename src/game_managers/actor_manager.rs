@@ -20,15 +20,18 @@ Purpose:
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
-use cast_iron::{actor::Actor, Plottable};
+use cast_iron::{actor::Actor, coords, Plottable};
 
-use ggez::{graphics as ggez_gfx, mint as ggez_mint, Context as GgEzContext};
+use ggez::{graphics as ggez_gfx, Context as GgEzContext};
 
 use mt_logger::{mt_log, Level};
 
 use crate::{
-    game_assets::{colors, hex_grid_cell::HexGridCell},
-    game_managers::DrawableMechanic,
+    game_assets::{chunked_mesh::ChunkedMesh, colors, hex_grid_cell::HexGridCell},
+    game_managers::{
+        entity::{EntityId, InstanceKind},
+        DrawableMechanic,
+    },
 };
 
 
@@ -36,9 +39,11 @@ use crate::{
 //  Data Structures
 ///////////////////////////////////////////////////////////////////////////////
 
+#[derive(Default)]
 pub struct ActorManager {
     actors: Vec<Actor>,
-    actor_mesh: ggez_gfx::Mesh,
+    actor_ids: Vec<EntityId>, // ID of each actor, in the same order
+    actor_mesh: ChunkedMesh,
 }
 
 #[derive(Debug)]
@@ -51,19 +56,11 @@ pub struct ActorError;
 
 impl ActorManager {
     /// Generic Constructor - creates an empty instance
-    pub fn new(ggez_ctx: &mut GgEzContext) -> Self {
+    pub fn new() -> Self {
         ActorManager {
             actors: Vec::new(),
-            actor_mesh: ggez_gfx::Mesh::new_line(
-                ggez_ctx,
-                &[
-                    ggez_mint::Point2 { x: 0.0, y: 0.0 },
-                    ggez_mint::Point2 { x: 10.0, y: 10.0 },
-                ],
-                crate::DEFAULT_LINE_WIDTH,
-                crate::DEFAULT_LINE_COLOR,
-            )
-            .unwrap(),
+            actor_ids: Vec::new(),
+            actor_mesh: ChunkedMesh::default(),
         }
     }
 }
@@ -76,28 +73,58 @@ impl ActorManager {
 impl DrawableMechanic for ActorManager {
     type Instance = Actor;
     type ErrorType = ActorError;
+    const KIND: InstanceKind = InstanceKind::Actor;
 
     fn instances(&self) -> &Vec<Self::Instance> {
         &self.actors
     }
 
-    fn push_instance(&mut self, instance: Self::Instance) {
+    fn instance_ids(&self) -> &Vec<EntityId> {
+        &self.actor_ids
+    }
+
+    fn push_instance(&mut self, instance: Self::Instance, id: EntityId) {
         mt_log!(
             Level::Debug,
-            "Adding actor: {} at {} to mesh.",
+            "Adding actor {}: {} at {} to mesh.",
+            id,
             instance.name(),
             instance.origin()
         );
 
         self.actors.push(instance);
+        self.actor_ids.push(id);
+    }
+
+    fn insert_instance_by_index(&mut self, index: usize, instance: Self::Instance, id: EntityId) {
+        self.actors.insert(index, instance);
+        self.actor_ids.insert(index, id);
+    }
+
+    fn remove_instance_by_index(&mut self, index: usize) -> Self::Instance {
+        self.actor_ids.remove(index);
+        self.actors.remove(index)
+    }
+
+    fn clear_instances(&mut self) {
+        self.actors.clear();
+        self.actor_ids.clear();
+    }
+
+    fn relocated_instance(
+        mut instance: Self::Instance,
+        positions: Vec<coords::Position>,
+    ) -> Self::Instance {
+        instance.set_origin(positions[0]);
+        instance
     }
 
-    fn mesh(&self) -> &ggez_gfx::Mesh {
+    fn mesh(&self) -> &ChunkedMesh {
         &self.actor_mesh
     }
 
-    fn set_mesh(&mut self, mesh: ggez_gfx::Mesh) {
-        self.actor_mesh = mesh;
+    fn mesh_mut(&mut self) -> &mut ChunkedMesh {
+        &mut self.actor_mesh
     }
 
     fn add_instance_to_mesh_builder(
@@ -0,0 +1,291 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/influence.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Computes the area of influence of a resource in hex space, and the
+    composite of all influence areas.
+
+    An influence area is the set of grid cells reachable from the resource's
+    origin within its radius. Cells outside the world grid are never
+    included, and blocked cells (e.g. obstacles) neither receive influence
+    nor let it pass through them.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use cast_iron::{
+    context::Context as CastIronContext,
+    coords,
+    element::{Element, Elemental},
+    hex_directions,
+    mechanics::resource::{Resource, State},
+    Plottable,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Method used to display overlapping influence areas
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InfluenceView {
+    PerResource, // Each resource draws its own influenced cells
+    Composited,  // Each influenced cell is drawn once, blending all contributing resources
+}
+
+/// Cells influenced by a single resource
+#[derive(Debug, Clone)]
+pub struct InfluenceArea {
+    origin: coords::Position,                // Position of the resource
+    element: Element,                        // Element of the resource
+    state: State,                            // State of the resource
+    radius: usize,                           // Radius of the resource, in cells
+    cells: HashMap<coords::Position, usize>, // Influenced cells and their distance from the origin
+}
+
+/// Combined influence of all resources on a single cell
+#[derive(Debug, Clone)]
+pub struct CompositeCell {
+    position: coords::Position,         // Position of the cell
+    contributions: Vec<(Element, f32)>, // Element and weight of each contributing resource
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl InfluenceView {
+    /// Returns the other influence view
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::PerResource => Self::Composited,
+            Self::Composited => Self::PerResource,
+        }
+    }
+}
+
+impl InfluenceArea {
+    /// Computes the influence area of the given resource
+    ///
+    /// Influence spreads outward from the origin one cell at a time, so a blocked cell also
+    /// shadows the cells behind it. The origin is always influenced, even if it is blocked.
+    pub fn compute<F>(
+        resource: &Resource,
+        in_grid: F,
+        blocked: &HashSet<coords::Position>,
+        ci_ctx: &CastIronContext,
+    ) -> Self
+    where
+        F: Fn(&coords::Position) -> bool,
+    {
+        let origin = *resource.origin();
+        let mut cells = HashMap::new();
+        let mut frontier = VecDeque::new();
+
+        cells.insert(origin, 0);
+        frontier.push_back((origin, 0));
+
+        // Breadth-first walk guarantees each cell is reached by its shortest unblocked path
+        while let Some((cell, distance)) = frontier.pop_front() {
+            if distance >= resource.radius() {
+                continue;
+            }
+
+            let sides: hex_directions::Provider<hex_directions::Side> =
+                hex_directions::Provider::new(hex_directions::Side::North);
+            for side in sides {
+                let mut neighbour = cell;
+                if neighbour
+                    .translate(&coords::Translation::from(side), ci_ctx)
+                    .is_err()
+                {
+                    continue;
+                }
+
+                if cells.contains_key(&neighbour)
+                    || !in_grid(&neighbour)
+                    || blocked.contains(&neighbour)
+                {
+                    continue;
+                }
+
+                cells.insert(neighbour, distance + 1);
+                frontier.push_back((neighbour, distance + 1));
+            }
+        }
+
+        Self {
+            origin,
+            element: resource.element(),
+            state: resource.state(),
+            radius: resource.radius(),
+            cells,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn origin(&self) -> &coords::Position {
+        &self.origin
+    }
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn radius(&self) -> usize {
+        self.radius
+    }
+
+    /// Returns the influenced cells and their distance from the origin
+    pub fn cells(&self) -> &HashMap<coords::Position, usize> {
+        &self.cells
+    }
+
+    /// Returns true if the given position is influenced
+    pub fn contains(&self, position: &coords::Position) -> bool {
+        self.cells.contains_key(position)
+    }
+
+    /// Returns the distance of the given position from the origin, or None if it is not influenced
+    pub fn distance_to(&self, position: &coords::Position) -> Option<usize> {
+        self.cells.get(position).copied()
+    }
+
+    /// Returns the strength of the influence at the given position, from 0.0 if not influenced
+    ///
+    /// Strength falls off linearly with distance from the origin, and scales with the resource's state.
+    pub fn weight_at(&self, position: &coords::Position) -> f32 {
+        match self.distance_to(position) {
+            Some(distance) => {
+                let falloff = 1.0 - (distance as f32 / (self.radius + 1) as f32);
+                falloff * state_weight(self.state)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+impl CompositeCell {
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn position(&self) -> &coords::Position {
+        &self.position
+    }
+
+    /// Returns the element and weight of each resource influencing the cell
+    pub fn contributions(&self) -> &[(Element, f32)] {
+        &self.contributions
+    }
+
+    /// Returns the combined weight of all contributions
+    pub fn total_weight(&self) -> f32 {
+        self.contributions
+            .iter()
+            .map(|(_element, weight)| weight)
+            .sum()
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl fmt::Display for InfluenceView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PerResource => write!(f, "Per-Resource"),
+            Self::Composited => write!(f, "Composited"),
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Combines the given influence areas into one entry per influenced cell
+///
+/// The origins of the resources are excluded, as they are drawn by the resources themselves.
+/// Cells whose contributions all have zero weight (e.g. depleted resources) are also excluded.
+pub fn composite(influence_areas: &[InfluenceArea]) -> Vec<CompositeCell> {
+    let origins: HashSet<&coords::Position> = influence_areas
+        .iter()
+        .map(|influence_area| influence_area.origin())
+        .collect();
+
+    let mut composite_cells: HashMap<coords::Position, CompositeCell> = HashMap::new();
+    for influence_area in influence_areas {
+        for position in influence_area.cells().keys() {
+            if origins.contains(position) {
+                continue;
+            }
+
+            let weight = influence_area.weight_at(position);
+            if weight <= 0.0 {
+                continue;
+            }
+
+            composite_cells
+                .entry(*position)
+                .or_insert_with(|| CompositeCell {
+                    position: *position,
+                    contributions: Vec::new(),
+                })
+                .contributions
+                .push((influence_area.element(), weight));
+        }
+    }
+
+    composite_cells
+        .into_iter()
+        .map(|(_position, cell)| cell)
+        .collect()
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the relative strength of a resource's influence in the given state
+fn state_weight(state: State) -> f32 {
+    match state {
+        State::Depleted => 0.00,
+        State::Low => 0.25,
+        State::Partial => 0.50,
+        State::High => 0.75,
+        State::Full => 1.00,
+        State::Overflow => 1.50,
+    }
+}
@@ -23,15 +23,37 @@ use cast_iron::{context::Context as CastIronContext, coords, Plottable, Randomiz
 
 use ggez::{graphics as ggez_gfx, Context as GgEzContext};
 
+use mt_logger::{mt_log, Level};
+
+use crate::{
+    game_assets::{
+        chunked_mesh::ChunkedMesh,
+        hex_batch::{HexBatch, RenderMode},
+        hex_grid_cell::HexGridCell,
+    },
+    game_managers::{
+        entity::{EntityId, InstanceKind},
+        occupancy::OccupancyIndex,
+    },
+};
+
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Module Declarations
 ///////////////////////////////////////////////////////////////////////////////
 
 pub mod actor_manager;
+pub mod entity;
+pub mod influence;
 pub mod obstacle_manager;
+pub mod occupancy;
+pub mod registry;
 pub mod resource_manager;
+pub mod weather_event;
+pub mod weather_fronts;
+pub mod weather_history;
 pub mod weather_manager;
+pub mod world_edit;
 pub mod world_grid_manager;
 
 
@@ -41,7 +63,10 @@ pub mod world_grid_manager;
 
 #[derive(Debug, PartialEq)]
 pub enum DrawableError {
-    CoordinatesOccupied(coords::Position),
+    CoordinatesOccupied(coords::Position, InstanceKind, EntityId),
+    DuplicateId(EntityId),
+    InvalidDestination(coords::Position),
+    NoSuchInstance(EntityId),
     ReachedMaxRandAttempts,
 }
 
@@ -65,17 +90,40 @@ pub trait DrawableMechanic {
     /// Implementor-defined type indicating an error
     type ErrorType: std::fmt::Debug;
 
-    /// Implementor-defined function to return a reference to its mesh
+    /// Implementor-defined kind of its instances, used to apply occupancy rules
+    const KIND: InstanceKind;
+
+    /// Implementor-defined function to return a reference to its instances
     fn instances(&self) -> &Vec<Self::Instance>;
 
-    /// Implementor-defined function to return a reference to its mesh
-    fn push_instance(&mut self, instance: Self::Instance);
+    /// Implementor-defined function to return the IDs of its instances, in the same order
+    fn instance_ids(&self) -> &Vec<EntityId>;
+
+    /// Implementor-defined function to append an instance and its ID
+    fn push_instance(&mut self, instance: Self::Instance, id: EntityId);
+
+    /// Implementor-defined function to insert an instance and its ID at the given index
+    fn insert_instance_by_index(&mut self, index: usize, instance: Self::Instance, id: EntityId);
+
+    /// Implementor-defined function to remove and return the instance at the given index, dropping its ID
+    fn remove_instance_by_index(&mut self, index: usize) -> Self::Instance;
+
+    /// Implementor-defined function to remove all instances
+    fn clear_instances(&mut self);
 
     /// Implementor-defined function to return a reference to its mesh
-    fn mesh(&self) -> &ggez_gfx::Mesh;
+    fn mesh(&self) -> &ChunkedMesh;
 
-    /// Implementor-defined function to set its mesh
-    fn set_mesh(&mut self, mesh: ggez_gfx::Mesh);
+    /// Implementor-defined function to return a mutable reference to its mesh
+    fn mesh_mut(&mut self) -> &mut ChunkedMesh;
+
+    /// Implementor-defined function to rebuild an instance so that it occupies the given positions
+    ///
+    /// The positions are those returned by translated_positions(), so the origin is always first.
+    fn relocated_instance(
+        instance: Self::Instance,
+        positions: Vec<coords::Position>,
+    ) -> Self::Instance;
 
     /// Implementor-defined function to an instance of itself to a mesh builder
     fn add_instance_to_mesh_builder(
@@ -89,67 +137,334 @@ pub trait DrawableMechanic {
      *  Defined by Default   *
     \*  *  *  *  *  *  *  *  */
 
-    /// Adds the given instance to the manager
+    /// Returns all positions occupied by the given instance
+    fn instance_positions(instance: &Self::Instance) -> Vec<coords::Position> {
+        vec![*instance.origin()]
+    }
+
+    /// Returns the batch used to draw the mechanic with instancing, or None if it only supports meshes
+    fn hex_batch_mut(&mut self) -> Option<&mut HexBatch> {
+        None
+    }
+
+    /// Returns the hex cells making up an instance, each with its fill and outline colour, for instanced drawing
+    fn instance_hex_cells(
+        _instance: &Self::Instance,
+        _ci_ctx: &CastIronContext,
+        _ggez_ctx: &GgEzContext,
+    ) -> Vec<(HexGridCell, ggez_gfx::Color, ggez_gfx::Color)> {
+        Vec::new()
+    }
+
+    /// Returns the positions the given instance would occupy if its origin were moved to the destination
+    fn translated_positions(
+        instance: &Self::Instance,
+        destination: &coords::Position,
+        ci_ctx: &CastIronContext,
+    ) -> Result<Vec<coords::Position>, DrawableError> {
+        let origin = instance.origin();
+        let (dx, dy, dz) = (
+            destination.x() - origin.x(),
+            destination.y() - origin.y(),
+            destination.z() - origin.z(),
+        );
+
+        Self::instance_positions(instance)
+            .iter()
+            .map(|position| {
+                coords::Position::new(
+                    position.x() + dx,
+                    position.y() + dy,
+                    position.z() + dz,
+                    ci_ctx,
+                )
+                .map_err(|_e| DrawableError::InvalidDestination(*destination))
+            })
+            .collect()
+    }
+
+    /// Returns the index of the instance occupying the given position, if any
+    fn index_at(&self, position: &coords::Position) -> Option<usize> {
+        self.instances()
+            .iter()
+            .position(|instance| Self::instance_positions(instance).contains(position))
+    }
+
+    /// Returns the index of the instance with the given ID, if any
+    fn index_of(&self, id: EntityId) -> Option<usize> {
+        self.instance_ids()
+            .iter()
+            .position(|instance_id| *instance_id == id)
+    }
+
+    /// Returns the instance occupying the given position, if any
+    fn instance_at(&self, position: &coords::Position) -> Option<&Self::Instance> {
+        self.index_at(position)
+            .map(|index| &self.instances()[index])
+    }
+
+    /// Returns the ID of the instance occupying the given position, if any
+    fn id_at(&self, position: &coords::Position) -> Option<EntityId> {
+        self.index_at(position)
+            .map(|index| self.instance_ids()[index])
+    }
+
+    /// Returns the instance with the given ID, if any
+    fn instance_by_id(&self, id: EntityId) -> Option<&Self::Instance> {
+        self.index_of(id).map(|index| &self.instances()[index])
+    }
+
+    /// Adds the given instance to the manager, returning its newly-allocated ID
+    ///
+    /// Placement is checked against every entity in the occupancy index, not just those in this
+    /// manager. The mesh is not rebuilt until the next call to update_mesh().
     fn add_instance(
         &mut self,
         new_instance: Self::Instance,
-        ggez_ctx: &mut GgEzContext,
+        occupancy: &mut OccupancyIndex,
+    ) -> Result<EntityId, DrawableError> {
+        let id = EntityId::next();
+        self.add_instance_with_id(new_instance, id, occupancy)?;
+
+        Ok(id)
+    }
+
+    /// Adds the given instance to the manager under an existing ID, e.g. when restoring a removed instance
+    fn add_instance_with_id(
+        &mut self,
+        new_instance: Self::Instance,
+        id: EntityId,
+        occupancy: &mut OccupancyIndex,
     ) -> Result<(), DrawableError> {
-        // Verify that no instance already exists in the same location
-        for existing_instance in self.instances() {
-            if new_instance.origin() == existing_instance.origin() {
-                return Err(DrawableError::CoordinatesOccupied(*new_instance.origin()));
-            }
+        // An ID may only be held by one instance at a time
+        if self.index_of(id).is_some() {
+            return Err(DrawableError::DuplicateId(id));
         }
 
+        // Verify that every cell of the new instance may be occupied
+        let new_positions = Self::instance_positions(&new_instance);
+        occupancy.check(Self::KIND, &new_positions, None)?;
+
         // New instance's coordinates are unoccupied, add it to the mesh
-        self.push_instance(new_instance);
+        occupancy.insert(Self::KIND, id, &new_positions);
+        self.push_instance(new_instance, id);
 
-        // Update mesh
-        self.update_mesh(ggez_ctx);
+        // Only the chunk holding the new instance needs to be rebuilt
+        let instance_count = self.instances().len();
+        self.mark_instance_dirty(instance_count - 1);
 
         Ok(())
     }
 
-    fn add_rand_instance(
+    /// Removes and returns the instance occupying the given position, if any
+    fn remove_instance_at(
+        &mut self,
+        position: &coords::Position,
+        occupancy: &mut OccupancyIndex,
+    ) -> Option<Self::Instance> {
+        let index = self.index_at(position)?;
+
+        Some(self.remove_and_sync(index, occupancy))
+    }
+
+    /// Removes and returns the instance with the given ID, if any
+    fn remove_instance_by_id(
+        &mut self,
+        id: EntityId,
+        occupancy: &mut OccupancyIndex,
+    ) -> Option<Self::Instance> {
+        let index = self.index_of(id)?;
+
+        Some(self.remove_and_sync(index, occupancy))
+    }
+
+    /// Moves the instance with the given ID such that its origin is at the destination
+    ///
+    /// The instance keeps its ID. A failed move leaves the manager untouched.
+    fn move_instance(
         &mut self,
+        id: EntityId,
+        destination: &coords::Position,
+        occupancy: &mut OccupancyIndex,
         ci_ctx: &CastIronContext,
-        ggez_ctx: &mut GgEzContext,
     ) -> Result<(), DrawableError> {
+        let index = self.index_of(id).ok_or(DrawableError::NoSuchInstance(id))?;
+
+        // Verify the destination is free before touching anything
+        let current_positions = Self::instance_positions(&self.instances()[index]);
+        let relocated_positions =
+            Self::translated_positions(&self.instances()[index], destination, ci_ctx)?;
+        occupancy.check(Self::KIND, &relocated_positions, Some(id))?;
+
+        // Destination is clear, rebuild the instance in place
+        occupancy.remove(id, &current_positions);
+        occupancy.insert(Self::KIND, id, &relocated_positions);
+        let instance = self.remove_instance_by_index(index);
+        let relocated_instance = Self::relocated_instance(instance, relocated_positions);
+        self.insert_instance_by_index(index, relocated_instance, id);
+
+        self.mark_instance_dirty(index);
+
+        Ok(())
+    }
+
+    /// Replaces the instance with the given ID with a new instance, returning the old one
+    ///
+    /// The new instance takes over the ID. A failed replacement leaves the manager untouched.
+    fn replace_instance(
+        &mut self,
+        id: EntityId,
+        new_instance: Self::Instance,
+        occupancy: &mut OccupancyIndex,
+    ) -> Result<Self::Instance, DrawableError> {
+        let index = self.index_of(id).ok_or(DrawableError::NoSuchInstance(id))?;
+
+        let new_positions = Self::instance_positions(&new_instance);
+        occupancy.check(Self::KIND, &new_positions, Some(id))?;
+
+        let old_instance = self.remove_instance_by_index(index);
+        occupancy.remove(id, &Self::instance_positions(&old_instance));
+        occupancy.insert(Self::KIND, id, &new_positions);
+        self.insert_instance_by_index(index, new_instance, id);
+
+        self.mark_instance_dirty(index);
+
+        Ok(old_instance)
+    }
+
+    /// Removes all instances from the manager
+    fn clear(&mut self, occupancy: &mut OccupancyIndex) {
+        occupancy.remove_all(self.instance_ids());
+        self.clear_instances();
+        self.mesh_mut().mark_all_dirty(0);
+        if let Some(hex_batch) = self.hex_batch_mut() {
+            hex_batch.mark_dirty();
+        }
+    }
+
+    /// Adds a random instance, returning the error from the last attempt if none could be placed
+    ///
+    /// ReachedMaxRandAttempts is only returned if no attempts are allowed at all.
+    fn add_rand_instance(
+        &mut self,
+        occupancy: &mut OccupancyIndex,
+        ci_ctx: &CastIronContext,
+    ) -> Result<EntityId, DrawableError> {
         // Create a random instance and attempt to add them until we succeed (or fail too many times)
+        let mut last_err = DrawableError::ReachedMaxRandAttempts;
         for _ in 0..ci_ctx.max_rand_attempts() {
             let rand_instance = Self::Instance::rand(ci_ctx);
-            if self.add_instance(rand_instance, ggez_ctx).is_ok() {
+            match self.add_instance(rand_instance, occupancy) {
                 // Successfully added instance
-                return Ok(());
+                Ok(id) => return Ok(id),
+                Err(e) => last_err = e,
             }
         }
 
-        // Failed to add instance within allowable number of attempts
-        Err(DrawableError::ReachedMaxRandAttempts)
+        // Failed to add instance within allowable number of attempts, report why the last one failed
+        Err(last_err)
     }
 
-    /// Draws the mesh for the mechanic in the given context
-    fn draw(&self, ggez_ctx: &mut GgEzContext) {
-        ggez_gfx::draw(ggez_ctx, self.mesh(), ggez_gfx::DrawParam::default()).unwrap();
+    /// Draws the mechanic in the given context, using instancing if requested and supported
+    fn draw(&mut self, render_mode: RenderMode, ggez_ctx: &mut GgEzContext) {
+        if render_mode == RenderMode::Instanced {
+            if let Some(hex_batch) = self.hex_batch_mut() {
+                hex_batch.draw(ggez_ctx);
+                return;
+            }
+        }
+
+        self.mesh().draw(ggez_ctx);
+    }
+
+    /// Brings the geometry for the given render mode up to date with the current instances
+    fn update_geometry(
+        &mut self,
+        render_mode: RenderMode,
+        ci_ctx: &CastIronContext,
+        ggez_ctx: &mut GgEzContext,
+    ) {
+        if render_mode == RenderMode::Instanced && self.hex_batch_mut().is_some() {
+            self.update_hex_batch(ci_ctx, ggez_ctx);
+        } else {
+            self.update_mesh(ggez_ctx);
+        }
     }
 
-    /// Updates the mechanic mesh with current instances
-    fn update_mesh(&mut self, ggez_ctx: &mut GgEzContext) {
-        // Short-circuit if there are no instances
-        if self.instances().is_empty() {
-            return;
+    /// Rebuilds the instanced hex batch if the instances have changed
+    fn update_hex_batch(&mut self, ci_ctx: &CastIronContext, ggez_ctx: &GgEzContext) {
+        match self.hex_batch_mut() {
+            Some(hex_batch) if hex_batch.is_dirty() => {}
+            _ => return,
         }
 
-        //OPT: *PERFORMANCE* is this necesary? could be faster if mesh is updated in-place
-        // Create a mesh builder for the update
-        let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+        let hex_cells: Vec<(HexGridCell, ggez_gfx::Color, ggez_gfx::Color)> = self
+            .instances()
+            .iter()
+            .flat_map(|instance| Self::instance_hex_cells(instance, ci_ctx, ggez_ctx))
+            .collect();
+
+        if let Some(hex_batch) = self.hex_batch_mut() {
+            hex_batch.clear();
+            for (hex_cell, fill_color, outline_color) in hex_cells.iter() {
+                hex_batch.add_hex(hex_cell, *fill_color, *outline_color);
+            }
+
+            mt_log!(
+                Level::Trace,
+                "Hex batch rebuilt with {} cell(s)",
+                hex_batch.len()
+            );
+        }
+    }
+
+    /// Rebuilds the chunks of the mechanic mesh whose instances have changed, returning the number rebuilt
+    fn update_mesh(&mut self, ggez_ctx: &mut GgEzContext) -> usize {
+        // Short-circuit if nothing has changed since the last update
+        if !self.mesh().is_dirty() {
+            return 0;
+        }
+
+        // Take the mesh out of the manager so it can be rebuilt from the instances
+        let mut mesh = std::mem::take(self.mesh_mut());
+        let instances = self.instances();
+        let rebuilt_count = mesh.update(ggez_ctx, |index, mesh_builder, ggez_ctx| {
+            Self::add_instance_to_mesh_builder(&instances[index], mesh_builder, ggez_ctx).unwrap();
+        });
+        *self.mesh_mut() = mesh;
+
+        mt_log!(Level::Trace, "{} mesh chunk(s) rebuilt", rebuilt_count);
+
+        rebuilt_count
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Marks the geometry of the instance at the given index as requiring a rebuild
+    fn mark_instance_dirty(&mut self, index: usize) {
+        let instance_count = self.instances().len();
+        self.mesh_mut().mark_dirty(index, instance_count);
+        if let Some(hex_batch) = self.hex_batch_mut() {
+            hex_batch.mark_dirty();
+        }
+    }
 
-        // Iterate through instances, adding to the mesh builder along the way
-        for instance in self.instances() {
-            Self::add_instance_to_mesh_builder(instance, &mut mesh_builder, ggez_ctx).unwrap();
+    /// Removes the instance at the given index and marks the shifted geometry as requiring a rebuild
+    fn remove_and_sync(&mut self, index: usize, occupancy: &mut OccupancyIndex) -> Self::Instance {
+        let id = self.instance_ids()[index];
+        let removed_instance = self.remove_instance_by_index(index);
+        occupancy.remove(id, &Self::instance_positions(&removed_instance));
+
+        // Instances after the removed one have shifted, so their chunks must be rebuilt
+        let instance_count = self.instances().len();
+        self.mesh_mut().mark_dirty_from(index, instance_count);
+        if let Some(hex_batch) = self.hex_batch_mut() {
+            hex_batch.mark_dirty();
         }
 
-        self.set_mesh(mesh_builder.build(ggez_ctx).unwrap());
+        removed_instance
     }
 }
@@ -20,15 +20,23 @@ Purpose:
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
-use cast_iron::{element::Elemental, hex_directions, mechanics::obstacle::Obstacle, Plottable};
+use cast_iron::{
+    context::Context as CastIronContext, coords, element::Elemental, hex_directions,
+    mechanics::obstacle::Obstacle, Plottable,
+};
 
-use ggez::{graphics as ggez_gfx, mint as ggez_mint, Context as GgEzContext};
+use ggez::{graphics as ggez_gfx, Context as GgEzContext};
 
 use mt_logger::{mt_log, Level};
 
 use crate::{
-    game_assets::{colors, hex_grid_cell::HexGridCell},
-    game_managers::DrawableMechanic,
+    game_assets::{
+        chunked_mesh::ChunkedMesh, colors, hex_batch::HexBatch, hex_grid_cell::HexGridCell,
+    },
+    game_managers::{
+        entity::{EntityId, InstanceKind},
+        DrawableMechanic,
+    },
 };
 
 
@@ -36,9 +44,12 @@ use crate::{
 //  Data Structures
 ///////////////////////////////////////////////////////////////////////////////
 
+#[derive(Default)]
 pub struct ObstacleManager {
     obstacles: Vec<Obstacle>,
-    obstacle_mesh: ggez_gfx::Mesh,
+    obstacle_ids: Vec<EntityId>, // ID of each obstacle, in the same order
+    obstacle_mesh: ChunkedMesh,
+    hex_batch: HexBatch,
 }
 
 #[derive(Debug)]
@@ -51,19 +62,12 @@ pub struct ObstacleError;
 
 impl ObstacleManager {
     /// Generic Constructor - creates an empty instance
-    pub fn new(ctx: &mut GgEzContext) -> Self {
+    pub fn new() -> Self {
         ObstacleManager {
             obstacles: Vec::new(),
-            obstacle_mesh: ggez_gfx::Mesh::new_line(
-                ctx,
-                &[
-                    ggez_mint::Point2 { x: 0.0, y: 0.0 },
-                    ggez_mint::Point2 { x: 10.0, y: 10.0 },
-                ],
-                crate::DEFAULT_LINE_WIDTH,
-                crate::DEFAULT_LINE_COLOR,
-            )
-            .unwrap(),
+            obstacle_ids: Vec::new(),
+            obstacle_mesh: ChunkedMesh::default(),
+            hex_batch: HexBatch::default(),
         }
     }
 }
@@ -76,28 +80,65 @@ impl ObstacleManager {
 impl DrawableMechanic for ObstacleManager {
     type Instance = Obstacle;
     type ErrorType = ObstacleError;
+    const KIND: InstanceKind = InstanceKind::Obstacle;
 
     fn instances(&self) -> &Vec<Self::Instance> {
         &self.obstacles
     }
 
-    fn push_instance(&mut self, instance: Self::Instance) {
+    fn instance_ids(&self) -> &Vec<EntityId> {
+        &self.obstacle_ids
+    }
+
+    fn push_instance(&mut self, instance: Self::Instance, id: EntityId) {
         mt_log!(
             Level::Debug,
-            "Adding {} obstacle starting at {} to mesh.",
+            "Adding {} obstacle {} starting at {} to mesh.",
             String::from(instance.element()),
+            id,
             instance.origin()
         );
 
         self.obstacles.push(instance);
+        self.obstacle_ids.push(id);
+    }
+
+    fn insert_instance_by_index(&mut self, index: usize, instance: Self::Instance, id: EntityId) {
+        self.obstacles.insert(index, instance);
+        self.obstacle_ids.insert(index, id);
+    }
+
+    fn remove_instance_by_index(&mut self, index: usize) -> Self::Instance {
+        self.obstacle_ids.remove(index);
+        self.obstacles.remove(index)
+    }
+
+    fn clear_instances(&mut self) {
+        self.obstacles.clear();
+        self.obstacle_ids.clear();
     }
 
-    fn mesh(&self) -> &ggez_gfx::Mesh {
+    fn relocated_instance(
+        instance: Self::Instance,
+        positions: Vec<coords::Position>,
+    ) -> Self::Instance {
+        Obstacle::new(positions, instance.element())
+    }
+
+    fn mesh(&self) -> &ChunkedMesh {
         &self.obstacle_mesh
     }
 
-    fn set_mesh(&mut self, mesh: ggez_gfx::Mesh) {
-        self.obstacle_mesh = mesh;
+    fn mesh_mut(&mut self) -> &mut ChunkedMesh {
+        &mut self.obstacle_mesh
+    }
+
+    fn hex_batch_mut(&mut self) -> Option<&mut HexBatch> {
+        Some(&mut self.hex_batch)
+    }
+
+    fn instance_positions(instance: &Self::Instance) -> Vec<coords::Position> {
+        instance.positions().to_vec()
     }
 
     fn add_instance_to_mesh_builder(
@@ -150,4 +191,27 @@ impl DrawableMechanic for ObstacleManager {
 
         Ok(())
     }
+
+    fn instance_hex_cells(
+        instance: &Self::Instance,
+        _ci_ctx: &CastIronContext,
+        ggez_ctx: &GgEzContext,
+    ) -> Vec<(HexGridCell, ggez_gfx::Color, ggez_gfx::Color)> {
+        // Instanced obstacles keep the outline between their cells, as each cell is a separate instance
+        instance
+            .positions()
+            .iter()
+            .map(|obstacle_pos| {
+                (
+                    HexGridCell::new_from_hex_coords(
+                        obstacle_pos,
+                        crate::HEX_RADIUS_VERTEX,
+                        ggez_ctx,
+                    ),
+                    colors::from_element(instance.element()),
+                    colors::DARKGREY,
+                )
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,244 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/occupancy.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Tracks which entities occupy each cell of the world, across all managers.
+
+    Every cell of every instance is indexed, so multi-cell obstacles block
+    placement along their whole length. Actors and obstacles never share a
+    cell with one another or with their own kind, nor do two resources.
+    Whether actors and obstacles may share a cell with a resource is set by
+    the configurable ResourceRule.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use cast_iron::coords;
+
+use crate::game_managers::{
+    entity::{EntityId, InstanceKind},
+    DrawableError,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Rule governing which entities may share a cell with a resource
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ResourceRule {
+    Exclusive,  // Nothing may share a cell with a resource
+    ActorsOnly, // Actors may stand on a resource, obstacles may not cover one
+    Open,       // Actors and obstacles may both share a cell with a resource
+}
+
+/// An entity occupying a cell
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Occupant {
+    kind: InstanceKind, // Kind of the occupying entity
+    id: EntityId,       // ID of the occupying entity
+}
+
+#[derive(Default)]
+pub struct OccupancyIndex {
+    cells: HashMap<coords::Position, Vec<Occupant>>, // Occupants of each occupied cell
+    resource_rule: ResourceRule,                     // Rule for sharing cells with resources
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Occupant {
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn kind(&self) -> InstanceKind {
+        self.kind
+    }
+
+    pub fn id(&self) -> EntityId {
+        self.id
+    }
+}
+
+impl OccupancyIndex {
+    /// Generic Constructor - creates an empty index with the default resource rule
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn resource_rule(&self) -> ResourceRule {
+        self.resource_rule
+    }
+
+    /// Returns the entities occupying the given position
+    pub fn occupants_at(&self, position: &coords::Position) -> &[Occupant] {
+        self.cells.get(position).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns true if any entity occupies the given position
+    pub fn is_occupied(&self, position: &coords::Position) -> bool {
+        !self.occupants_at(position).is_empty()
+    }
+
+    /// Returns the number of occupied cells
+    pub fn occupied_cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Sets the rule for sharing cells with resources
+    ///
+    /// Entities already sharing cells are left in place; the rule applies to subsequent placements.
+    pub fn set_resource_rule(&mut self, resource_rule: ResourceRule) {
+        self.resource_rule = resource_rule;
+    }
+
+    /// Records the given entity as occupying the given positions
+    pub fn insert(&mut self, kind: InstanceKind, id: EntityId, positions: &[coords::Position]) {
+        for position in positions {
+            self.cells
+                .entry(*position)
+                .or_insert_with(Vec::new)
+                .push(Occupant { kind, id });
+        }
+    }
+
+    /// Removes the given entity from the given positions
+    pub fn remove(&mut self, id: EntityId, positions: &[coords::Position]) {
+        for position in positions {
+            if let Some(occupants) = self.cells.get_mut(position) {
+                occupants.retain(|occupant| occupant.id != id);
+                if occupants.is_empty() {
+                    self.cells.remove(position);
+                }
+            }
+        }
+    }
+
+    /// Removes the given entities from every cell they occupy
+    pub fn remove_all(&mut self, ids: &[EntityId]) {
+        self.cells.retain(|_position, occupants| {
+            occupants.retain(|occupant| !ids.contains(&occupant.id));
+            !occupants.is_empty()
+        });
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Verifies that an entity of the given kind may occupy all of the given positions
+    ///
+    /// The entity identified by `ignore`, if any, is disregarded so that an entity may be moved
+    /// onto cells it already occupies.
+    pub fn check(
+        &self,
+        kind: InstanceKind,
+        positions: &[coords::Position],
+        ignore: Option<EntityId>,
+    ) -> Result<(), DrawableError> {
+        for position in positions {
+            for occupant in self.occupants_at(position) {
+                if Some(occupant.id) == ignore {
+                    continue;
+                }
+
+                if !self.may_share(kind, occupant.kind) {
+                    return Err(DrawableError::CoordinatesOccupied(
+                        *position,
+                        occupant.kind,
+                        occupant.id,
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns true if entities of the given kinds may occupy the same cell
+    fn may_share(&self, kind: InstanceKind, other_kind: InstanceKind) -> bool {
+        match (kind, other_kind) {
+            (InstanceKind::Resource, InstanceKind::Resource) => false,
+            (InstanceKind::Resource, other) | (other, InstanceKind::Resource) => {
+                match self.resource_rule {
+                    ResourceRule::Exclusive => false,
+                    ResourceRule::ActorsOnly => other == InstanceKind::Actor,
+                    ResourceRule::Open => true,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for ResourceRule {
+    fn default() -> Self {
+        Self::Exclusive
+    }
+}
+
+impl FromStr for ResourceRule {
+    type Err = String;
+
+    fn from_str(rule_str: &str) -> Result<Self, Self::Err> {
+        match rule_str {
+            "exclusive" => Ok(Self::Exclusive),
+            "actors" => Ok(Self::ActorsOnly),
+            "open" => Ok(Self::Open),
+            _ => Err(format!(
+                "Unknown resource rule '{}', expected 'exclusive', 'actors' or 'open'",
+                rule_str
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ResourceRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Exclusive => write!(f, "exclusive"),
+            Self::ActorsOnly => write!(f, "actors"),
+            Self::Open => write!(f, "open"),
+        }
+    }
+}
@@ -0,0 +1,233 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/registry.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Provides a unified view of every entity in the world, regardless of which
+    manager owns it.
+
+    Entities are looked up by their stable ID, by position or by kind. IDs
+    survive moves and replacements, and are never reused after removal, so
+    they may be held onto by anything needing to refer to a specific entity.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::fmt;
+
+use cast_iron::{
+    actor::Actor,
+    coords,
+    element::Elemental,
+    mechanics::{obstacle::Obstacle, resource::Resource},
+    Plottable,
+};
+
+use crate::game_managers::{
+    actor_manager::ActorManager,
+    entity::{EntityId, InstanceKind},
+    obstacle_manager::ObstacleManager,
+    occupancy::OccupancyIndex,
+    resource_manager::ResourceManager,
+    DrawableMechanic,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Reference to an entity of any kind
+#[derive(Copy, Clone)]
+pub enum EntityRef<'a> {
+    Actor(&'a Actor),
+    Obstacle(&'a Obstacle),
+    Resource(&'a Resource),
+}
+
+/// Read-only view of all entities in the world
+pub struct EntityRegistry<'a> {
+    actor_manager: &'a ActorManager,
+    obstacle_manager: &'a ObstacleManager,
+    resource_manager: &'a ResourceManager,
+    occupancy: &'a OccupancyIndex,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl<'a> EntityRef<'a> {
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn kind(&self) -> InstanceKind {
+        match self {
+            Self::Actor(_actor) => InstanceKind::Actor,
+            Self::Obstacle(_obstacle) => InstanceKind::Obstacle,
+            Self::Resource(_resource) => InstanceKind::Resource,
+        }
+    }
+
+    pub fn origin(&self) -> &'a coords::Position {
+        match self {
+            Self::Actor(actor) => actor.origin(),
+            Self::Obstacle(obstacle) => obstacle.origin(),
+            Self::Resource(resource) => resource.origin(),
+        }
+    }
+
+    /// Returns all positions occupied by the entity
+    pub fn positions(&self) -> Vec<coords::Position> {
+        match self {
+            Self::Actor(actor) => ActorManager::instance_positions(actor),
+            Self::Obstacle(obstacle) => ObstacleManager::instance_positions(obstacle),
+            Self::Resource(resource) => ResourceManager::instance_positions(resource),
+        }
+    }
+}
+
+impl<'a> EntityRegistry<'a> {
+    /// Fully-qualified constructor
+    pub fn new(
+        actor_manager: &'a ActorManager,
+        obstacle_manager: &'a ObstacleManager,
+        resource_manager: &'a ResourceManager,
+        occupancy: &'a OccupancyIndex,
+    ) -> Self {
+        Self {
+            actor_manager,
+            obstacle_manager,
+            resource_manager,
+            occupancy,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the entity with the given ID, if it is still in the world
+    pub fn get(&self, id: EntityId) -> Option<EntityRef<'a>> {
+        self.actor_manager
+            .instance_by_id(id)
+            .map(EntityRef::Actor)
+            .or_else(|| {
+                self.obstacle_manager
+                    .instance_by_id(id)
+                    .map(EntityRef::Obstacle)
+            })
+            .or_else(|| {
+                self.resource_manager
+                    .instance_by_id(id)
+                    .map(EntityRef::Resource)
+            })
+    }
+
+    /// Returns true if the entity with the given ID is still in the world
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Returns every entity occupying the given position
+    pub fn at(&self, position: &coords::Position) -> Vec<(EntityId, EntityRef<'a>)> {
+        self.occupancy
+            .occupants_at(position)
+            .iter()
+            .filter_map(|occupant| {
+                self.get(occupant.id())
+                    .map(|entity| (occupant.id(), entity))
+            })
+            .collect()
+    }
+
+    /// Returns the IDs of all entities of the given kind, in the order they were added
+    pub fn ids_of_kind(&self, kind: InstanceKind) -> &'a [EntityId] {
+        match kind {
+            InstanceKind::Actor => self.actor_manager.instance_ids(),
+            InstanceKind::Obstacle => self.obstacle_manager.instance_ids(),
+            InstanceKind::Resource => self.resource_manager.instance_ids(),
+        }
+    }
+
+    /// Returns every entity in the world, ordered by ID
+    pub fn all(&self) -> Vec<(EntityId, EntityRef<'a>)> {
+        let mut entities: Vec<(EntityId, EntityRef<'a>)> = self
+            .actor_manager
+            .instance_ids()
+            .iter()
+            .copied()
+            .zip(self.actor_manager.instances().iter().map(EntityRef::Actor))
+            .chain(
+                self.obstacle_manager.instance_ids().iter().copied().zip(
+                    self.obstacle_manager
+                        .instances()
+                        .iter()
+                        .map(EntityRef::Obstacle),
+                ),
+            )
+            .chain(
+                self.resource_manager.instance_ids().iter().copied().zip(
+                    self.resource_manager
+                        .instances()
+                        .iter()
+                        .map(EntityRef::Resource),
+                ),
+            )
+            .collect();
+        entities.sort_by_key(|(id, _entity)| *id);
+
+        entities
+    }
+
+    /// Returns the total number of entities in the world
+    pub fn len(&self) -> usize {
+        self.actor_manager.instances().len()
+            + self.obstacle_manager.instances().len()
+            + self.resource_manager.instances().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl<'a> fmt::Display for EntityRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Actor(actor) => write!(f, "actor '{}' at {}", actor.name(), actor.origin()),
+            Self::Obstacle(obstacle) => write!(
+                f,
+                "{} obstacle of {} cell(s) at {}",
+                String::from(obstacle.element()),
+                obstacle.positions().len(),
+                obstacle.origin()
+            ),
+            Self::Resource(resource) => write!(
+                f,
+                "{} resource at {}",
+                String::from(resource.element()),
+                resource.origin()
+            ),
+        }
+    }
+}
@@ -20,15 +20,31 @@ Purpose:
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
-use cast_iron::{element::Elemental, mechanics::resource::Resource, Plottable};
+use std::collections::HashSet;
 
-use ggez::{graphics as ggez_gfx, mint as ggez_mint, Context as GgEzContext};
+use cast_iron::{
+    context::Context as CastIronContext, coords, element::Elemental, mechanics::resource::Resource,
+    Plottable,
+};
+
+use ggez::{graphics as ggez_gfx, Context as GgEzContext};
 
 use mt_logger::{mt_log, Level};
 
 use crate::{
-    game_assets::{colors, hex_grid_cell::HexGridCell},
-    game_managers::DrawableMechanic,
+    game_assets::{
+        chunked_mesh::ChunkedMesh,
+        colors,
+        hex_batch::{HexBatch, RenderMode},
+        hex_grid_cell::HexGridCell,
+    },
+    game_managers::{
+        entity::{EntityId, InstanceKind},
+        influence::{self, InfluenceArea, InfluenceView},
+        obstacle_manager::ObstacleManager,
+        world_grid_manager::WorldGridManager,
+        DrawableMechanic,
+    },
 };
 
 
@@ -38,7 +54,16 @@ use crate::{
 
 pub struct ResourceManager {
     resources: Vec<Resource>,
-    resource_mesh: ggez_gfx::Mesh,
+    resource_ids: Vec<EntityId>, // ID of each resource, in the same order
+    resource_mesh: ChunkedMesh,
+    hex_batch: HexBatch,
+    influence_areas: Vec<InfluenceArea>, // Influence area of each resource, in the same order
+    influence_blocked_cells: HashSet<coords::Position>, // Cells that blocked the current influence areas
+    blocked_by_obstacles: bool, // Flag indicating if obstacles block influence
+    influence_dirty: bool,      // Flag indicating the influence areas must be recomputed
+    influence_mesh: Option<ggez_gfx::Mesh>, // Mesh of all influenced cells, None if there are none
+    influence_batch: HexBatch,  // Instanced influenced cells
+    influence_view: InfluenceView, // Method used to display overlapping influence areas
 }
 
 #[derive(Debug)]
@@ -51,20 +76,178 @@ pub struct ResourceError;
 
 impl ResourceManager {
     /// Generic Constructor - creates an empty instance
-    pub fn new(ctx: &mut GgEzContext) -> Self {
+    pub fn new() -> Self {
         ResourceManager {
             resources: Vec::new(),
-            resource_mesh: ggez_gfx::Mesh::new_line(
-                ctx,
-                &[
-                    ggez_mint::Point2 { x: 0.0, y: 0.0 },
-                    ggez_mint::Point2 { x: 10.0, y: 10.0 },
-                ],
-                crate::DEFAULT_LINE_WIDTH,
-                crate::DEFAULT_LINE_COLOR,
-            )
-            .unwrap(),
+            resource_ids: Vec::new(),
+            resource_mesh: ChunkedMesh::default(),
+            hex_batch: HexBatch::default(),
+            influence_areas: Vec::new(),
+            influence_blocked_cells: HashSet::new(),
+            blocked_by_obstacles: false,
+            influence_dirty: false,
+            influence_mesh: None,
+            influence_batch: HexBatch::default(),
+            influence_view: InfluenceView::PerResource,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the influence area of each resource, as of the last call to update_influence()
+    pub fn influence_areas(&self) -> &[InfluenceArea] {
+        &self.influence_areas
+    }
+
+    /// Returns the influence areas covering the given position
+    pub fn influences_at(&self, position: &coords::Position) -> Vec<&InfluenceArea> {
+        self.influence_areas
+            .iter()
+            .filter(|influence_area| influence_area.contains(position))
+            .collect()
+    }
+
+    pub fn blocked_by_obstacles(&self) -> bool {
+        self.blocked_by_obstacles
+    }
+
+    pub fn influence_view(&self) -> InfluenceView {
+        self.influence_view
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Sets whether obstacles block the spread of influence
+    pub fn set_blocked_by_obstacles(&mut self, blocked_by_obstacles: bool) {
+        self.blocked_by_obstacles = blocked_by_obstacles;
+        self.influence_dirty = true;
+    }
+
+    /// Sets how overlapping influence areas are displayed
+    pub fn set_influence_view(&mut self, influence_view: InfluenceView) {
+        self.influence_view = influence_view;
+        self.influence_dirty = true;
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Recomputes the influence areas if the resources or blocking cells have changed
+    ///
+    /// Returns true if the influence areas were recomputed.
+    pub fn update_influence(
+        &mut self,
+        world_grid_manager: &WorldGridManager,
+        obstacle_manager: &ObstacleManager,
+        ci_ctx: &CastIronContext,
+        ggez_ctx: &mut GgEzContext,
+    ) -> bool {
+        let blocked_cells: HashSet<coords::Position> = if self.blocked_by_obstacles {
+            obstacle_manager
+                .instances()
+                .iter()
+                .flat_map(|obstacle| ObstacleManager::instance_positions(obstacle))
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        // Short-circuit if nothing affecting influence has changed
+        if !self.influence_dirty && blocked_cells == self.influence_blocked_cells {
+            return false;
         }
+
+        let hex_map = world_grid_manager.hex_map();
+        self.influence_areas = self
+            .resources
+            .iter()
+            .map(|resource| {
+                InfluenceArea::compute(
+                    resource,
+                    |position| hex_map.contains_key(position),
+                    &blocked_cells,
+                    ci_ctx,
+                )
+            })
+            .collect();
+        self.influence_blocked_cells = blocked_cells;
+        self.influence_dirty = false;
+
+        self.rebuild_influence_geometry(ggez_ctx);
+
+        mt_log!(
+            Level::Debug,
+            "Influence recomputed for {} resource(s)",
+            self.influence_areas.len()
+        );
+
+        true
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Rebuilds the mesh and instanced cells for the current influence areas
+    fn rebuild_influence_geometry(&mut self, ggez_ctx: &mut GgEzContext) {
+        let influenced_cells: Vec<(coords::Position, ggez_gfx::Color)> = match self.influence_view {
+            InfluenceView::PerResource => self
+                .resources
+                .iter()
+                .zip(&self.influence_areas)
+                .flat_map(|(resource, influence_area)| {
+                    let resource_color = colors::from_resource(resource);
+
+                    // The origin cell is drawn with the resource itself
+                    influence_area
+                        .cells()
+                        .iter()
+                        .filter(|(_position, distance)| **distance > 0)
+                        .map(move |(position, distance)| {
+                            // Cells fade with distance from the origin
+                            let mut fill_color = resource_color;
+                            fill_color.a = HexGridCell::radial_alpha(
+                                resource_color.a,
+                                resource.radius(),
+                                *distance,
+                            );
+
+                            (*position, fill_color)
+                        })
+                })
+                .collect(),
+            InfluenceView::Composited => influence::composite(&self.influence_areas)
+                .iter()
+                .map(|composite_cell| {
+                    (
+                        *composite_cell.position(),
+                        colors::from_contributions(composite_cell.contributions()),
+                    )
+                })
+                .collect(),
+        };
+
+        let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+        self.influence_batch.clear();
+        for (position, fill_color) in influenced_cells.iter() {
+            let hex_cell =
+                HexGridCell::new_from_hex_coords(position, crate::HEX_RADIUS_VERTEX, ggez_ctx);
+            hex_cell.add_to_mesh(*fill_color, colors::WHITE, &mut mesh_builder);
+            self.influence_batch
+                .add_hex(&hex_cell, *fill_color, colors::WHITE);
+        }
+
+        // Building fails if no cells were added, which simply leaves nothing to draw
+        self.influence_mesh = mesh_builder.build(ggez_ctx).ok();
     }
 }
 
@@ -73,31 +256,79 @@ impl ResourceManager {
 //  Trait Implementations
 ///////////////////////////////////////////////////////////////////////////////
 
+impl Default for ResourceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DrawableMechanic for ResourceManager {
     type Instance = Resource;
     type ErrorType = ResourceError;
+    const KIND: InstanceKind = InstanceKind::Resource;
 
     fn instances(&self) -> &Vec<Self::Instance> {
         &self.resources
     }
 
-    fn push_instance(&mut self, instance: Self::Instance) {
+    fn instance_ids(&self) -> &Vec<EntityId> {
+        &self.resource_ids
+    }
+
+    fn push_instance(&mut self, instance: Self::Instance, id: EntityId) {
         mt_log!(
             Level::Debug,
-            "Adding {} resource starting at {} to mesh.",
+            "Adding {} resource {} starting at {} to mesh.",
             String::from(instance.element()),
+            id,
             instance.origin()
         );
 
         self.resources.push(instance);
+        self.resource_ids.push(id);
+        self.influence_dirty = true;
+    }
+
+    fn insert_instance_by_index(&mut self, index: usize, instance: Self::Instance, id: EntityId) {
+        self.resources.insert(index, instance);
+        self.resource_ids.insert(index, id);
+        self.influence_dirty = true;
+    }
+
+    fn remove_instance_by_index(&mut self, index: usize) -> Self::Instance {
+        self.influence_dirty = true;
+        self.resource_ids.remove(index);
+        self.resources.remove(index)
     }
 
-    fn mesh(&self) -> &ggez_gfx::Mesh {
+    fn clear_instances(&mut self) {
+        self.influence_dirty = true;
+        self.resources.clear();
+        self.resource_ids.clear();
+    }
+
+    fn relocated_instance(
+        instance: Self::Instance,
+        positions: Vec<coords::Position>,
+    ) -> Self::Instance {
+        Resource::new(
+            instance.element(),
+            instance.state(),
+            positions[0],
+            instance.radius(),
+        )
+    }
+
+    fn mesh(&self) -> &ChunkedMesh {
         &self.resource_mesh
     }
 
-    fn set_mesh(&mut self, mesh: ggez_gfx::Mesh) {
-        self.resource_mesh = mesh;
+    fn mesh_mut(&mut self) -> &mut ChunkedMesh {
+        &mut self.resource_mesh
+    }
+
+    fn hex_batch_mut(&mut self) -> Option<&mut HexBatch> {
+        Some(&mut self.hex_batch)
     }
 
     fn add_instance_to_mesh_builder(
@@ -110,15 +341,37 @@ impl DrawableMechanic for ResourceManager {
             HexGridCell::new_from_hex_coords(instance.origin(), crate::HEX_RADIUS_VERTEX, ggez_ctx);
         cur_hex.add_to_mesh(colors::from_resource(instance), colors::WHITE, mesh_builder);
 
-        // Create radial HexGridCells as necessary
-        cur_hex.add_radials_to_mesh(
+        // Influenced cells are drawn separately, as they depend on the rest of the world
+
+        Ok(())
+    }
+
+    fn instance_hex_cells(
+        instance: &Self::Instance,
+        _ci_ctx: &CastIronContext,
+        ggez_ctx: &GgEzContext,
+    ) -> Vec<(HexGridCell, ggez_gfx::Color, ggez_gfx::Color)> {
+        vec![(
+            HexGridCell::new_from_hex_coords(instance.origin(), crate::HEX_RADIUS_VERTEX, ggez_ctx),
             colors::from_resource(instance),
             colors::WHITE,
-            instance.radius(),
-            true,
-            mesh_builder,
-        );
+        )]
+    }
 
-        Ok(())
+    fn draw(&mut self, render_mode: RenderMode, ggez_ctx: &mut GgEzContext) {
+        // Draw influenced cells beneath the resources themselves
+        match render_mode {
+            RenderMode::Mesh => {
+                if let Some(influence_mesh) = &self.influence_mesh {
+                    ggez_gfx::draw(ggez_ctx, influence_mesh, ggez_gfx::DrawParam::default())
+                        .unwrap();
+                }
+                self.resource_mesh.draw(ggez_ctx);
+            }
+            RenderMode::Instanced => {
+                self.influence_batch.draw(ggez_ctx);
+                self.hex_batch.draw(ggez_ctx);
+            }
+        }
     }
 }
@@ -0,0 +1,276 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_event.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines a scheduled weather event with a known element, peak intensity,
+    start time and duration.
+
+    Each event passes through three phases: it builds up from nothing to its
+    peak intensity, holds at its peak, then fades out again. The length of the
+    build-up and fade-out are set as fractions of the event's duration.
+
+    Random events take their element, duration and peak intensity from
+    CastIron's weather events, but may also be built explicitly so that
+    scenarios and the console can schedule specific weather.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{fmt, time::Duration};
+
+use cast_iron::{
+    context::Context as CastIronContext,
+    element::{Element, Elemental},
+    mechanics::weather,
+    Randomizable,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Number of samples taken across a CastIron weather event to find its peak intensity
+const PEAK_SAMPLE_COUNT: u32 = 32;
+
+/// Default fractions of an event's duration spent building up and fading out
+pub const DEFAULT_BUILD_UP_FRACTION: f64 = 0.25;
+pub const DEFAULT_FADE_OUT_FRACTION: f64 = 0.25;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Phase of a weather event's lifetime
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Phase {
+    BuildUp, // Intensity rising towards the peak
+    Peak,    // Intensity held at the peak
+    FadeOut, // Intensity falling away to nothing
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WeatherEvent {
+    element: Element,    // Element of the weather
+    peak_intensity: f64, // Intensity held through the peak phase
+    start: Duration,     // Game time at which the event begins
+    duration: Duration,  // Length of the event
+    build_up: f64,       // Fraction of the duration spent building up to the peak
+    fade_out: f64,       // Fraction of the duration spent fading out from the peak
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl WeatherEvent {
+    /// Fully-qualified constructor - creates an event starting at time zero
+    pub fn new(element: Element, peak_intensity: f64, duration: Duration) -> Self {
+        Self {
+            element,
+            peak_intensity,
+            start: Duration::default(),
+            duration,
+            build_up: DEFAULT_BUILD_UP_FRACTION,
+            fade_out: DEFAULT_FADE_OUT_FRACTION,
+        }
+    }
+
+    /// Creates a random event starting at time zero, following CastIron's weather distributions
+    pub fn rand(ci_ctx: &CastIronContext) -> Self {
+        let ci_event = weather::Event::rand(ci_ctx).starting_at(Duration::default());
+
+        // Sample the CastIron event over its lifetime to find its peak
+        let duration_secs = ci_event.duration().as_secs_f64();
+        let peak_intensity = (0..=PEAK_SAMPLE_COUNT)
+            .map(|i| {
+                ci_event
+                    .intensity_exact(duration_secs * f64::from(i) / f64::from(PEAK_SAMPLE_COUNT))
+            })
+            .fold(0.0, f64::max);
+
+        Self::new(ci_event.element(), peak_intensity, ci_event.duration())
+    }
+
+    /// Returns the same event, rescheduled to start at the given game time
+    pub fn starting_at(mut self, start: Duration) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Returns the same event, with the given fractions of its duration spent building up and fading out
+    ///
+    /// Fractions are clamped so that the two transitions never exceed the whole duration.
+    pub fn with_transitions(mut self, build_up: f64, fade_out: f64) -> Self {
+        self.build_up = build_up.clamp(0.0, 1.0);
+        self.fade_out = fade_out.clamp(0.0, 1.0 - self.build_up);
+        self
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn peak_intensity(&self) -> f64 {
+        self.peak_intensity
+    }
+
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the game time at which the event ends
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+
+    pub fn build_up(&self) -> f64 {
+        self.build_up
+    }
+
+    pub fn fade_out(&self) -> f64 {
+        self.fade_out
+    }
+
+    /// Returns the length of the fade-out phase
+    pub fn fade_out_duration(&self) -> Duration {
+        self.duration.mul_f64(self.fade_out)
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns true if the event is underway at the given game time
+    pub fn is_active_at(&self, game_time: Duration) -> bool {
+        game_time >= self.start && game_time < self.end()
+    }
+
+    /// Returns the phase of the event at the given game time, or None outside of the event
+    pub fn phase_at(&self, game_time: Duration) -> Option<Phase> {
+        if !self.is_active_at(game_time) {
+            return None;
+        }
+
+        let progress = self.progress_at(game_time);
+        if progress < self.build_up {
+            Some(Phase::BuildUp)
+        } else if progress > 1.0 - self.fade_out {
+            Some(Phase::FadeOut)
+        } else {
+            Some(Phase::Peak)
+        }
+    }
+
+    /// Returns the intensity of the event at the given game time, or 0.0 outside of the event
+    pub fn intensity_at(&self, game_time: Duration) -> f64 {
+        if !self.is_active_at(game_time) {
+            return 0.0;
+        }
+
+        self.peak_intensity * envelope(self.progress_at(game_time), self.build_up, self.fade_out)
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the fraction of the event elapsed at the given game time
+    fn progress_at(&self, game_time: Duration) -> f64 {
+        game_time
+            .checked_sub(self.start)
+            .unwrap_or_default()
+            .as_secs_f64()
+            / self.duration.as_secs_f64()
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for WeatherEvent {
+    fn default() -> Self {
+        Self::new(Element::default(), 0.0, Duration::default())
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BuildUp => write!(f, "Build-up"),
+            Self::Peak => write!(f, "Peak"),
+            Self::FadeOut => write!(f, "Fade-out"),
+        }
+    }
+}
+
+impl fmt::Display for WeatherEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (peak {:.0}) at {:.1}s for {:.1}s",
+            String::from(self.element),
+            self.peak_intensity,
+            self.start.as_secs_f64(),
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the fraction of peak strength at the given progress through an event's lifetime
+///
+/// Strength eases in over the build-up fraction, holds at 1.0, then eases out over the fade-out fraction.
+pub fn envelope(progress: f64, build_up: f64, fade_out: f64) -> f64 {
+    if progress < 0.0 || progress >= 1.0 {
+        0.0
+    } else if progress < build_up {
+        smoothstep(progress / build_up)
+    } else if progress > 1.0 - fade_out {
+        smoothstep((1.0 - progress) / fade_out)
+    } else {
+        1.0
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Eases the given value between 0.0 and 1.0, with zero slope at either end
+fn smoothstep(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
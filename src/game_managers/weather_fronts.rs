@@ -0,0 +1,489 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_fronts.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Manages regional weather fronts, which drift across the world grid.
+
+    A front is a hexagonal region of cells with an element, an intensity and
+    a velocity. Its radius and intensity follow the build-up, peak and fade-out
+    phases of its weather event. Where fronts overlap, fronts of the same
+    element reinforce one another, while fronts of differing elements cancel
+    out, leaving the strongest element with whatever intensity remains. The
+    overlay cross-fades between the colours of overlapping elements.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+use cast_iron::{context::Context as CastIronContext, coords, element::Element, hex_directions};
+
+use ggez::{graphics as ggez_gfx, Context as GgEzContext};
+
+use mt_logger::{mt_log, Level};
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    game_assets::{colors, hex_grid_cell::HexGridCell},
+    game_managers::weather_event::WeatherEvent,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Range of the full radius (in cells) of a randomly-spawned front
+const FRONT_MIN_RADIUS: f64 = 2.0;
+const FRONT_MAX_RADIUS: f64 = 5.0;
+
+/// Alpha of an overlay cell at the maximum weather intensity
+const FRONT_OVERLAY_MAX_ALPHA: f32 = 0.5;
+
+/// Number of discrete levels to which each overlay color channel is rounded, so that the overlay
+/// mesh is only rebuilt when the colors visibly change
+const FRONT_OVERLAY_COLOR_LEVELS: f32 = 32.0;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A region of weather drifting across the grid
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeatherFront {
+    weather: WeatherEvent, // Weather carried by the front, which also sets its lifetime
+    peak_radius: f64,      // Radius of the front (in cells) at the height of its lifetime
+    origin: [f64; 3],      // Cube coordinates of the front's center at spawn time
+    velocity: [f64; 3],    // Cube-coordinate distance travelled per second
+}
+
+/// Combined weather of all fronts covering a cell
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CellWeather {
+    element: Element, // Dominant element in the cell
+    intensity: f64,   // Intensity of the dominant element, after opposing elements are subtracted
+}
+
+pub struct WeatherFrontManager {
+    fronts: Vec<WeatherFront>, // Active fronts, oldest first
+    cells: HashMap<coords::Position, CellWeather>, // Weather of each covered cell, as of the last update
+    overlay_colors: HashMap<coords::Position, ggez_gfx::Color>, // Cross-faded overlay color of each covered cell
+    overlay_dirty: bool, // Flag indicating the overlay mesh must be rebuilt
+    overlay_mesh: Option<ggez_gfx::Mesh>, // Mesh of all covered cells, None if there are none
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl WeatherFront {
+    /// Fully-qualified constructor
+    ///
+    /// The front spawns at the start of the given weather and lasts for its duration. Velocity is
+    /// given in cube coordinates per second, see velocity_towards().
+    pub fn new(
+        weather: WeatherEvent,
+        peak_radius: f64,
+        origin: &coords::Position,
+        velocity: [f64; 3],
+    ) -> Self {
+        Self {
+            weather,
+            peak_radius,
+            origin: [
+                f64::from(origin.x()),
+                f64::from(origin.y()),
+                f64::from(origin.z()),
+            ],
+            velocity,
+        }
+    }
+
+    /// Creates a front for the given weather event, entering from a random edge of the grid
+    ///
+    /// The front crosses the whole grid over the course of the event, so that it is at its
+    /// strongest while passing through the middle of the map.
+    pub fn rand_for_event(
+        event: &WeatherEvent,
+        grid_radius: usize,
+        ci_ctx: &CastIronContext,
+    ) -> Self {
+        let mut rng = rand::thread_rng();
+
+        let sides: Vec<hex_directions::Side> =
+            hex_directions::Provider::new(hex_directions::Side::North).collect();
+        let heading = *sides.choose(&mut rng).unwrap();
+        let unit = side_vector(heading, ci_ctx);
+
+        // Start on the edge opposite the heading, shifted sideways by a random amount
+        let lateral = side_vector(*sides.choose(&mut rng).unwrap(), ci_ctx);
+        // A grid of a single cell leaves no room to shift, and an empty range would panic
+        let lateral_offset = if grid_radius == 0 {
+            0.0
+        } else {
+            rng.gen_range(0.0, grid_radius as f64 / 2.0)
+        };
+        let backward_offset = grid_radius as f64 - lateral_offset;
+        let start = [
+            -unit[0] * backward_offset + lateral[0] * lateral_offset,
+            -unit[1] * backward_offset + lateral[1] * lateral_offset,
+            -unit[2] * backward_offset + lateral[2] * lateral_offset,
+        ];
+        let origin =
+            HexGridCell::hex_round(start[0] as f32, start[1] as f32, start[2] as f32, ci_ctx)
+                .unwrap_or_default();
+
+        let speed = 2.0 * grid_radius as f64 / event.duration().as_secs_f64().max(f64::EPSILON);
+
+        Self::new(
+            *event,
+            rng.gen_range(FRONT_MIN_RADIUS, FRONT_MAX_RADIUS),
+            &origin,
+            Self::velocity_towards(heading, speed, ci_ctx),
+        )
+    }
+
+    /// Returns the velocity of a front moving towards the given side at the given speed (in cells per second)
+    pub fn velocity_towards(
+        heading: hex_directions::Side,
+        speed: f64,
+        ci_ctx: &CastIronContext,
+    ) -> [f64; 3] {
+        let unit = side_vector(heading, ci_ctx);
+
+        [unit[0] * speed, unit[1] * speed, unit[2] * speed]
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn weather(&self) -> &WeatherEvent {
+        &self.weather
+    }
+
+    pub fn element(&self) -> Element {
+        self.weather.element()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns true if the front has dissipated by the given game time
+    pub fn dissipated_at(&self, game_time: Duration) -> bool {
+        game_time >= self.weather.end()
+    }
+
+    /// Returns the fraction of its peak strength the front has at the given game time
+    ///
+    /// Strength builds up from 0.0 at spawn, holds at 1.0 through the peak, then fades away.
+    pub fn strength_at(&self, game_time: Duration) -> f64 {
+        if self.weather.peak_intensity() <= 0.0 {
+            return 0.0;
+        }
+
+        self.intensity_at(game_time) / self.weather.peak_intensity()
+    }
+
+    /// Returns the intensity of the front's center at the given game time
+    pub fn intensity_at(&self, game_time: Duration) -> f64 {
+        self.weather.intensity_at(game_time)
+    }
+
+    /// Returns the radius (in cells) of the front at the given game time
+    pub fn radius_at(&self, game_time: Duration) -> usize {
+        (self.peak_radius * self.strength_at(game_time)).round() as usize
+    }
+
+    /// Returns the position of the front's center at the given game time
+    ///
+    /// Returns None once the center has drifted beyond the edge of the grid.
+    pub fn center_at(
+        &self,
+        game_time: Duration,
+        ci_ctx: &CastIronContext,
+    ) -> Option<coords::Position> {
+        let age = game_time
+            .checked_sub(self.weather.start())
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        HexGridCell::hex_round(
+            (self.origin[0] + self.velocity[0] * age) as f32,
+            (self.origin[1] + self.velocity[1] * age) as f32,
+            (self.origin[2] + self.velocity[2] * age) as f32,
+            ci_ctx,
+        )
+        .ok()
+    }
+
+    /// Returns each cell covered by the front at the given game time, with the front's intensity there
+    ///
+    /// Intensity falls off linearly from the center to the edge of the front.
+    pub fn cells_at(
+        &self,
+        game_time: Duration,
+        ci_ctx: &CastIronContext,
+    ) -> Vec<(coords::Position, f64)> {
+        let intensity = self.intensity_at(game_time);
+        if intensity <= 0.0 {
+            return Vec::new();
+        }
+
+        let center = match self.center_at(game_time, ci_ctx) {
+            Some(center) => center,
+            None => return Vec::new(),
+        };
+        let radius = self.radius_at(game_time);
+
+        HexGridCell::hex_range(&center, radius, ci_ctx)
+            .into_iter()
+            .map(|position| {
+                let distance = HexGridCell::hex_distance(&center, &position);
+                let falloff = 1.0 - (distance as f64 / (radius + 1) as f64);
+
+                (position, intensity * falloff)
+            })
+            .collect()
+    }
+}
+
+impl CellWeather {
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+}
+
+impl WeatherFrontManager {
+    /// Generic Constructor - creates an empty instance
+    pub fn new() -> Self {
+        Self {
+            fronts: Vec::new(),
+            cells: HashMap::new(),
+            overlay_colors: HashMap::new(),
+            overlay_dirty: false,
+            overlay_mesh: None,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the active fronts, oldest first
+    pub fn fronts(&self) -> &[WeatherFront] {
+        &self.fronts
+    }
+
+    /// Returns the weather at the given position, as of the last update, or None if no front covers it
+    pub fn weather_at(&self, position: &coords::Position) -> Option<CellWeather> {
+        self.cells.get(position).copied()
+    }
+
+    /// Returns every cell covered by weather, as of the last update
+    pub fn cells(&self) -> &HashMap<coords::Position, CellWeather> {
+        &self.cells
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Adds a front to the world
+    pub fn spawn_front(&mut self, front: WeatherFront) {
+        mt_log!(Level::Info, "Weather front spawned: {}", front.weather());
+
+        self.fronts.push(front);
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Moves all fronts to their positions at the given game time, dropping any that have dissipated
+    pub fn update(&mut self, game_time: Duration, ci_ctx: &CastIronContext) {
+        self.fronts.retain(|front| !front.dissipated_at(game_time));
+
+        // Total up the intensity of each element in each covered cell
+        let mut contributions: HashMap<coords::Position, Vec<(Element, f64)>> = HashMap::new();
+        for front in &self.fronts {
+            for (position, intensity) in front.cells_at(game_time, ci_ctx) {
+                let cell_contributions = contributions.entry(position).or_insert_with(Vec::new);
+                match cell_contributions
+                    .iter_mut()
+                    .find(|(element, _intensity)| *element == front.element())
+                {
+                    Some((_element, total)) => *total += intensity,
+                    None => cell_contributions.push((front.element(), intensity)),
+                }
+            }
+        }
+
+        self.cells = contributions
+            .iter()
+            .filter_map(|(position, cell_contributions)| {
+                combine(cell_contributions, ci_ctx.max_weather_intensity())
+                    .map(|cell_weather| (*position, cell_weather))
+            })
+            .collect();
+
+        // The overlay blends every element present, so that transitions between fronts cross-fade
+        let overlay_colors: HashMap<coords::Position, ggez_gfx::Color> = contributions
+            .iter()
+            .map(|(position, cell_contributions)| {
+                (
+                    *position,
+                    overlay_color(cell_contributions, ci_ctx.max_weather_intensity()),
+                )
+            })
+            .collect();
+
+        if overlay_colors != self.overlay_colors {
+            self.overlay_colors = overlay_colors;
+            self.overlay_dirty = true;
+        }
+    }
+
+    /// Rebuilds the overlay mesh if the covered cells have changed since the last rebuild
+    pub fn update_geometry(&mut self, ggez_ctx: &mut GgEzContext) {
+        if !self.overlay_dirty {
+            return;
+        }
+
+        let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+        for (position, fill_color) in &self.overlay_colors {
+            let hex_cell =
+                HexGridCell::new_from_hex_coords(position, crate::HEX_RADIUS_VERTEX, ggez_ctx);
+            hex_cell.add_to_mesh(*fill_color, colors::TRANSPARENT, &mut mesh_builder);
+        }
+
+        // Building fails if no cells were added, which simply leaves nothing to draw
+        self.overlay_mesh = mesh_builder.build(ggez_ctx).ok();
+        self.overlay_dirty = false;
+    }
+
+    pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        if let Some(overlay_mesh) = &self.overlay_mesh {
+            ggez_gfx::draw(ggez_ctx, overlay_mesh, ggez_gfx::DrawParam::default()).unwrap();
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for WeatherFrontManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for CellWeather {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} weather of intensity {:.0}",
+            String::from(self.element),
+            self.intensity
+        )
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the cube-coordinate vector of a single step towards the given side
+fn side_vector(side: hex_directions::Side, ci_ctx: &CastIronContext) -> [f64; 3] {
+    let mut step = coords::Position::default();
+    match step.translate(&coords::Translation::from(side), ci_ctx) {
+        Ok(()) => [
+            f64::from(step.x()),
+            f64::from(step.y()),
+            f64::from(step.z()),
+        ],
+        Err(_e) => [0.0; 3],
+    }
+}
+
+/// Returns the quantized overlay color of a cell, blending each element present by its intensity
+fn overlay_color(contributions: &[(Element, f64)], max_intensity: f64) -> ggez_gfx::Color {
+    let weighted_elements: Vec<(Element, f32)> = contributions
+        .iter()
+        .map(|(element, intensity)| (*element, *intensity as f32))
+        .collect();
+    let total: f64 = contributions
+        .iter()
+        .map(|(_element, intensity)| intensity)
+        .sum();
+
+    let mut fill_color = colors::blend_elements(&weighted_elements);
+    fill_color.a = (total / max_intensity).min(1.0) as f32 * FRONT_OVERLAY_MAX_ALPHA;
+
+    // Quantize the color, as it changes slightly every step while fronts drift
+    let quantize =
+        |channel: f32| (channel * FRONT_OVERLAY_COLOR_LEVELS).round() / FRONT_OVERLAY_COLOR_LEVELS;
+    ggez_gfx::Color::new(
+        quantize(fill_color.r),
+        quantize(fill_color.g),
+        quantize(fill_color.b),
+        quantize(fill_color.a),
+    )
+}
+
+/// Combines the total intensity of each element in a cell into the cell's weather
+///
+/// The element with the greatest total dominates, weakened by the totals of all other elements.
+/// Returns None if the elements cancel out entirely.
+fn combine(contributions: &[(Element, f64)], max_intensity: f64) -> Option<CellWeather> {
+    let total: f64 = contributions
+        .iter()
+        .map(|(_element, intensity)| intensity)
+        .sum();
+    let (element, dominant) = contributions.iter().copied().fold(
+        None,
+        |strongest: Option<(Element, f64)>, contribution| match strongest {
+            Some(strongest) if strongest.1 >= contribution.1 => Some(strongest),
+            _ => Some(contribution),
+        },
+    )?;
+
+    let intensity = (dominant - (total - dominant)).min(max_intensity);
+    if intensity > 0.0 {
+        Some(CellWeather { element, intensity })
+    } else {
+        None
+    }
+}
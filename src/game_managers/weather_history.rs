@@ -0,0 +1,261 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/weather_history.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Records a rolling history of the weather intensity over the last few
+    seconds of game time, along with the times at which weather events changed.
+
+    The history backs the intensity graph in the weather HUD, and may be
+    queried directly to check the shape of weather curves over time.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use cast_iron::element::Element;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Minimum game time between consecutive samples, in seconds
+const HISTORY_SAMPLE_INTERVAL: f64 = 0.1;
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Weather intensity at a single point in game time
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WeatherSample {
+    time: Duration,   // Game time at which the sample was taken
+    element: Element, // Element of the active weather
+    intensity: f64,   // Combined intensity of the active and outgoing weather
+}
+
+/// Change of weather event at a single point in game time
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WeatherMarker {
+    time: Duration,   // Game time at which the new event began
+    element: Element, // Element of the new event
+}
+
+pub struct WeatherHistory {
+    window: Duration,                 // Length of game time covered by the history
+    samples: VecDeque<WeatherSample>, // Samples within the window, oldest first
+    markers: VecDeque<WeatherMarker>, // Event changes within the window, oldest first
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl WeatherSample {
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+}
+
+impl WeatherMarker {
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+}
+
+impl WeatherHistory {
+    /// Fully-qualified constructor
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+            markers: VecDeque::new(),
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the length of game time covered by the history
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns the recorded samples, oldest first
+    pub fn samples(&self) -> &VecDeque<WeatherSample> {
+        &self.samples
+    }
+
+    /// Returns the recorded event changes, oldest first
+    pub fn markers(&self) -> &VecDeque<WeatherMarker> {
+        &self.markers
+    }
+
+    /// Returns the most recent sample, if any
+    pub fn latest(&self) -> Option<&WeatherSample> {
+        self.samples.back()
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Sets the length of game time covered by the history, discarding anything older
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+
+        if let Some(latest_time) = self.latest().map(WeatherSample::time) {
+            self.prune(latest_time);
+        }
+    }
+
+    /// Discards all samples and markers
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.markers.clear();
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Records the weather at the given game time, returning true if a new sample was taken
+    ///
+    /// Samples are taken no more often than the sample interval, unless the element has changed.
+    pub fn record(&mut self, game_time: Duration, element: Element, intensity: f64) -> bool {
+        if let Some(latest) = self.latest() {
+            let since_latest = game_time.checked_sub(latest.time).unwrap_or_default();
+            if since_latest.as_secs_f64() < HISTORY_SAMPLE_INTERVAL && latest.element == element {
+                return false;
+            }
+        }
+
+        self.samples.push_back(WeatherSample {
+            time: game_time,
+            element,
+            intensity,
+        });
+        self.prune(game_time);
+
+        true
+    }
+
+    /// Records that a new weather event of the given element began at the given game time
+    pub fn mark_change(&mut self, game_time: Duration, element: Element) {
+        self.markers.push_back(WeatherMarker {
+            time: game_time,
+            element,
+        });
+        self.prune(game_time);
+    }
+
+    /// Returns the samples taken between the given game times, inclusive
+    pub fn samples_between(
+        &self,
+        from: Duration,
+        to: Duration,
+    ) -> impl Iterator<Item = &WeatherSample> {
+        self.samples
+            .iter()
+            .filter(move |sample| sample.time >= from && sample.time <= to)
+    }
+
+    /// Returns the intensity of the latest sample taken at or before the given game time
+    pub fn intensity_at(&self, game_time: Duration) -> Option<f64> {
+        self.samples
+            .iter()
+            .rev()
+            .find(|sample| sample.time <= game_time)
+            .map(WeatherSample::intensity)
+    }
+
+    /// Returns the highest intensity sampled between the given game times, inclusive
+    pub fn peak_between(&self, from: Duration, to: Duration) -> Option<f64> {
+        self.samples_between(from, to)
+            .map(WeatherSample::intensity)
+            .fold(None, |peak, intensity| {
+                Some(peak.map_or(intensity, |peak: f64| peak.max(intensity)))
+            })
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Discards samples and markers which have fallen out of the window before the given game time
+    fn prune(&mut self, game_time: Duration) {
+        let window_start = game_time.checked_sub(self.window).unwrap_or_default();
+
+        while self
+            .samples
+            .front()
+            .map_or(false, |sample| sample.time < window_start)
+        {
+            self.samples.pop_front();
+        }
+        while self
+            .markers
+            .front()
+            .map_or(false, |marker| marker.time < window_start)
+        {
+            self.markers.pop_front();
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Default for WeatherHistory {
+    fn default() -> Self {
+        Self::new(Duration::from_secs_f64(
+            crate::DEFAULT_WEATHER_HISTORY_WINDOW,
+        ))
+    }
+}
+
+impl fmt::Display for WeatherSample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.1}s: {} {:.0}",
+            self.time.as_secs_f64(),
+            String::from(self.element),
+            self.intensity
+        )
+    }
+}
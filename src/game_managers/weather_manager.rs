@@ -18,20 +18,39 @@ Purpose:
     This module manages weather effects over the course of the game, including
     but not limited to generating random weather events.
 
+    Upcoming events are pre-generated into a forecast queue, so that they may
+    be shown to the player ahead of time. Specific events may be injected into
+    the forecast by scenarios or the console.
+
+    Consecutive events may overlap, with the next event building up while the
+    previous one fades out. The HUD cross-fades between their elements.
+
+    A rolling history of the combined intensity is kept, and graphed in the
+    HUD with markers where each event began.
+
+    Weather may instead follow a scripted timeline, in which case events are
+    played back at exactly their scripted times until the timeline finishes.
+
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
-use cast_iron::{
-    context::Context as CastIronContext,
-    element::{Element, Elemental},
-    mechanics::weather,
-    Randomizable,
-};
+use std::{collections::VecDeque, error::Error, fmt, time::Duration};
+
+use cast_iron::{context::Context as CastIronContext, element::Element};
 
-use ggez::{graphics as ggez_gfx, mint as ggez_mint, timer as ggez_timer, Context as GgEzContext};
+use ggez::{graphics as ggez_gfx, mint as ggez_mint, Context as GgEzContext};
 
 use mt_logger::{mt_log, Level};
 
-use crate::{game_assets::colors, profiler};
+use crate::{
+    event_bus::{self, GameEvent},
+    game_assets::colors,
+    game_managers::{
+        weather_event::{Phase, WeatherEvent},
+        weather_history::WeatherHistory,
+    },
+    profiler,
+    weather_timeline::{TimelinePlayback, WeatherTimeline},
+};
 
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -46,6 +65,19 @@ const HUD_OUTLINE_LINE_COLOR: ggez_gfx::Color = colors::MAGENTA;
 // Offset of text from HUD frame
 const HUD_TEXT_OFFSET: f32 = 5.0;
 
+// Number of discrete intensity levels at which the HUD colour and text are refreshed
+const HUD_INTENSITY_LEVELS: f64 = 32.0;
+
+// Size of, and spacing between, the element swatches in the forecast strip
+const HUD_FORECAST_SWATCH_SIZE: f32 = 12.0;
+const HUD_FORECAST_ROW_SPACING: f32 = 20.0;
+
+// Size and line features of the intensity history graph
+const HUD_HISTORY_WIDTH: f32 = 200.0;
+const HUD_HISTORY_HEIGHT: f32 = 40.0;
+const HUD_HISTORY_LINE_WIDTH: f32 = 2.0;
+const HUD_HISTORY_MARKER_WIDTH: f32 = 1.0;
+
 
 ///////////////////////////////////////////////////////////////////////////////
 // Data Structures
@@ -53,9 +85,17 @@ const HUD_TEXT_OFFSET: f32 = 5.0;
 
 pub struct WeatherManager {
     profiler: profiler::Instance,
-    active_weather: weather::Event,
-    timeout_ms: u128,
-    prev_intensity: weather::Intensity,
+    events: event_bus::Publisher,
+    active_weather: WeatherEvent,
+    outgoing_weather: Option<WeatherEvent>, // Previous event, while it fades out under the active one
+    overlap: Duration,                      // Time by which consecutive events may overlap
+    forecast: VecDeque<WeatherEvent>,       // Upcoming events, in the order they will occur
+    forecast_length: usize,                 // Number of upcoming events kept pre-generated
+    forecast_dirty: bool,                   // Whether the forecast strip needs rebuilding
+    prev_hud_state: (u32, u32, Option<Phase>), // Intensity level, cross-fade level and phase last shown
+    history: WeatherHistory,                   // Rolling record of intensity over recent game time
+    timeline: Option<TimelinePlayback>,        // Scripted timeline being played back, if any
+    scripted_count: usize, // Number of scripted events at the front of the forecast
     hud_elements: HudElements,
 }
 
@@ -71,6 +111,16 @@ struct HudElements {
     pub text_int_pos: ggez_mint::Point2<f32>,
     pub text_int_str: String,
     pub text_int_obj: ggez_gfx::Text,
+    pub forecast_pos: ggez_mint::Point2<f32>,
+    pub forecast_mesh: ggez_gfx::Mesh,
+    pub forecast_text_objs: Vec<ggez_gfx::Text>,
+    pub history_pos: ggez_mint::Point2<f32>,
+    pub history_mesh: ggez_gfx::Mesh,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WeatherManagerError {
+    TimelinePlaying(WeatherEvent),
 }
 
 
@@ -82,39 +132,170 @@ impl WeatherManager {
     /// Fully-qualified constructor
     pub fn new(
         profiler_original: &profiler::Instance,
-        active_weather: weather::Event,
-        timeout_ms: u128,
+        events: event_bus::Publisher,
+        active_weather: WeatherEvent,
+        forecast_length: usize,
         ci_ctx: &CastIronContext,
         ggez_ctx: &mut GgEzContext,
     ) -> Self {
         // Clone the profiler instances for use by this module
         let profiler_clone = profiler_original.clone();
 
-        WeatherManager {
+        let mut weather_manager = WeatherManager {
             profiler: profiler_clone,
+            events,
             active_weather,
-            timeout_ms,
-            prev_intensity: weather::Intensity::default(),
+            outgoing_weather: None,
+            overlap: Duration::from_secs_f64(crate::DEFAULT_WEATHER_TRANSITION_OVERLAP),
+            forecast: VecDeque::with_capacity(forecast_length),
+            forecast_length,
+            forecast_dirty: true,
+            prev_hud_state: (u32::default(), u32::default(), None),
+            history: WeatherHistory::default(),
+            timeline: None,
+            scripted_count: 0,
             hud_elements: HudElements::default(ci_ctx, ggez_ctx),
-        }
+        };
+        weather_manager.refill_forecast(ci_ctx);
+
+        weather_manager
     }
 
     /// Default constructor
     pub fn default(
         profiler_original: &profiler::Instance,
+        events: event_bus::Publisher,
         ci_ctx: &CastIronContext,
         ggez_ctx: &mut GgEzContext,
     ) -> Self {
-        // Clone the profiler instances for use by this module
-        let profiler_clone = profiler_original.clone();
+        Self::new(
+            profiler_original,
+            events,
+            WeatherEvent::default(),
+            crate::DEFAULT_WEATHER_FORECAST_LENGTH,
+            ci_ctx,
+            ggez_ctx,
+        )
+    }
 
-        WeatherManager {
-            profiler: profiler_clone,
-            active_weather: weather::Event::default(),
-            timeout_ms: u128::default(),
-            prev_intensity: weather::Intensity::default(),
-            hud_elements: HudElements::default(ci_ctx, ggez_ctx),
+
+    /*  *  *  *  *  *  *  *
+     *  Accessor Methods  *
+     *  *  *  *  *  *  *  */
+
+    pub fn active_weather(&self) -> &WeatherEvent {
+        &self.active_weather
+    }
+
+    /// Returns the previous weather event, if it is still fading out
+    pub fn outgoing_weather(&self) -> Option<&WeatherEvent> {
+        self.outgoing_weather.as_ref()
+    }
+
+    /// Returns the upcoming weather events, in the order they will occur
+    pub fn forecast(&self) -> &VecDeque<WeatherEvent> {
+        &self.forecast
+    }
+
+    pub fn transition_overlap(&self) -> Duration {
+        self.overlap
+    }
+
+    /// Returns the scripted timeline being played back, if any
+    pub fn timeline(&self) -> Option<&TimelinePlayback> {
+        self.timeline.as_ref()
+    }
+
+    /// Returns the rolling history of weather intensity
+    pub fn history(&self) -> &WeatherHistory {
+        &self.history
+    }
+
+
+    /*  *  *  *  *  *  *  *
+     *  Mutator Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Inserts the given event into the forecast at the given index, clamped to the end of the queue
+    ///
+    /// The injected event and all those after it are rescheduled to follow on from the event before
+    /// them. Injected events are never discarded, so the queue may grow beyond the forecast length.
+    /// Scripted events keep their times, so injections are refused while a timeline is playing, and
+    /// otherwise placed after any scripted events still in the forecast.
+    pub fn inject_forecast(
+        &mut self,
+        index: usize,
+        event: WeatherEvent,
+    ) -> Result<(), WeatherManagerError> {
+        if self.timeline.is_some() {
+            return Err(WeatherManagerError::TimelinePlaying(event));
         }
+
+        let index = index.clamp(self.scripted_count, self.forecast.len());
+
+        mt_log!(
+            Level::Info,
+            "Injecting weather event {} into forecast at position {}",
+            event,
+            index
+        );
+
+        self.forecast.insert(index, event);
+        self.retime_forecast(index);
+
+        Ok(())
+    }
+
+    /// Sets the time by which each event begins before the previous one ends
+    ///
+    /// The overlap never exceeds the fade-out of the previous event, so an event's peak is never shared.
+    pub fn set_transition_overlap(&mut self, overlap: Duration) {
+        self.overlap = overlap;
+        self.retime_forecast(0);
+    }
+
+    /// Sets the length of game time covered by the intensity history
+    pub fn set_history_window(&mut self, window: Duration) {
+        self.history.set_window(window);
+    }
+
+    /// Plays back the given timeline from the given game time, replacing the current forecast
+    pub fn play_timeline(
+        &mut self,
+        timeline: WeatherTimeline,
+        origin: Duration,
+        ci_ctx: &CastIronContext,
+    ) {
+        mt_log!(
+            Level::Info,
+            "Playing weather timeline of {} events from {:.3}s, then {}",
+            timeline.events().len(),
+            origin.as_secs_f64(),
+            timeline.end()
+        );
+
+        self.forecast.clear();
+        self.scripted_count = 0;
+        self.timeline = Some(TimelinePlayback::new(timeline, origin));
+        self.refill_forecast(ci_ctx);
+    }
+
+    /// Injects the given events at the front of the forecast, so that they occur next and in order
+    ///
+    /// Either all of the events are injected, or none are.
+    pub fn schedule_next(&mut self, events: &[WeatherEvent]) -> Result<(), WeatherManagerError> {
+        // Refuse up front, so that a refusal leaves the forecast unchanged
+        if self.timeline.is_some() {
+            if let Some(first_event) = events.first() {
+                return Err(WeatherManagerError::TimelinePlaying(*first_event));
+            }
+        }
+
+        for (index, event) in events.iter().enumerate() {
+            self.inject_forecast(index, *event)?;
+        }
+
+        Ok(())
     }
 
 
@@ -122,81 +303,218 @@ impl WeatherManager {
      *  Utility Methods   *
      *  *  *  *  *  *  *  */
 
-    /// Updates the active weather if the current effect has timed out
-    pub fn update_weather(&mut self, ci_ctx: &CastIronContext, ggez_ctx: &mut GgEzContext) {
-        //OPT: *PERFORMANCE* Would it be faster to use 2 usizes for seconds and milli/nanoseconds?
-        let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
+    /// Updates the active weather if the next forecasted event is due to begin by the given game time
+    pub fn update_weather(
+        &mut self,
+        elapsed_time: Duration,
+        ci_ctx: &CastIronContext,
+        ggez_ctx: &mut GgEzContext,
+    ) {
         let mut new_weather_generated = false;
 
-        // If current weather has timed out, randomly generate a new weather pattern
-        if elapsed_time.as_millis() >= self.timeout_ms {
-            // Send WEATHER_GEN event marker to profiler
-            self.profiler
-                .mark_event(String::from("WEATHER_GEN_START"), ggez_ctx)
-                .unwrap();
+        // Drop the outgoing weather once it has faded out completely
+        if self
+            .outgoing_weather
+            .map_or(false, |outgoing| elapsed_time >= outgoing.end())
+        {
+            self.outgoing_weather = None;
+        }
 
-            self.active_weather = weather::Event::rand(ci_ctx).starting_at(elapsed_time);
+        // If the next event is due, move on to it, letting the current weather fade out underneath
+        // NOTE: The forecast is never empty, as it is refilled whenever an event is taken from it
+        if elapsed_time >= self.forecast[0].start() {
+            // Mark the start of generation for the weather change in the profiler
+            let (gen_start_label, gen_stop_label) =
+                GameEvent::WeatherChanged(self.forecast[0]).marker_span_labels("gen");
+            self.profiler.mark_event(gen_start_label, ggez_ctx).unwrap();
+
+            // Scripted events play at exactly their scripted time, others from when they are taken
+            let next_weather = if self.scripted_count > 0 {
+                self.scripted_count -= 1;
+                self.forecast.pop_front().unwrap()
+            } else {
+                self.forecast.pop_front().unwrap().starting_at(elapsed_time)
+            };
+            let prev_weather = std::mem::replace(&mut self.active_weather, next_weather);
+            if elapsed_time < prev_weather.end() {
+                self.outgoing_weather = Some(prev_weather);
+            }
 
             // Log weather change
             mt_log!(
                 Level::Info,
-                "GameTime: {:.3}s: Weather changed to Elem: {:?}, Duration: {:.3}s",
+                "GameTime: {:.3}s: Weather changed to Elem: {:?}, Peak: {:.1}, Duration: {:.3}s",
                 elapsed_time.as_secs_f64(),
                 self.active_weather.element(),
+                self.active_weather.peak_intensity(),
                 self.active_weather.duration().as_secs_f64()
             );
 
-            // Set the timeout to the duration of the new weather pattern
-            self.timeout_ms = elapsed_time.as_millis() + self.active_weather.duration().as_millis();
+            self.events
+                .publish(GameEvent::WeatherChanged(self.active_weather));
+            self.history
+                .mark_change(elapsed_time, self.active_weather.element());
+
+            // Keep the forecast topped up and following on from the new event
+            self.retime_forecast(0);
+            self.refill_forecast(ci_ctx);
 
             new_weather_generated = true;
 
-            // Send WEATHER_GEN event marker to profiler
-            self.profiler
-                .mark_event(String::from("WEATHER_GEN_STOP"), ggez_ctx)
-                .unwrap();
+            // Mark the end of generation in the profiler
+            self.profiler.mark_event(gen_stop_label, ggez_ctx).unwrap();
         }
 
-        // Check for change in weather event
-        let cur_intensity = self.active_weather.intensity(elapsed_time.as_secs_f64());
-        if self.prev_intensity != cur_intensity || new_weather_generated {
-            // Send WEATHER_GEN event marker to profiler
-            self.profiler
-                .mark_event(String::from("WEATHER_CHANGE_START"), ggez_ctx)
-                .unwrap();
-
-            // Update HUD content with new alpha level
-            let mut content_color = colors::from_element(self.active_weather.element());
-            content_color.a = cur_intensity.to_alpha();
+        // Check for change in weather event, combining the active and outgoing weather
+        let incoming_intensity = self.active_weather.intensity_at(elapsed_time);
+        let outgoing_intensity = self
+            .outgoing_weather
+            .map_or(0.0, |outgoing| outgoing.intensity_at(elapsed_time));
+        let cur_intensity =
+            (incoming_intensity + outgoing_intensity).min(ci_ctx.max_weather_intensity());
+        let incoming_share = if incoming_intensity + outgoing_intensity > 0.0 {
+            incoming_intensity / (incoming_intensity + outgoing_intensity)
+        } else {
+            1.0
+        };
+        let cur_phase = self.active_weather.phase_at(elapsed_time);
+        let cur_hud_state = (
+            (cur_intensity / ci_ctx.max_weather_intensity() * HUD_INTENSITY_LEVELS) as u32,
+            (incoming_share * HUD_INTENSITY_LEVELS) as u32,
+            cur_phase,
+        );
+        if self.prev_hud_state != cur_hud_state || new_weather_generated {
+            // Mark the start of the HUD update for the current weather in the profiler
+            let (hud_start_label, hud_stop_label) =
+                GameEvent::WeatherChanged(self.active_weather).marker_span_labels("hud");
+            self.profiler.mark_event(hud_start_label, ggez_ctx).unwrap();
+
+            // Update HUD content, cross-fading from the outgoing element to the active one
+            let mut weighted_elements =
+                vec![(self.active_weather.element(), incoming_share as f32)];
+            if let Some(outgoing) = self.outgoing_weather {
+                weighted_elements.push((outgoing.element(), (1.0 - incoming_share) as f32));
+            }
+            // NOTE: Without any weather (e.g. before a scripted timeline's first event) there is
+            //       no element to colour by, which would otherwise be the unset element
+            let mut content_color = if cur_intensity > 0.0 {
+                colors::blend_elements(&weighted_elements)
+            } else {
+                colors::TRANSPARENT
+            };
+            content_color.a = (cur_intensity / ci_ctx.max_weather_intensity()) as f32;
             self.hud_elements
                 .update_content_mesh(content_color, ggez_ctx);
 
-            // Update intensity text
+            // Update element and intensity text
+            let element_str = match self.outgoing_weather {
+                Some(outgoing) => format!(
+                    "{} > {}",
+                    String::from(outgoing.element()),
+                    String::from(self.active_weather.element())
+                ),
+                None => String::from(self.active_weather.element()),
+            };
             self.hud_elements
-                .update_text_elements(self.active_weather.element(), cur_intensity);
+                .update_text_elements(element_str, cur_intensity, cur_phase);
 
             // Update previous-state values
-            self.prev_intensity = self.active_weather.intensity(elapsed_time.as_secs_f64());
+            self.prev_hud_state = cur_hud_state;
 
-            // Send WEATHER_GEN event marker to profiler
-            self.profiler
-                .mark_event(String::from("WEATHER_CHANGE_STOP"), ggez_ctx)
-                .unwrap();
+            // Mark the end of the HUD update in the profiler
+            self.profiler.mark_event(hud_stop_label, ggez_ctx).unwrap();
+        }
+
+        // Update forecast strip
+        if self.forecast_dirty {
+            self.hud_elements
+                .update_forecast_elements(&self.forecast, ci_ctx, ggez_ctx);
+            self.forecast_dirty = false;
         }
 
         // Update intensity bar
-        self.hud_elements.update_int_bar_mesh(
-            self.active_weather
-                .intensity_exact(elapsed_time.as_secs_f64()),
-            ci_ctx,
-            ggez_ctx,
-        );
+        self.hud_elements
+            .update_int_bar_mesh(cur_intensity, ci_ctx, ggez_ctx);
+
+        // Update intensity history graph whenever a new sample is taken
+        if self
+            .history
+            .record(elapsed_time, self.active_weather.element(), cur_intensity)
+        {
+            self.hud_elements
+                .update_history_mesh(&self.history, elapsed_time, ci_ctx, ggez_ctx);
+        }
     }
 
     pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
         // Draw HUD elements
         self.hud_elements.draw(ggez_ctx);
     }
+
+
+    /*  *  *  *  *  *  *  *
+     *   Helper Methods   *
+     *  *  *  *  *  *  *  */
+
+    /// Adds events onto the end of the forecast until it reaches the forecast length
+    ///
+    /// Events are taken from the timeline while one is playing, and are otherwise generated randomly.
+    fn refill_forecast(&mut self, ci_ctx: &CastIronContext) {
+        while self.forecast.len() < self.forecast_length {
+            let event = match self.timeline.as_mut() {
+                Some(playback) => match playback.next_event() {
+                    Some(scripted_event) => {
+                        self.scripted_count += 1;
+                        scripted_event
+                    }
+                    None => {
+                        mt_log!(
+                            Level::Info,
+                            "Weather timeline finished, falling back to random weather"
+                        );
+                        self.timeline = None;
+                        continue;
+                    }
+                },
+                None => WeatherEvent::rand(ci_ctx).starting_at(self.forecast_end()),
+            };
+
+            self.forecast.push_back(event);
+            self.forecast_dirty = true;
+        }
+    }
+
+    /// Reschedules forecast events from the given index onward to follow one another without gaps
+    ///
+    /// Scripted events are never rescheduled.
+    fn retime_forecast(&mut self, from_index: usize) {
+        let from_index = from_index.max(self.scripted_count);
+        let overlap = self.overlap;
+        let mut start = match from_index {
+            0 => Self::next_start_after(&self.active_weather, overlap),
+            _ => Self::next_start_after(&self.forecast[from_index - 1], overlap),
+        };
+
+        for event in self.forecast.iter_mut().skip(from_index) {
+            *event = event.starting_at(start);
+            start = Self::next_start_after(event, overlap);
+        }
+
+        self.forecast_dirty = true;
+    }
+
+    /// Returns the game time at which the event following the last forecasted event begins
+    fn forecast_end(&self) -> Duration {
+        Self::next_start_after(
+            self.forecast.back().unwrap_or(&self.active_weather),
+            self.overlap,
+        )
+    }
+
+    /// Returns the game time at which the event following the given event begins
+    fn next_start_after(event: &WeatherEvent, overlap: Duration) -> Duration {
+        event.end() - overlap.min(event.fade_out_duration())
+    }
 }
 
 
@@ -263,13 +581,61 @@ impl HudElements {
             },
             text_int_str: String::default(),
             text_int_obj: ggez_gfx::Text::default(),
+            forecast_pos: ggez_mint::Point2 {
+                x: calc_frame_pos.x,
+                y: calc_frame_pos.y
+                    + calc_frame_size
+                    + (3.0 * HUD_TEXT_OFFSET)
+                    + crate::DEFAULT_TEXT_SIZE
+                    + HUD_HISTORY_HEIGHT
+                    + HUD_FORECAST_ROW_SPACING,
+            },
+            forecast_mesh: ggez_gfx::MeshBuilder::new()
+                .line(
+                    &[
+                        ggez_mint::Point2 { x: 0.0, y: 0.0 },
+                        ggez_mint::Point2 { x: 10.0, y: 10.0 },
+                    ],
+                    crate::DEFAULT_LINE_WIDTH,
+                    crate::DEFAULT_LINE_COLOR,
+                )
+                .unwrap()
+                .build(ggez_ctx)
+                .unwrap(),
+            forecast_text_objs: Vec::new(),
+            history_pos: ggez_mint::Point2 {
+                x: calc_frame_pos.x,
+                y: calc_frame_pos.y
+                    + calc_frame_size
+                    + (2.0 * HUD_TEXT_OFFSET)
+                    + crate::DEFAULT_TEXT_SIZE,
+            },
+            history_mesh: ggez_gfx::MeshBuilder::new()
+                .line(
+                    &[
+                        ggez_mint::Point2 { x: 0.0, y: 0.0 },
+                        ggez_mint::Point2 { x: 10.0, y: 10.0 },
+                    ],
+                    crate::DEFAULT_LINE_WIDTH,
+                    crate::DEFAULT_LINE_COLOR,
+                )
+                .unwrap()
+                .build(ggez_ctx)
+                .unwrap(),
         };
 
         // Do first 'updates' of the meshes so we have valid meshes from first use
         hud_elements.update_frame_mesh(ggez_ctx);
         hud_elements.update_content_mesh(colors::TRANSPARENT, ggez_ctx);
         hud_elements.update_int_bar_mesh(f64::default(), ci_ctx, ggez_ctx);
-        hud_elements.update_text_elements(Element::default(), weather::Intensity::default());
+        hud_elements.update_text_elements(String::from(Element::default()), f64::default(), None);
+        hud_elements.update_forecast_elements(&VecDeque::new(), ci_ctx, ggez_ctx);
+        hud_elements.update_history_mesh(
+            &WeatherHistory::default(),
+            Duration::default(),
+            ci_ctx,
+            ggez_ctx,
+        );
 
         hud_elements
     }
@@ -304,6 +670,29 @@ impl HudElements {
 
         // Draw intensity bar
         ggez_gfx::draw(ggez_ctx, &self.int_bar_mesh, ggez_gfx::DrawParam::default()).unwrap();
+
+        // Draw intensity history graph
+        ggez_gfx::draw(ggez_ctx, &self.history_mesh, ggez_gfx::DrawParam::default()).unwrap();
+
+        // Draw forecast strip, with each row of text beside its swatch
+        ggez_gfx::draw(
+            ggez_ctx,
+            &self.forecast_mesh,
+            ggez_gfx::DrawParam::default(),
+        )
+        .unwrap();
+        for (i, forecast_text_obj) in self.forecast_text_objs.iter().enumerate() {
+            let forecast_text_pos = ggez_mint::Point2 {
+                x: self.forecast_pos.x + HUD_FORECAST_SWATCH_SIZE + HUD_TEXT_OFFSET,
+                y: self.forecast_pos.y + (i as f32 * HUD_FORECAST_ROW_SPACING),
+            };
+            ggez_gfx::draw(
+                ggez_ctx,
+                forecast_text_obj,
+                (forecast_text_pos, 0.0, colors::GREEN),
+            )
+            .unwrap();
+        }
     }
 
     /// Updates the frame mesh for the HUD (just a square outline for now)
@@ -379,10 +768,152 @@ impl HudElements {
             .unwrap();
     }
 
+    /// Updates the forecast strip, with one row per upcoming event
+    fn update_forecast_elements(
+        &mut self,
+        forecast: &VecDeque<WeatherEvent>,
+        ci_ctx: &CastIronContext,
+        ggez_ctx: &mut GgEzContext,
+    ) {
+        // Need a mesh builder with a dummy line to avoid an empty mesh
+        let mut forecast_mesh_builder = ggez_gfx::MeshBuilder::new();
+        let dummy_line = [
+            ggez_mint::Point2 { x: 0.0, y: 0.0 },
+            ggez_mint::Point2 { x: 1.0, y: 1.0 },
+        ];
+        forecast_mesh_builder
+            .line(&dummy_line, 1.0, colors::TRANSPARENT)
+            .unwrap();
+
+        self.forecast_text_objs.clear();
+        for (i, event) in forecast.iter().enumerate() {
+            // Swatch of the event's element, as opaque as its peak is intense
+            let mut swatch_color = colors::from_element(event.element());
+            swatch_color.a = (event.peak_intensity() / ci_ctx.max_weather_intensity()) as f32;
+            let swatch_rect = ggez_gfx::Rect::new(
+                self.forecast_pos.x,
+                self.forecast_pos.y + (i as f32 * HUD_FORECAST_ROW_SPACING),
+                HUD_FORECAST_SWATCH_SIZE,
+                HUD_FORECAST_SWATCH_SIZE,
+            );
+            forecast_mesh_builder
+                .rectangle(ggez_gfx::DrawMode::fill(), swatch_rect, swatch_color)
+                .unwrap();
+            forecast_mesh_builder
+                .rectangle(
+                    ggez_gfx::DrawMode::stroke(1.0),
+                    swatch_rect,
+                    HUD_OUTLINE_LINE_COLOR,
+                )
+                .unwrap();
+
+            let forecast_str = format!(
+                "{} {:.0} @ {:.1}s",
+                String::from(event.element()),
+                event.peak_intensity(),
+                event.start().as_secs_f64()
+            );
+            self.forecast_text_objs.push(ggez_gfx::Text::new((
+                forecast_str.as_str(),
+                ggez_gfx::Font::default(),
+                crate::DEFAULT_TEXT_SIZE,
+            )));
+        }
+
+        self.forecast_mesh = forecast_mesh_builder.build(ggez_ctx).unwrap();
+    }
+
+    /// Updates the intensity history graph, with the given game time at its right-hand edge
+    fn update_history_mesh(
+        &mut self,
+        history: &WeatherHistory,
+        game_time: Duration,
+        ci_ctx: &CastIronContext,
+        ggez_ctx: &mut GgEzContext,
+    ) {
+        // The graph's outline means the mesh is never empty
+        let mut history_mesh_builder = ggez_gfx::MeshBuilder::new();
+        let outline_rect = ggez_gfx::Rect::new(
+            self.history_pos.x,
+            self.history_pos.y,
+            HUD_HISTORY_WIDTH,
+            HUD_HISTORY_HEIGHT,
+        );
+        history_mesh_builder
+            .rectangle(
+                ggez_gfx::DrawMode::stroke(1.0),
+                outline_rect,
+                HUD_OUTLINE_LINE_COLOR,
+            )
+            .unwrap();
+
+        // Map game times onto the graph's x-axis, and intensities onto its y-axis
+        let window_secs = history.window().as_secs_f64();
+        let time_to_x = |time: Duration| {
+            let age = game_time
+                .checked_sub(time)
+                .unwrap_or_default()
+                .as_secs_f64();
+            self.history_pos.x + HUD_HISTORY_WIDTH * (1.0 - (age / window_secs) as f32)
+        };
+        let intensity_to_y = |intensity: f64| {
+            let fraction = (intensity / ci_ctx.max_weather_intensity()).clamp(0.0, 1.0);
+            self.history_pos.y + HUD_HISTORY_HEIGHT * (1.0 - fraction as f32)
+        };
+
+        // Mark the start of each event with a vertical line in its element's colour
+        for marker in history.markers() {
+            if let Some(color) = history_color(marker.element()) {
+                let marker_x = time_to_x(marker.time());
+                history_mesh_builder
+                    .line(
+                        &[
+                            ggez_mint::Point2 {
+                                x: marker_x,
+                                y: self.history_pos.y,
+                            },
+                            ggez_mint::Point2 {
+                                x: marker_x,
+                                y: self.history_pos.y + HUD_HISTORY_HEIGHT,
+                            },
+                        ],
+                        HUD_HISTORY_MARKER_WIDTH,
+                        color,
+                    )
+                    .unwrap();
+            }
+        }
+
+        // Plot intensity, coloured by the element active at the end of each segment
+        let samples = history.samples();
+        for (prev_sample, sample) in samples.iter().zip(samples.iter().skip(1)) {
+            if let Some(color) = history_color(sample.element()) {
+                history_mesh_builder
+                    .line(
+                        &[
+                            ggez_mint::Point2 {
+                                x: time_to_x(prev_sample.time()),
+                                y: intensity_to_y(prev_sample.intensity()),
+                            },
+                            ggez_mint::Point2 {
+                                x: time_to_x(sample.time()),
+                                y: intensity_to_y(sample.intensity()),
+                            },
+                        ],
+                        HUD_HISTORY_LINE_WIDTH,
+                        color,
+                    )
+                    .unwrap();
+            }
+        }
+
+        self.history_mesh = history_mesh_builder.build(ggez_ctx).unwrap();
+    }
+
     /// Updates text elements of the HUD
-    fn update_text_elements(&mut self, element: Element, intensity: weather::Intensity) {
+    fn update_text_elements(&mut self, element_str: String, intensity: f64, phase: Option<Phase>) {
         // Update element text
-        self.text_elem_str = String::from(element);
+        self.text_elem_str = element_str;
         self.text_elem_obj = ggez_gfx::Text::new((
             self.text_elem_str.as_str(),
             ggez_gfx::Font::default(),
@@ -390,7 +921,10 @@ impl HudElements {
         ));
 
         // Update intensity text
-        self.text_int_str = String::from(intensity);
+        self.text_int_str = match phase {
+            Some(phase) => format!("{:.0} ({})", intensity, phase),
+            None => format!("{:.0}", intensity),
+        };
         self.text_int_obj = ggez_gfx::Text::new((
             self.text_int_str.as_str(),
             ggez_gfx::Font::default(),
@@ -398,3 +932,35 @@ impl HudElements {
         ));
     }
 }
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Error for WeatherManagerError {}
+
+impl fmt::Display for WeatherManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TimelinePlaying(weather_event) => write!(
+                f,
+                "Weather event {} refused, as a weather timeline is playing",
+                weather_event
+            ),
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the colour in which the given element is graphed, or None if it has no colour
+fn history_color(element: Element) -> Option<ggez_gfx::Color> {
+    match element {
+        Element::Unset => None,
+        _ => Some(colors::from_element(element)),
+    }
+}
@@ -0,0 +1,933 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : game_managers/world_edit.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines reversible edits of the game world, for use with the undo/redo
+    history.
+
+    Edits are applied through the WorldEditTarget trait rather than directly
+    to the managers, so they can be applied to a stand-in world in tests.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{error::Error, fmt};
+
+use cast_iron::{
+    actor::Actor,
+    context::Context as CastIronContext,
+    coords,
+    mechanics::{obstacle::Obstacle, resource::Resource},
+    Plottable,
+};
+
+use mt_logger::{mt_log, Level};
+
+use crate::{
+    edit_history::Command,
+    event_bus::{self, GameEvent},
+    game_managers::{
+        actor_manager::ActorManager,
+        entity::{EntityId, InstanceKind},
+        obstacle_manager::ObstacleManager,
+        occupancy::OccupancyIndex,
+        resource_manager::ResourceManager,
+        world_grid_manager::WorldGridManager,
+        DrawableError, DrawableMechanic,
+    },
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// An instance of any drawable mechanic in the world
+pub enum WorldInstance {
+    Actor(Actor),
+    Obstacle(Obstacle),
+    Resource(Resource),
+}
+
+/// A reversible edit of the world
+pub enum WorldEdit {
+    /// Adds an instance, which is held here whenever it is not in the world
+    ///
+    /// The ID assigned when first added is kept, so that redoing the edit restores the same entity.
+    Add(
+        InstanceKind,
+        coords::Position,
+        Option<WorldInstance>,
+        Option<EntityId>,
+    ),
+    /// Removes the instance at a position, which is held here with its ID whenever it is not in the world
+    Remove(
+        InstanceKind,
+        coords::Position,
+        Option<(EntityId, WorldInstance)>,
+    ),
+    /// Moves the instance whose origin is at the first position to the second position
+    Move(InstanceKind, coords::Position, coords::Position),
+    /// Swaps the held instance with the one of the same kind and origin in the world, keeping its ID
+    Replace(InstanceKind, coords::Position, Option<WorldInstance>),
+    /// Toggles the highlight of a grid cell
+    ToggleHighlight(coords::Position),
+    /// A labelled group of edits that are done and undone as one
+    Batch(String, Vec<WorldEdit>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WorldEditError {
+    CellNotInGrid(coords::Position),
+    DestinationOccupied(InstanceKind, coords::Position),
+    InstanceUnavailable(InstanceKind, coords::Position),
+    NothingAtPosition(InstanceKind, coords::Position),
+
+    // Wrappers
+    DrawableError(DrawableError),
+}
+
+/// Live view of the game world, binding the managers to the context needed to update them
+pub struct LiveWorld<'a> {
+    pub actor_manager: &'a mut ActorManager,
+    pub obstacle_manager: &'a mut ObstacleManager,
+    pub resource_manager: &'a mut ResourceManager,
+    pub world_grid_manager: &'a mut WorldGridManager,
+    pub occupancy: &'a mut OccupancyIndex,
+    pub events: event_bus::Publisher,
+    pub ci_ctx: &'a CastIronContext,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Declarations
+///////////////////////////////////////////////////////////////////////////////
+
+/// Primitive world mutations from which all WorldEdits are composed
+pub trait WorldEditTarget {
+    /// Adds the instance to the world with the given ID, or a new ID if None, returning its ID
+    fn insert_instance(
+        &mut self,
+        instance: WorldInstance,
+        id: Option<EntityId>,
+    ) -> Result<EntityId, WorldEditError>;
+
+    /// Removes and returns the instance of the given kind occupying the position, along with its ID
+    fn remove_instance(
+        &mut self,
+        kind: InstanceKind,
+        position: &coords::Position,
+    ) -> Result<(EntityId, WorldInstance), WorldEditError>;
+
+    /// Moves the instance of the given kind with its origin at `from` such that its origin is at `to`
+    fn move_instance(
+        &mut self,
+        kind: InstanceKind,
+        from: &coords::Position,
+        to: &coords::Position,
+    ) -> Result<(), WorldEditError>;
+
+    /// Replaces the instance of the same kind and origin as the given one, returning the old instance
+    fn replace_instance(
+        &mut self,
+        instance: WorldInstance,
+    ) -> Result<WorldInstance, WorldEditError>;
+
+    /// Toggles the highlight of the cell at the given position
+    fn toggle_highlight(&mut self, position: &coords::Position) -> Result<(), WorldEditError>;
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl WorldInstance {
+    pub fn kind(&self) -> InstanceKind {
+        match self {
+            Self::Actor(_actor) => InstanceKind::Actor,
+            Self::Obstacle(_obstacle) => InstanceKind::Obstacle,
+            Self::Resource(_resource) => InstanceKind::Resource,
+        }
+    }
+
+    pub fn origin(&self) -> coords::Position {
+        match self {
+            Self::Actor(actor) => *actor.origin(),
+            Self::Obstacle(obstacle) => *obstacle.origin(),
+            Self::Resource(resource) => *resource.origin(),
+        }
+    }
+}
+
+impl WorldEdit {
+    /// Creates an edit adding the given instance
+    pub fn add(instance: WorldInstance) -> Self {
+        Self::Add(instance.kind(), instance.origin(), Some(instance), None)
+    }
+
+    /// Creates an edit removing the instance of the given kind at the given position
+    pub fn remove(kind: InstanceKind, position: coords::Position) -> Self {
+        Self::Remove(kind, position, None)
+    }
+
+    /// Creates an edit replacing the instance of the same kind and origin with the given instance
+    pub fn replace(instance: WorldInstance) -> Self {
+        Self::Replace(instance.kind(), instance.origin(), Some(instance))
+    }
+
+    /// Returns true if the edit would leave the world unchanged, i.e. it is a batch of no edits
+    pub fn is_noop(&self) -> bool {
+        match self {
+            Self::Batch(_label, edits) => edits.iter().all(Self::is_noop),
+            _ => false,
+        }
+    }
+
+    /// Swaps the held instance with the one in the world, for both doing and undoing a replacement
+    fn swap_held<T: WorldEditTarget>(
+        target: &mut T,
+        kind: InstanceKind,
+        origin: &coords::Position,
+        held: &mut Option<WorldInstance>,
+    ) -> Result<(), WorldEditError> {
+        let instance = held
+            .take()
+            .ok_or(WorldEditError::InstanceUnavailable(kind, *origin))?;
+        *held = Some(target.replace_instance(instance)?);
+
+        Ok(())
+    }
+}
+
+impl<'a> LiveWorld<'a> {
+    /// Removes the instance in the given manager occupying the position, returning it with its ID
+    fn remove_within<M: DrawableMechanic>(
+        manager: &mut M,
+        position: &coords::Position,
+        occupancy: &mut OccupancyIndex,
+    ) -> Option<(EntityId, M::Instance)> {
+        let id = manager.id_at(position)?;
+
+        manager
+            .remove_instance_by_id(id, occupancy)
+            .map(|instance| (id, instance))
+    }
+
+    /// Moves the instance in the given manager with its origin at `from` such that its origin is at `to`
+    fn move_within<M: DrawableMechanic>(
+        manager: &mut M,
+        world_grid_manager: &WorldGridManager,
+        occupancy: &mut OccupancyIndex,
+        kind: InstanceKind,
+        from: &coords::Position,
+        to: &coords::Position,
+        ci_ctx: &CastIronContext,
+    ) -> Result<EntityId, WorldEditError> {
+        // Only the origin of an instance may be used to move it
+        let index = manager
+            .instances()
+            .iter()
+            .position(|instance| instance.origin() == from)
+            .ok_or(WorldEditError::NothingAtPosition(kind, *from))?;
+        let id = manager.instance_ids()[index];
+
+        // Verify the whole instance would remain within the grid
+        let relocated_positions = M::translated_positions(&manager.instances()[index], to, ci_ctx)
+            .map_err(|_e| WorldEditError::CellNotInGrid(*to))?;
+        for position in &relocated_positions {
+            if !world_grid_manager.hex_map().contains_key(position) {
+                return Err(WorldEditError::CellNotInGrid(*position));
+            }
+        }
+
+        manager
+            .move_instance(id, to, occupancy, ci_ctx)
+            .map(|()| id)
+            .map_err(|e| match e {
+                DrawableError::CoordinatesOccupied(..) => {
+                    WorldEditError::DestinationOccupied(kind, *to)
+                }
+                e => WorldEditError::DrawableError(e),
+            })
+    }
+
+    /// Replaces the instance in the given manager with the same origin as the given instance,
+    /// returning the old instance with its ID
+    fn replace_within<M: DrawableMechanic>(
+        manager: &mut M,
+        instance: M::Instance,
+        occupancy: &mut OccupancyIndex,
+        kind: InstanceKind,
+    ) -> Result<(EntityId, M::Instance), WorldEditError> {
+        let origin = *instance.origin();
+        let id = manager
+            .instances()
+            .iter()
+            .position(|existing| *existing.origin() == origin)
+            .map(|index| manager.instance_ids()[index])
+            .ok_or(WorldEditError::NothingAtPosition(kind, origin))?;
+
+        manager
+            .replace_instance(id, instance, occupancy)
+            .map(|old_instance| (id, old_instance))
+            .map_err(WorldEditError::DrawableError)
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+/*  *  *  *  *  *  *  *  *  *\
+ *         WorldEdit        *
+\*  *  *  *  *  *  *  *  *  */
+
+impl<T: WorldEditTarget> Command<T> for WorldEdit {
+    type Error = WorldEditError;
+
+    fn execute(&mut self, target: &mut T) -> Result<(), Self::Error> {
+        match self {
+            Self::Add(kind, origin, pending, assigned_id) => {
+                let instance = pending
+                    .take()
+                    .ok_or(WorldEditError::InstanceUnavailable(*kind, *origin))?;
+                *assigned_id = Some(target.insert_instance(instance, *assigned_id)?);
+                Ok(())
+            }
+            Self::Remove(kind, position, removed) => {
+                *removed = Some(target.remove_instance(*kind, position)?);
+                Ok(())
+            }
+            Self::Move(kind, from, to) => target.move_instance(*kind, from, to),
+            Self::Replace(kind, origin, held) => Self::swap_held(target, *kind, origin, held),
+            Self::ToggleHighlight(position) => target.toggle_highlight(position),
+            Self::Batch(label, edits) => {
+                for i in 0..edits.len() {
+                    if let Err(e) = edits[i].execute(target) {
+                        // Roll back every edit that did succeed so the batch is all-or-nothing,
+                        // even if some of them cannot be undone
+                        let rollback_errors: Vec<WorldEditError> = edits[..i]
+                            .iter_mut()
+                            .rev()
+                            .filter_map(|edit| edit.undo(target).err())
+                            .collect();
+                        for rollback_err in &rollback_errors {
+                            mt_log!(
+                                Level::Error,
+                                "Error '{}' while rolling back '{}' after '{}'.",
+                                rollback_err,
+                                label,
+                                e
+                            );
+                        }
+
+                        return Err(e);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn undo(&mut self, target: &mut T) -> Result<(), Self::Error> {
+        match self {
+            Self::Add(kind, origin, pending, _assigned_id) => {
+                let (_id, instance) = target.remove_instance(*kind, origin)?;
+                *pending = Some(instance);
+                Ok(())
+            }
+            Self::Remove(kind, position, removed) => {
+                let (id, instance) = removed
+                    .take()
+                    .ok_or(WorldEditError::InstanceUnavailable(*kind, *position))?;
+                target.insert_instance(instance, Some(id)).map(|_id| ())
+            }
+            Self::Move(kind, from, to) => target.move_instance(*kind, to, from),
+            Self::Replace(kind, origin, held) => Self::swap_held(target, *kind, origin, held),
+            Self::ToggleHighlight(position) => target.toggle_highlight(position),
+            Self::Batch(label, edits) => {
+                for i in (0..edits.len()).rev() {
+                    if let Err(e) = edits[i].undo(target) {
+                        // Redo every edit that was undone so the batch is left fully applied,
+                        // even if some of them cannot be redone
+                        let rollback_errors: Vec<WorldEditError> = edits[i + 1..]
+                            .iter_mut()
+                            .filter_map(|edit| edit.execute(target).err())
+                            .collect();
+                        for rollback_err in &rollback_errors {
+                            mt_log!(
+                                Level::Error,
+                                "Error '{}' while rolling back undo of '{}' after '{}'.",
+                                rollback_err,
+                                label,
+                                e
+                            );
+                        }
+
+                        return Err(e);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn is_noop(&self) -> bool {
+        WorldEdit::is_noop(self)
+    }
+}
+
+impl fmt::Display for WorldEdit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Add(kind, origin, _pending, _assigned_id) => {
+                write!(f, "Add {} at {}", kind, origin)
+            }
+            Self::Remove(kind, position, _removed) => {
+                write!(f, "Remove {} at {}", kind, position)
+            }
+            Self::Move(kind, from, to) => write!(f, "Move {} from {} to {}", kind, from, to),
+            Self::Replace(kind, origin, _held) => write!(f, "Replace {} at {}", kind, origin),
+            Self::ToggleHighlight(position) => write!(f, "Toggle highlight at {}", position),
+            Self::Batch(label, edits) => write!(f, "{} ({} edits)", label, edits.len()),
+        }
+    }
+}
+
+
+/*  *  *  *  *  *  *  *  *  *\
+ *         LiveWorld        *
+\*  *  *  *  *  *  *  *  *  */
+
+impl<'a> WorldEditTarget for LiveWorld<'a> {
+    fn insert_instance(
+        &mut self,
+        instance: WorldInstance,
+        id: Option<EntityId>,
+    ) -> Result<EntityId, WorldEditError> {
+        let id = id.unwrap_or_else(EntityId::next);
+        let (kind, origin) = (instance.kind(), instance.origin());
+        let insert_result = match instance {
+            WorldInstance::Actor(actor) => {
+                self.actor_manager
+                    .add_instance_with_id(actor, id, self.occupancy)
+            }
+            WorldInstance::Obstacle(obstacle) => {
+                self.obstacle_manager
+                    .add_instance_with_id(obstacle, id, self.occupancy)
+            }
+            WorldInstance::Resource(resource) => {
+                self.resource_manager
+                    .add_instance_with_id(resource, id, self.occupancy)
+            }
+        };
+
+        insert_result.map_err(WorldEditError::DrawableError)?;
+        self.events
+            .publish(GameEvent::EntitySpawned(id, kind, origin));
+
+        Ok(id)
+    }
+
+    fn remove_instance(
+        &mut self,
+        kind: InstanceKind,
+        position: &coords::Position,
+    ) -> Result<(EntityId, WorldInstance), WorldEditError> {
+        let removed_instance = match kind {
+            InstanceKind::Actor => {
+                Self::remove_within(self.actor_manager, position, self.occupancy)
+                    .map(|(id, actor)| (id, WorldInstance::Actor(actor)))
+            }
+            InstanceKind::Obstacle => {
+                Self::remove_within(self.obstacle_manager, position, self.occupancy)
+                    .map(|(id, obstacle)| (id, WorldInstance::Obstacle(obstacle)))
+            }
+            InstanceKind::Resource => {
+                Self::remove_within(self.resource_manager, position, self.occupancy)
+                    .map(|(id, resource)| (id, WorldInstance::Resource(resource)))
+            }
+        };
+
+        let (id, instance) =
+            removed_instance.ok_or(WorldEditError::NothingAtPosition(kind, *position))?;
+        self.events.publish(GameEvent::EntityRemoved(id, kind));
+
+        Ok((id, instance))
+    }
+
+    fn move_instance(
+        &mut self,
+        kind: InstanceKind,
+        from: &coords::Position,
+        to: &coords::Position,
+    ) -> Result<(), WorldEditError> {
+        let moved_id = match kind {
+            InstanceKind::Actor => Self::move_within(
+                self.actor_manager,
+                self.world_grid_manager,
+                self.occupancy,
+                kind,
+                from,
+                to,
+                self.ci_ctx,
+            ),
+            InstanceKind::Obstacle => Self::move_within(
+                self.obstacle_manager,
+                self.world_grid_manager,
+                self.occupancy,
+                kind,
+                from,
+                to,
+                self.ci_ctx,
+            ),
+            InstanceKind::Resource => Self::move_within(
+                self.resource_manager,
+                self.world_grid_manager,
+                self.occupancy,
+                kind,
+                from,
+                to,
+                self.ci_ctx,
+            ),
+        }?;
+
+        // Only actors have a dedicated movement event
+        if kind == InstanceKind::Actor {
+            self.events
+                .publish(GameEvent::ActorMoved(moved_id, *from, *to));
+        }
+
+        Ok(())
+    }
+
+    fn replace_instance(
+        &mut self,
+        instance: WorldInstance,
+    ) -> Result<WorldInstance, WorldEditError> {
+        let kind = instance.kind();
+        match instance {
+            WorldInstance::Actor(actor) => {
+                Self::replace_within(self.actor_manager, actor, self.occupancy, kind)
+                    .map(|(_id, old_actor)| WorldInstance::Actor(old_actor))
+            }
+            WorldInstance::Obstacle(obstacle) => {
+                Self::replace_within(self.obstacle_manager, obstacle, self.occupancy, kind)
+                    .map(|(_id, old_obstacle)| WorldInstance::Obstacle(old_obstacle))
+            }
+            WorldInstance::Resource(resource) => {
+                let new_state = resource.state();
+                let (id, old_resource) =
+                    Self::replace_within(self.resource_manager, resource, self.occupancy, kind)?;
+
+                let old_state = old_resource.state();
+                if old_state != new_state {
+                    self.events
+                        .publish(GameEvent::ResourceStateChanged(id, old_state, new_state));
+                }
+
+                Ok(WorldInstance::Resource(old_resource))
+            }
+        }
+    }
+
+    fn toggle_highlight(&mut self, position: &coords::Position) -> Result<(), WorldEditError> {
+        self.world_grid_manager
+            .toggle_cell_highlight(position)
+            .map_err(|_e| WorldEditError::CellNotInGrid(*position))
+    }
+}
+
+
+/*  *  *  *  *  *  *  *  *  *\
+ *      WorldEditError      *
+\*  *  *  *  *  *  *  *  *  */
+
+impl Error for WorldEditError {}
+
+impl fmt::Display for WorldEditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CellNotInGrid(position) => {
+                write!(f, "Position {} is not in the world grid", position)
+            }
+            Self::DestinationOccupied(kind, position) => {
+                write!(f, "Cannot move {} to occupied position {}", kind, position)
+            }
+            Self::InstanceUnavailable(kind, position) => {
+                write!(f, "No held {} instance for edit at {}", kind, position)
+            }
+            Self::NothingAtPosition(kind, position) => {
+                write!(f, "No {} at position {}", kind, position)
+            }
+            Self::DrawableError(drawable_err) => {
+                write!(f, "DrawableError '{:?}' encountered", drawable_err)
+            }
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, error::Error};
+
+    use cast_iron::{actor::Actor, context::Context as CastIronContext, coords};
+
+    use crate::{
+        edit_history::History,
+        game_managers::{
+            entity::{EntityId, InstanceKind},
+            world_edit::{WorldEdit, WorldEditError, WorldEditTarget, WorldInstance},
+        },
+    };
+
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    /// Stand-in world that records instances by position, and fails on request
+    #[derive(Default)]
+    struct StubWorld {
+        instances: Vec<(EntityId, WorldInstance)>, // Instances in the world, in insertion order
+        highlighted: HashSet<coords::Position>,    // Highlighted cells
+        fail_insert_at: Option<coords::Position>,  // Position at which inserts fail
+        fail_remove_at: Option<coords::Position>,  // Position at which removals fail
+    }
+
+    impl StubWorld {
+        fn origins(&self) -> Vec<coords::Position> {
+            self.instances
+                .iter()
+                .map(|(_id, instance)| instance.origin())
+                .collect()
+        }
+    }
+
+    impl WorldEditTarget for StubWorld {
+        fn insert_instance(
+            &mut self,
+            instance: WorldInstance,
+            id: Option<EntityId>,
+        ) -> Result<EntityId, WorldEditError> {
+            let origin = instance.origin();
+            if self.fail_insert_at == Some(origin) {
+                return Err(WorldEditError::CellNotInGrid(origin));
+            }
+
+            let id = id.unwrap_or_else(EntityId::next);
+            self.instances.push((id, instance));
+
+            Ok(id)
+        }
+
+        fn remove_instance(
+            &mut self,
+            kind: InstanceKind,
+            position: &coords::Position,
+        ) -> Result<(EntityId, WorldInstance), WorldEditError> {
+            if self.fail_remove_at == Some(*position) {
+                return Err(WorldEditError::CellNotInGrid(*position));
+            }
+
+            let index = self
+                .instances
+                .iter()
+                .position(|(_id, instance)| {
+                    instance.kind() == kind && instance.origin() == *position
+                })
+                .ok_or(WorldEditError::NothingAtPosition(kind, *position))?;
+
+            Ok(self.instances.remove(index))
+        }
+
+        fn move_instance(
+            &mut self,
+            kind: InstanceKind,
+            from: &coords::Position,
+            to: &coords::Position,
+        ) -> Result<(), WorldEditError> {
+            match self
+                .instances
+                .iter_mut()
+                .find(|(_id, instance)| instance.kind() == kind && instance.origin() == *from)
+            {
+                Some((_id, WorldInstance::Actor(actor))) => {
+                    actor.set_origin(*to);
+                    Ok(())
+                }
+                _ => Err(WorldEditError::NothingAtPosition(kind, *from)),
+            }
+        }
+
+        fn replace_instance(
+            &mut self,
+            instance: WorldInstance,
+        ) -> Result<WorldInstance, WorldEditError> {
+            let (kind, origin) = (instance.kind(), instance.origin());
+            let (_id, existing) = self
+                .instances
+                .iter_mut()
+                .find(|(_id, existing)| existing.kind() == kind && existing.origin() == origin)
+                .ok_or(WorldEditError::NothingAtPosition(kind, origin))?;
+
+            Ok(std::mem::replace(existing, instance))
+        }
+
+        fn toggle_highlight(&mut self, position: &coords::Position) -> Result<(), WorldEditError> {
+            if !self.highlighted.remove(position) {
+                self.highlighted.insert(*position);
+            }
+
+            Ok(())
+        }
+    }
+
+    fn position(x: i32, y: i32, z: i32) -> Result<coords::Position, Box<dyn Error>> {
+        coords::Position::new(x, y, z, &CastIronContext::default())
+            .ok()
+            .ok_or_else(|| "Invalid test position".into())
+    }
+
+    fn actor_names(world: &StubWorld) -> Vec<String> {
+        world
+            .instances
+            .iter()
+            .filter_map(|(_id, instance)| match instance {
+                WorldInstance::Actor(actor) => Some(actor.name().to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn add_actor(origin: coords::Position) -> WorldEdit {
+        let mut actor = Actor::new_name_only("Test Actor");
+        actor.set_origin(origin);
+
+        WorldEdit::add(WorldInstance::Actor(actor))
+    }
+
+
+    #[test]
+    fn execute_undo_redo_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(10);
+        let (origin, destination) = (position(0, 0, 0)?, position(1, -1, 0)?);
+
+        history.execute(add_actor(origin), &mut world)?;
+        history.execute(
+            WorldEdit::Move(InstanceKind::Actor, origin, destination),
+            &mut world,
+        )?;
+        history.execute(WorldEdit::ToggleHighlight(origin), &mut world)?;
+        assert_eq!(world.origins(), vec![destination]);
+        assert!(world.highlighted.contains(&origin));
+        let added_id = world.instances[0].0;
+
+        // Undo everything, then verify nothing is left to undo
+        assert!(history.undo(&mut world)?);
+        assert!(world.highlighted.is_empty());
+        assert!(history.undo(&mut world)?);
+        assert_eq!(world.origins(), vec![origin]);
+        assert!(history.undo(&mut world)?);
+        assert!(world.instances.is_empty());
+        assert!(!history.undo(&mut world)?);
+        assert!(history.can_redo());
+
+        // Redo everything, and verify the actor keeps its original ID
+        assert!(history.redo(&mut world)?);
+        assert!(history.redo(&mut world)?);
+        assert!(history.redo(&mut world)?);
+        assert!(!history.redo(&mut world)?);
+        assert_eq!(world.origins(), vec![destination]);
+        assert_eq!(world.instances[0].0, added_id);
+        assert!(world.highlighted.contains(&origin));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(10);
+        let origin = position(0, 0, 0)?;
+
+        let mut replacement = Actor::new_name_only("Replacement Actor");
+        replacement.set_origin(origin);
+
+        history.execute(add_actor(origin), &mut world)?;
+        let added_id = world.instances[0].0;
+        history.execute(
+            WorldEdit::replace(WorldInstance::Actor(replacement)),
+            &mut world,
+        )?;
+        assert_eq!(actor_names(&world), vec!["Replacement Actor"]);
+        assert_eq!(world.instances[0].0, added_id);
+
+        // Undoing and redoing swaps the instances back and forth under the same ID
+        assert!(history.undo(&mut world)?);
+        assert_eq!(actor_names(&world), vec!["Test Actor"]);
+        assert!(history.redo(&mut world)?);
+        assert_eq!(actor_names(&world), vec!["Replacement Actor"]);
+        assert_eq!(world.instances[0].0, added_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn history_cap_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(2);
+
+        for x in 0..3 {
+            history.execute(add_actor(position(x, -x, 0)?), &mut world)?;
+        }
+        assert_eq!(history.undo_entries().count(), 2);
+
+        // Only the two most recent edits can be undone
+        assert!(history.undo(&mut world)?);
+        assert!(history.undo(&mut world)?);
+        assert!(!history.undo(&mut world)?);
+        assert_eq!(world.origins(), vec![position(0, 0, 0)?]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn new_edit_clears_redo_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(10);
+
+        history.execute(add_actor(position(0, 0, 0)?), &mut world)?;
+        history.undo(&mut world)?;
+        assert!(history.can_redo());
+
+        history.execute(add_actor(position(1, -1, 0)?), &mut world)?;
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut world)?);
+        assert_eq!(world.origins(), vec![position(1, -1, 0)?]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_batch_not_recorded_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(10);
+
+        history.execute(add_actor(position(0, 0, 0)?), &mut world)?;
+        history.undo(&mut world)?;
+
+        // An empty batch neither takes an undo slot nor discards the redoable edit
+        history.execute(
+            WorldEdit::Batch(String::from("Empty"), Vec::new()),
+            &mut world,
+        )?;
+        assert!(!history.can_undo());
+        assert!(history.can_redo());
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_batch_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(10);
+        let failing_position = position(2, -2, 0)?;
+        world.fail_insert_at = Some(failing_position);
+
+        let batch = WorldEdit::Batch(
+            String::from("Half-failing"),
+            vec![
+                add_actor(position(0, 0, 0)?),
+                add_actor(position(1, -1, 0)?),
+                add_actor(failing_position),
+                add_actor(position(3, -3, 0)?),
+            ],
+        );
+
+        // The batch is rolled back entirely and not recorded
+        assert_eq!(
+            history.execute(batch, &mut world),
+            Err(WorldEditError::CellNotInGrid(failing_position))
+        );
+        assert!(world.instances.is_empty());
+        assert!(!history.can_undo());
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_batch_rollback_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(10);
+        let (stuck_position, failing_position) = (position(1, -1, 0)?, position(2, -2, 0)?);
+        world.fail_insert_at = Some(failing_position);
+        world.fail_remove_at = Some(stuck_position);
+
+        let batch = WorldEdit::Batch(
+            String::from("Half-failing"),
+            vec![
+                add_actor(position(0, 0, 0)?),
+                add_actor(stuck_position),
+                add_actor(failing_position),
+            ],
+        );
+
+        // The original error is reported, and edits before the stuck one are still rolled back
+        assert_eq!(
+            history.execute(batch, &mut world),
+            Err(WorldEditError::CellNotInGrid(failing_position))
+        );
+        assert_eq!(world.origins(), vec![stuck_position]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_batch_undo_test() -> TestResult {
+        let mut world = StubWorld::default();
+        let mut history = History::new(10);
+        let stuck_position = position(1, -1, 0)?;
+        let origins = vec![position(0, 0, 0)?, stuck_position, position(2, -2, 0)?];
+
+        let batch = WorldEdit::Batch(
+            String::from("Half-undoable"),
+            origins.iter().map(|origin| add_actor(*origin)).collect(),
+        );
+        history.execute(batch, &mut world)?;
+        world.fail_remove_at = Some(stuck_position);
+
+        // The undone edits are redone, leaving the batch applied and still undoable
+        assert_eq!(
+            history.undo(&mut world),
+            Err(WorldEditError::CellNotInGrid(stuck_position))
+        );
+        assert_eq!(world.origins(), origins);
+        assert!(history.can_undo());
+
+        // Once the stuck edit can be undone, the whole batch is
+        world.fail_remove_at = None;
+        assert!(history.undo(&mut world)?);
+        assert!(world.instances.is_empty());
+
+        Ok(())
+    }
+}
@@ -24,11 +24,16 @@ use std::collections::HashMap;
 
 use cast_iron::{context::Context as CastIronContext, coords, hex_directions};
 
-use ggez::{graphics as ggez_gfx, mint as ggez_mint, Context as GgEzContext};
+use ggez::{graphics as ggez_gfx, Context as GgEzContext};
 
 use mt_logger::{mt_log, Level};
 
-use crate::game_assets::{colors, hex_grid_cell::HexGridCell};
+use crate::game_assets::{
+    chunked_mesh::ChunkedMesh,
+    colors,
+    hex_batch::{HexBatch, RenderMode},
+    hex_grid_cell::HexGridCell,
+};
 
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -50,8 +55,11 @@ const FIRST_INTRARING_DIRECTION: hex_directions::Side = hex_directions::Side::No
 ///////////////////////////////////////////////////////////////////////////////
 
 pub struct WorldGridManager {
-    radial_size: usize,             // Maximum value for an axis of the hex grid
-    base_grid_mesh: ggez_gfx::Mesh, // Mesh for the base hex grid
+    radial_size: usize,                // Maximum value for an axis of the hex grid
+    base_grid_mesh: ggez_gfx::Mesh,    // Static mesh for the base hex grid, built once
+    highlight_mesh: ChunkedMesh,       // Chunked overlay mesh for highlighted cells
+    cell_order: Vec<coords::Position>, // Cell positions in spiral order, defining the overlay chunks
+    cell_indices: HashMap<coords::Position, usize>, // Index of each cell position in cell_order
     hex_map: HashMap<coords::Position, HexGridCell>,
 }
 
@@ -72,24 +80,28 @@ impl WorldGridManager {
         ci_ctx: &CastIronContext,
         ggez_ctx: &mut GgEzContext,
     ) -> Self {
-        // Create manager and update mesh for initialization
-        let mut world_grid_manager = Self {
+        let cell_order = Self::grid_positions(radial_size, ci_ctx);
+        let cell_indices = cell_order
+            .iter()
+            .enumerate()
+            .map(|(index, position)| (*position, index))
+            .collect();
+        let hex_map = Self::build_default_hex_cell_map(&cell_order, ggez_ctx);
+
+        // Instanced cells are built on first use
+        let mut hex_batch = HexBatch::default();
+        hex_batch.mark_dirty();
+
+        // Create manager, building the static grid mesh once for initialization
+        Self {
             radial_size,
-            base_grid_mesh: ggez_gfx::Mesh::new_line(
-                ggez_ctx,
-                &[
-                    ggez_mint::Point2 { x: 0.0, y: 0.0 },
-                    ggez_mint::Point2 { x: 10.0, y: 10.0 },
-                ],
-                crate::DEFAULT_LINE_WIDTH,
-                crate::DEFAULT_LINE_COLOR,
-            )
-            .unwrap(),
-            hex_map: Self::build_default_hex_cell_map(radial_size, ci_ctx, ggez_ctx),
-        };
-        world_grid_manager.update_base_mesh(ggez_ctx);
-
-        world_grid_manager
+            base_grid_mesh: Self::build_base_mesh(&hex_map, ggez_ctx),
+            highlight_mesh: ChunkedMesh::default(),
+            hex_batch,
+            cell_order,
+            cell_indices,
+            hex_map,
+        }
     }
 
 
@@ -109,15 +121,30 @@ impl WorldGridManager {
         &self.hex_map
     }
 
+    /// Returns all positions in the grid within the given radius (in cells) of the center
+    pub fn positions_within(
+        &self,
+        center: &coords::Position,
+        radius: usize,
+        ci_ctx: &CastIronContext,
+    ) -> Vec<coords::Position> {
+        HexGridCell::hex_range(center, radius, ci_ctx)
+            .into_iter()
+            .filter(|position| self.hex_map.contains_key(position))
+            .collect()
+    }
+
 
     /*  *  *  *  *  *  *  *\
      *  Mutator Methods   *
     \*  *  *  *  *  *  *  */
 
+    /// Toggles the highlight of the cell at the given position
+    ///
+    /// The highlight overlay is not rebuilt until the next call to update_mesh().
     pub fn toggle_cell_highlight(
         &mut self,
         cell_position: &coords::Position,
-        ggez_ctx: &mut GgEzContext,
     ) -> Result<(), WorldGridError> {
         // Look up cell by position
         match self.hex_map.get_mut(cell_position) {
@@ -125,8 +152,14 @@ impl WorldGridManager {
                 // Update highlight property of the found cell
                 hex_cell.toggle_highlight();
 
-                // Update the mesh
-                self.update_base_mesh(ggez_ctx);
+                // Only the overlay chunk containing the cell needs to be rebuilt
+                let cell_index = self.cell_indices[cell_position];
+                self.highlight_mesh
+                    .mark_dirty(cell_index, self.cell_order.len());
+
+                // Instanced cells are recoloured in place
+                self.hex_batch
+                    .set_fill_color(cell_index, Self::cell_fill_color(hex_cell));
                 Ok(())
             }
             _ => Err(WorldGridError),
@@ -138,7 +171,13 @@ impl WorldGridManager {
      *  Utility Methods   *
     \*  *  *  *  *  *  *  */
 
-    pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
+    pub fn draw(&mut self, render_mode: RenderMode, ggez_ctx: &mut GgEzContext) {
+        // Draw instanced cells if requested, which include their highlights
+        if render_mode == RenderMode::Instanced {
+            self.hex_batch.draw(ggez_ctx);
+            return;
+        }
+
         // Draw world grid mesh
         ggez_gfx::draw(
             ggez_ctx,
@@ -146,17 +185,91 @@ impl WorldGridManager {
             ggez_gfx::DrawParam::default(),
         )
         .unwrap();
+
+        // Draw highlight overlay
+        self.highlight_mesh.draw(ggez_ctx);
+    }
+
+    /// Brings the geometry for the given render mode up to date with the current cells
+    pub fn update_geometry(&mut self, render_mode: RenderMode, ggez_ctx: &mut GgEzContext) {
+        match render_mode {
+            RenderMode::Mesh => {
+                self.update_mesh(ggez_ctx);
+            }
+            RenderMode::Instanced => self.update_hex_batch(),
+        }
+    }
+
+    /// Rebuilds the instanced cells if they have not yet been built
+    pub fn update_hex_batch(&mut self) {
+        if !self.hex_batch.is_dirty() {
+            return;
+        }
+
+        self.hex_batch.clear();
+        for position in self.cell_order.iter() {
+            let hex_cell = &self.hex_map[position];
+            self.hex_batch.add_hex(
+                hex_cell,
+                Self::cell_fill_color(hex_cell),
+                crate::DEFAULT_LINE_COLOR,
+            );
+        }
+
+        mt_log!(
+            Level::Debug,
+            "Hex batch built with {} cell(s)",
+            self.hex_batch.len()
+        );
+    }
+
+    /// Rebuilds the highlight overlay chunks whose cells have changed, returning the number rebuilt
+    pub fn update_mesh(&mut self, ggez_ctx: &mut GgEzContext) -> usize {
+        // Short-circuit if nothing has changed since the last update
+        if !self.highlight_mesh.is_dirty() {
+            return 0;
+        }
+
+        let cell_order = &self.cell_order;
+        let hex_map = &self.hex_map;
+        let rebuilt_count =
+            self.highlight_mesh
+                .update(ggez_ctx, |index, mesh_builder, _ggez_ctx| {
+                    let hex_cell = &hex_map[&cell_order[index]];
+                    if hex_cell.highlighted() {
+                        hex_cell.add_highlight_to_mesh(mesh_builder);
+                    }
+                });
+
+        mt_log!(Level::Debug, "{} highlight chunk(s) updated", rebuilt_count);
+
+        rebuilt_count
     }
 
 
     /*  *  *  *  *  *  *  *\
-     *  Helper Methods    *
+     *  Helper Functions  *
     \*  *  *  *  *  *  *  */
 
-    fn update_base_mesh(&mut self, ggez_ctx: &mut GgEzContext) {
+    /// Returns the instanced fill colour of the given cell
+    fn cell_fill_color(hex_cell: &HexGridCell) -> ggez_gfx::Color {
+        if hex_cell.highlighted() {
+            colors::HILITE_STD
+        } else {
+            colors::TRANSPARENT
+        }
+    }
+
+    /// Builds the static mesh of all hex grid cell outlines
+    ///
+    /// Highlights are drawn by a separate overlay, so this mesh never needs to be rebuilt.
+    fn build_base_mesh(
+        hex_map: &HashMap<coords::Position, HexGridCell>,
+        ggez_ctx: &mut GgEzContext,
+    ) -> ggez_gfx::Mesh {
         let mut mesh_builder = ggez_gfx::MeshBuilder::new();
 
-        for (_position, hex_cell) in self.hex_map.iter() {
+        for hex_cell in hex_map.values() {
             hex_cell.add_to_mesh(
                 colors::TRANSPARENT,
                 crate::DEFAULT_LINE_COLOR,
@@ -164,43 +277,50 @@ impl WorldGridManager {
             );
         }
 
-        self.base_grid_mesh = mesh_builder.build(ggez_ctx).unwrap();
+        mt_log!(Level::Debug, "Base mesh built");
 
-        mt_log!(Level::Debug, "Base mesh updated");
+        mesh_builder.build(ggez_ctx).unwrap()
     }
 
-
-    /*  *  *  *  *  *  *  *\
-     *  Helper Functions  *
-    \*  *  *  *  *  *  *  */
-
     /// Builds representation of all hex grid cells
     fn build_default_hex_cell_map(
-        radial_size: usize,
-        ci_ctx: &CastIronContext,
+        positions: &[coords::Position],
         ggez_ctx: &GgEzContext,
     ) -> HashMap<coords::Position, HexGridCell> {
-        // There are 6*(n-1) cells for a given (1-based) level n of a hex grid, so size map according to arithmetic sum
-        let map_size = 1
+        positions
+            .iter()
+            .map(|position| {
+                (
+                    *position,
+                    HexGridCell::new_from_hex_coords(position, crate::HEX_RADIUS_VERTEX, ggez_ctx),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the positions of all cells in a hex grid of the given radius, spiralling out from the center
+    ///
+    /// Does not require a GGEZ context, so may be used for headless rendering.
+    pub fn grid_positions(radial_size: usize, ci_ctx: &CastIronContext) -> Vec<coords::Position> {
+        // There are 6*(n-1) cells for a given (1-based) level n of a hex grid, so size vec according to arithmetic sum
+        let grid_size = 1
             + ((radial_size as f32 / 2.0)
                 * ((2.0 * NUM_ADDITIONAL_CELLS_PER_LEVEL as f32)
                     + ((radial_size as f32 - 1.0) * NUM_ADDITIONAL_CELLS_PER_LEVEL as f32)))
                 as usize;
 
-        // Create a hashmap of hex grid cells with the appropriate capacity (avoids expensive re-allocations)
-        let mut hex_map: HashMap<coords::Position, HexGridCell> = HashMap::with_capacity(map_size);
+        // Create a vec of positions with the appropriate capacity (avoids expensive re-allocations)
+        let mut positions: Vec<coords::Position> = Vec::with_capacity(grid_size);
 
-        /* Populate Map */
+        /* Populate Positions */
         // Add central hex
         let central_hex_position = coords::Position::default();
         let mut cur_hex_position = central_hex_position;
-        let mut cur_hex_cell_instance =
-            HexGridCell::new_from_hex_coords(&cur_hex_position, crate::HEX_RADIUS_VERTEX, ggez_ctx);
-        hex_map.insert(cur_hex_position, cur_hex_cell_instance);
+        positions.push(cur_hex_position);
 
         // Add the remainder of the hexes in a spiral pattern
         for radial_level in 1..=radial_size {
-            // Translate to the starting hex of the next ring, but don't add it to the map (will be done by the innermost loop)
+            // Translate to the starting hex of the next ring, but don't add it to the vec (will be done by the innermost loop)
             cur_hex_position
                 .translate(&coords::Translation::from(NEW_RING_START_DIRECTION), ci_ctx)
                 .expect("Could not translate to the starting hex of the next ring.");
@@ -214,16 +334,11 @@ impl WorldGridManager {
                         .translate(&coords::Translation::from(direction), ci_ctx)
                         .expect("Could not translate to next intrastep hex.");
 
-                    cur_hex_cell_instance = HexGridCell::new_from_hex_coords(
-                        &cur_hex_position,
-                        crate::HEX_RADIUS_VERTEX,
-                        ggez_ctx,
-                    );
-                    hex_map.insert(cur_hex_position, cur_hex_cell_instance);
+                    positions.push(cur_hex_position);
                 }
             }
         }
 
-        hex_map
+        positions
     }
 }
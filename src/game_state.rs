@@ -21,9 +21,17 @@ Purpose:
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
-use std::{error::Error, fmt, io::Write};
+use std::{
+    error::Error,
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use cast_iron::context::Context as CastIronContext;
+use cast_iron::{
+    actor::Actor, context::Context as CastIronContext, coords, mechanics::obstacle::Obstacle,
+};
 
 use dd_statechart::{event::Event, StateChart, StateChartError};
 
@@ -36,13 +44,38 @@ use ggez::{
 use mt_logger::{mt_flush, mt_log, Level};
 
 use crate::{
-    game_assets::{colors, hex_grid_cell::HexGridCell},
+    chart_bridge::{self, BridgedChart, ChartBridge, ChartFacts, ChartOutput},
+    chart_layout::ChartLayout,
+    chart_watcher::{self, ChartSource, ChartWatcher},
+    console::Console,
+    edit_history::History,
+    event_bus::{EventBus, GameEvent},
+    fixed_timestep::FixedTimestep,
+    game_assets::{
+        chart_overlay::ChartOverlay, colors, hex_batch::RenderMode, hex_grid_cell::HexGridCell,
+        toasts::ToastQueue, weather_particles::WeatherParticles,
+    },
+    game_clock::GameClock,
+    game_command::{GameCommand, GameCommandError},
     game_managers::{
-        actor_manager::ActorManager, obstacle_manager::ObstacleManager,
-        resource_manager::ResourceManager, weather_manager::WeatherManager,
-        world_grid_manager::WorldGridManager, DrawableMechanic,
+        actor_manager::ActorManager,
+        entity::{EntityId, InstanceKind},
+        obstacle_manager::ObstacleManager,
+        occupancy::{OccupancyIndex, ResourceRule},
+        registry::EntityRegistry,
+        resource_manager::ResourceManager,
+        weather_event::WeatherEvent,
+        weather_fronts::{CellWeather, WeatherFront, WeatherFrontManager},
+        weather_manager::{WeatherManager, WeatherManagerError},
+        world_edit::{LiveWorld, WorldEdit, WorldEditError, WorldInstance},
+        world_grid_manager::WorldGridManager,
+        DrawableMechanic,
     },
+    keymap::{self, BoundAction, Input, Keymap, NamedAction},
+    map_editor::{self, EditorAction, MapEditor},
+    map_file::MapFile,
     profiler,
+    svg_export::SvgExporter,
 };
 
 
@@ -53,6 +86,23 @@ use crate::{
 //FIXME: These probably should be relative to window size
 // Position of debug info text in window
 const DEBUG_POS_STATE: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 800.0 };
+const DEBUG_POS_RENDER_MODE: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 820.0 };
+const DEBUG_POS_CLOCK: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 840.0 };
+
+const DEBUG_POS_STATECHART: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 860.0 };
+const DEBUG_POS_CHART_JOURNAL: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 880.0 };
+
+// Position of edit history panel in window
+const HISTORY_PANEL_POS: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 750.0, y: 250.0 };
+
+/// Maximum number of entries shown in the edit history panel
+const HISTORY_PANEL_MAX_ENTRIES: usize = 20;
+
+/// Default path to which the current world is exported as an SVG image
+const DEFAULT_SVG_EXPORT_PATH: &str = "./sand_casting.svg";
+
+/// Maximum number of statechart commands carried out in a single step, in case commands loop
+const MAX_CHART_COMMANDS_PER_STEP: usize = 64;
 
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -62,22 +112,58 @@ const DEBUG_POS_STATE: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 8
 //TODO: Rename and refactor this - very likely does not need to keep clones of the logger and profiler
 /// Primary Game Struct
 pub struct SandCastingGameState<W: Write> {
-    initialized: bool,                 // Flag indicating if game has been initialized
-    debug_display: bool,               // Flag indicating if debug info should be displayed
-    ci_ctx: CastIronContext,           // CastIron engine context
-    profiler: profiler::Instance,      // Instance of SandCasting performance profiler
-    actor_manager: ActorManager,       // Actor Manager instance
-    obstacle_manager: ObstacleManager, // Obstacle Manager instance
-    resource_manager: ResourceManager, // Resource Manager instance
-    statechart: StateChart<W>,     // StateChart covering all game states
-    weather_manager: WeatherManager,   // Weather Manager instance
+    initialized: bool,                   // Flag indicating if game has been initialized
+    debug_display: bool,                 // Flag indicating if debug info should be displayed
+    chart_display: bool,                 // Flag indicating if the chart overlay is displayed
+    ci_ctx: CastIronContext,             // CastIron engine context
+    profiler: profiler::Instance,        // Instance of SandCasting performance profiler
+    game_clock: GameClock,               // Game time read by the simulation
+    sim_timestep: FixedTimestep,         // Accumulator stepping the simulation at a fixed rate
+    actor_manager: ActorManager,         // Actor Manager instance
+    obstacle_manager: ObstacleManager,   // Obstacle Manager instance
+    resource_manager: ResourceManager,   // Resource Manager instance
+    statechart: BridgedChart<W>,         // StateChart covering all game states
+    chart_bridge: ChartBridge,           // Passes facts to the statechart, and commands back
+    chart_watcher: ChartWatcher,         // Watches the statechart file, for reloading on change
+    chart_source: ChartSource<W>,        // Location of the statechart, and writer for its output
+    chart_overlay: ChartOverlay,         // Node graph of the statechart, for debugging
+    weather_manager: WeatherManager,     // Weather Manager instance
+    weather_fronts: WeatherFrontManager, // Regional weather fronts drifting across the grid
+    weather_particles: WeatherParticles, // Particle effects over cells covered by weather
     world_grid_manager: WorldGridManager, // World Grid Manager instance
+    occupancy: OccupancyIndex,           // Occupants of every cell, across all managers
+    map_editor: MapEditor,               // Map Editor, active only in the 'editor' state
+    starting_map: Option<MapFile>,       // Map to load on first-frame initialization, if any
+    map_path: PathBuf,                   // Path to which the editor saves the world map
+    scenario_weather: Vec<WeatherEvent>, // Weather scheduled by the loaded map, kept when saving
+    edit_history: History<WorldEdit>,    // Undo/redo history of world edits
+    render_mode: RenderMode,             // Method used to draw the grid, obstacles and resources
+    event_bus: EventBus,                 // Bus carrying game events to the game state
+    toasts: ToastQueue,                  // Notifications of notable game events
+    console: Console,                    // Console for typed game commands and chart events
+    keymap: Keymap,                      // Bindings of keys and mouse buttons to actions
+    keymap_path: PathBuf,                // Path from which the keymap is loaded
+    svg_path: PathBuf,                   // Path to which the world is exported as an SVG image
+    svg_show_coords: bool,               // Flag indicating if SVG exports label cell coordinates
+    selected_actor: Option<EntityId>,    // Actor last clicked outside of the editor, if any
+    background: Option<ggez_gfx::Color>, // Background color set by the statechart, if any
 }
 
 #[derive(Debug, PartialEq)]
 pub enum GameStateError {
     // Wrappers
     StateChartError(StateChartError),
+    GameCommandError(GameCommandError),
+    WeatherManagerError(WeatherManagerError),
+
+    // Startup
+    StatechartNotFound(PathBuf),
+    InvalidStatechart(PathBuf, StateChartError),
+    EmbeddedStatechartUnavailable(PathBuf, String),
+
+    // Commands
+    EditFailed(String, WorldEditError),
+    MapNotSaved(PathBuf, String),
 }
 
 
@@ -87,12 +173,16 @@ pub enum GameStateError {
 
 /// Constructor
 impl<W: Write> SandCastingGameState<W> {
+    /// Creates the game state, failing if the statechart cannot be loaded
     pub fn new(
         profiler_original: &profiler::Instance,
         ci_ctx: &CastIronContext,
         ggez_ctx: &mut GgEzContext,
-        writer: W,
-    ) -> Self {
+        chart_source: ChartSource<W>,
+        starting_map: Option<MapFile>,
+        map_path: PathBuf,
+        render_mode: RenderMode,
+    ) -> Result<Self, GameStateError> {
         //NOTE: Load/create resources here: images, fonts, sounds, etc.
 
         // Clone the profiler instances for use by this module
@@ -101,18 +191,55 @@ impl<W: Write> SandCastingGameState<W> {
         // Clone context for use by submodules
         let ctx_clone = ci_ctx.clone();
 
-        SandCastingGameState {
+        // Create the event bus first, so that submodules may publish to it
+        let event_bus = EventBus::new();
+
+        // Load the statechart before anything else, as the game cannot run without it
+        let chart_bridge = ChartBridge::new();
+        let statechart = Self::load_statechart(&chart_source, &chart_bridge)?;
+
+        Ok(SandCastingGameState {
             initialized: false,
             debug_display: false,
+            chart_display: false,
             ci_ctx: ctx_clone,
             profiler: profiler_clone,
-            actor_manager: ActorManager::new(ggez_ctx),
-            obstacle_manager: ObstacleManager::new(ggez_ctx),
-            resource_manager: ResourceManager::new(ggez_ctx),
-            statechart: StateChart::from("./res/default.scxml", writer).unwrap(),
-            weather_manager: WeatherManager::default(profiler_original, ci_ctx, ggez_ctx),
-            world_grid_manager: WorldGridManager::new(crate::DEFAULT_GRID_RADIUS, ci_ctx, ggez_ctx),
-        }
+            game_clock: GameClock::new(),
+            sim_timestep: FixedTimestep::default(),
+            actor_manager: ActorManager::new(),
+            obstacle_manager: ObstacleManager::new(),
+            resource_manager: ResourceManager::new(),
+            statechart,
+            chart_bridge,
+            chart_watcher: ChartWatcher::new(chart_source.path()),
+            chart_overlay: ChartOverlay::new(Self::load_chart_layout(chart_source.path())),
+            chart_source,
+            weather_manager: WeatherManager::default(
+                profiler_original,
+                event_bus.publisher(),
+                ci_ctx,
+                ggez_ctx,
+            ),
+            weather_fronts: WeatherFrontManager::new(),
+            weather_particles: WeatherParticles::default(),
+            world_grid_manager: WorldGridManager::new(ci_ctx.grid_radius(), ci_ctx, ggez_ctx),
+            occupancy: OccupancyIndex::new(),
+            map_editor: MapEditor::default(),
+            starting_map,
+            map_path,
+            scenario_weather: Vec::new(),
+            edit_history: History::new(crate::DEFAULT_MAX_EDIT_HISTORY),
+            render_mode,
+            event_bus,
+            toasts: ToastQueue::default(),
+            console: Console::default(),
+            keymap: Keymap::default(),
+            keymap_path: keymap::default_keymap_path(),
+            svg_path: PathBuf::from(DEFAULT_SVG_EXPORT_PATH),
+            svg_show_coords: false,
+            selected_actor: None,
+            background: None,
+        })
     }
 
 
@@ -125,6 +252,14 @@ impl<W: Write> SandCastingGameState<W> {
         self.initialized
     }
 
+    pub fn game_clock(&mut self) -> &mut GameClock {
+        &mut self.game_clock
+    }
+
+    pub fn sim_timestep(&mut self) -> &mut FixedTimestep {
+        &mut self.sim_timestep
+    }
+
     //TODO: These should not give out mutable references
     pub fn actor_manager(&mut self) -> &mut ActorManager {
         &mut self.actor_manager
@@ -142,14 +277,72 @@ impl<W: Write> SandCastingGameState<W> {
         &mut self.weather_manager
     }
 
+    pub fn weather_fronts(&mut self) -> &mut WeatherFrontManager {
+        &mut self.weather_fronts
+    }
+
+    pub fn weather_particles(&mut self) -> &mut WeatherParticles {
+        &mut self.weather_particles
+    }
+
     pub fn world_grid_manager(&mut self) -> &mut WorldGridManager {
         &mut self.world_grid_manager
     }
 
+    /// Returns a view of every entity in the world, for lookup by ID, position or kind
+    pub fn entities(&self) -> EntityRegistry {
+        EntityRegistry::new(
+            &self.actor_manager,
+            &self.obstacle_manager,
+            &self.resource_manager,
+            &self.occupancy,
+        )
+    }
+
+    /// Returns the weather at the given position, or None if no weather front covers it
+    pub fn weather_at(&self, position: &coords::Position) -> Option<CellWeather> {
+        self.weather_fronts.weather_at(position)
+    }
+
     pub fn active_state_ids(&self) -> Vec<&str> {
         self.statechart.active_state_ids()
     }
 
+    pub fn in_editor(&self) -> bool {
+        self.active_state_ids()
+            .contains(&map_editor::EDITOR_STATE_ID)
+    }
+
+    /// Returns the mirrored value of the given statechart datamodel member, if the chart reports it
+    pub fn chart_data(&self, id: &str) -> Option<String> {
+        self.chart_bridge.data(id)
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Mutator Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Sets the path from which the keymap is loaded, taking effect when it is next loaded
+    pub fn set_keymap_path(&mut self, keymap_path: PathBuf) {
+        self.keymap_path = keymap_path;
+    }
+
+    /// Sets the rule for sharing cells with resources
+    pub fn set_resource_rule(&mut self, resource_rule: ResourceRule) {
+        self.occupancy.set_resource_rule(resource_rule);
+    }
+
+    /// Sets the path to which the world is exported as an SVG image
+    pub fn set_svg_path(&mut self, svg_path: PathBuf) {
+        self.svg_path = svg_path;
+    }
+
+    /// Sets whether SVG exports label each cell with its coordinates
+    pub fn set_svg_show_coords(&mut self, svg_show_coords: bool) {
+        self.svg_show_coords = svg_show_coords;
+    }
+
 
     /*  *  *  *  *  *  *  *\
      *  Utility Methods   *
@@ -158,8 +351,7 @@ impl<W: Write> SandCastingGameState<W> {
     pub fn process_event(&mut self, event: &Event) -> Result<(), GameStateError> {
         // Pass the event to the StateChart
         let process_result = self
-            .statechart
-            .process_external_event(event)
+            .send_to_statechart(event)
             .map_err(GameStateError::StateChartError);
 
         if process_result.is_ok() {
@@ -181,38 +373,1008 @@ impl<W: Write> SandCastingGameState<W> {
         process_result
     }
 
+    /// Parses and carries out the given game command, returning the command carried out
+    pub fn run_command(&mut self, command_str: &str) -> Result<GameCommand, GameStateError> {
+        let command = GameCommand::parse(command_str, &self.ci_ctx)
+            .map_err(GameStateError::GameCommandError)?;
+
+        match &command {
+            GameCommand::SpawnActor(position) => {
+                let mut actor = Actor::new_name_only(&format!(
+                    "Scripted Actor {}",
+                    self.actor_manager.instances().len() + 1
+                ));
+                actor.set_origin(*position);
+
+                self.execute_edit(WorldEdit::add(WorldInstance::Actor(actor)))?;
+            }
+            GameCommand::Weather(element, peak_intensity, duration) => {
+                self.weather_manager
+                    .schedule_next(&[WeatherEvent::new(*element, *peak_intensity, *duration)])
+                    .map_err(GameStateError::WeatherManagerError)?;
+            }
+            GameCommand::Background(color) => {
+                self.background = *color;
+            }
+            GameCommand::Event(event_id) => {
+                let event = Event::from(event_id).map_err(|e| {
+                    GameStateError::GameCommandError(GameCommandError::MalformedCommand(
+                        String::from("event"),
+                        e.to_string(),
+                    ))
+                })?;
+                self.process_event(&event)?;
+            }
+            GameCommand::Seed(seed) => {
+                self.regenerate_world(*seed)?;
+            }
+            GameCommand::Save => {
+                self.save_map()?;
+            }
+        }
+
+        mt_log!(Level::Info, "Command '{}' carried out", command);
+
+        Ok(command)
+    }
+
+    /// Saves the current world to the map file
+    pub fn save_map(&self) -> Result<(), GameStateError> {
+        MapFile::save(
+            &self.map_path,
+            self.actor_manager.instances(),
+            self.obstacle_manager.instances(),
+            self.resource_manager.instances(),
+            &self.scenario_weather,
+        )
+        .map_err(|e| GameStateError::MapNotSaved(self.map_path.clone(), e.to_string()))?;
+
+        mt_log!(Level::Info, "Map saved to '{}'", self.map_path.display());
+
+        Ok(())
+    }
+
+    /// Exports the current world to an SVG image
+    pub fn export_svg(&self, ggez_ctx: &GgEzContext) {
+        let (width, height) = ggez_gfx::size(ggez_ctx);
+        let exporter = SvgExporter::new(width, height, self.svg_show_coords);
+        let svg = exporter.render_world(
+            &self.world_grid_manager,
+            &self.actor_manager,
+            &self.obstacle_manager,
+            &self.resource_manager,
+        );
+
+        match std::fs::write(&self.svg_path, svg) {
+            Ok(()) => mt_log!(
+                Level::Info,
+                "World exported to '{}'",
+                self.svg_path.display()
+            ),
+            Err(e) => mt_log!(
+                Level::Error,
+                "Error '{}' while exporting world to '{}'",
+                e,
+                self.svg_path.display()
+            ),
+        }
+    }
+
 
     /*  *  *  *  *  *  *  *\
      *   Helper Methods   *
     \*  *  *  *  *  *  *  */
 
-    fn initialize(&mut self, ggez_ctx: &mut GgEzContext) {
+    fn initialize(&mut self) {
+        self.reload_keymap();
+
+        // Use the starting map if one was provided, otherwise generate a random world
+        if let Some(starting_map) = self.starting_map.take() {
+            self.load_map(starting_map);
+        } else {
+            self.generate_rand_map();
+        }
+
+        // Announce the starting entities on the event bus
+        for (id, entity) in self.entities().all() {
+            self.event_bus.publish(GameEvent::EntitySpawned(
+                id,
+                entity.kind(),
+                *entity.origin(),
+            ));
+        }
+
+        mt_log!(Level::Info, "First-frame initialization complete.");
+        self.initialized = true;
+    }
+
+    fn load_map(&mut self, map: MapFile) {
+        let (actors, obstacles, resources, weather) = map.into_parts();
+
+        for resource in resources {
+            if let Err(e) = self
+                .resource_manager
+                .add_instance(resource, &mut self.occupancy)
+            {
+                mt_log!(Level::Warning, "Skipped map resource: {:?}", e);
+            }
+        }
+        mt_log!(Level::Info, "Resources loaded from map.");
+
+        for obstacle in obstacles {
+            if let Err(e) = self
+                .obstacle_manager
+                .add_instance(obstacle, &mut self.occupancy)
+            {
+                mt_log!(Level::Warning, "Skipped map obstacle: {:?}", e);
+            }
+        }
+        mt_log!(Level::Info, "Obstacles loaded from map.");
+
+        for actor in actors {
+            if let Err(e) = self.actor_manager.add_instance(actor, &mut self.occupancy) {
+                mt_log!(Level::Warning, "Skipped map actor: {:?}", e);
+            }
+        }
+        mt_log!(Level::Info, "Actors loaded from map.");
+
+        match self.weather_manager.schedule_next(&weather) {
+            Ok(()) => mt_log!(Level::Info, "Weather scheduled from map."),
+            Err(e) => mt_log!(Level::Warning, "Weather from map not scheduled: {}", e),
+        }
+        self.scenario_weather = weather;
+    }
+
+    fn generate_rand_map(&mut self) {
         // Create random resources
         for _i in 0..3 {
-            self.resource_manager
-                .add_rand_instance(&self.ci_ctx, ggez_ctx)
-                .unwrap();
+            if let Err(e) = self
+                .resource_manager
+                .add_rand_instance(&mut self.occupancy, &self.ci_ctx)
+            {
+                mt_log!(
+                    Level::Warning,
+                    "Failed to generate random resource: {:?}",
+                    e
+                );
+            }
         }
         mt_log!(Level::Info, "Resources generated.");
 
         // Create random obstacles
         for _i in 0..3 {
-            self.obstacle_manager
-                .add_rand_instance(&self.ci_ctx, ggez_ctx)
-                .unwrap();
+            if let Err(e) = self
+                .obstacle_manager
+                .add_rand_instance(&mut self.occupancy, &self.ci_ctx)
+            {
+                mt_log!(
+                    Level::Warning,
+                    "Failed to generate random obstacle: {:?}",
+                    e
+                );
+            }
         }
         mt_log!(Level::Info, "Obstacles generated.");
 
         // Create random actors
         for _i in 0..3 {
-            self.actor_manager
-                .add_rand_instance(&self.ci_ctx, ggez_ctx)
-                .unwrap();
+            if let Err(e) = self
+                .actor_manager
+                .add_rand_instance(&mut self.occupancy, &self.ci_ctx)
+            {
+                mt_log!(Level::Warning, "Failed to generate random actor: {:?}", e);
+            }
         }
         mt_log!(Level::Info, "Actors generated.");
+    }
 
-        mt_log!(Level::Info, "First-frame initialization complete.");
-        self.initialized = true;
+    /// Replaces every instance in the world with those of a map generated from the given seed
+    ///
+    /// The replacement is a single edit, so it can be undone.
+    fn regenerate_world(&mut self, seed: u64) -> Result<(), GameStateError> {
+        let grid_positions =
+            WorldGridManager::grid_positions(self.ci_ctx.grid_radius(), &self.ci_ctx);
+        let mut edits = self.region_removals(&grid_positions);
+
+        let (actors, obstacles, resources, weather) = MapFile::generate(
+            seed,
+            self.ci_ctx.grid_radius(),
+            self.occupancy.resource_rule(),
+            &self.ci_ctx,
+        )
+        .into_parts();
+        edits.extend(
+            resources
+                .into_iter()
+                .map(|resource| WorldEdit::add(WorldInstance::Resource(resource))),
+        );
+        edits.extend(
+            obstacles
+                .into_iter()
+                .map(|obstacle| WorldEdit::add(WorldInstance::Obstacle(obstacle))),
+        );
+        edits.extend(
+            actors
+                .into_iter()
+                .map(|actor| WorldEdit::add(WorldInstance::Actor(actor))),
+        );
+        self.execute_edit(WorldEdit::Batch(
+            format!("Regenerate from seed {}", seed),
+            edits,
+        ))?;
+        mt_log!(Level::Info, "World regenerated from seed {}", seed);
+
+        // The world is kept even if its weather is refused, as the two are independent
+        let schedule_result = self
+            .weather_manager
+            .schedule_next(&weather)
+            .map_err(GameStateError::WeatherManagerError);
+        self.scenario_weather = weather;
+
+        schedule_result
+    }
+
+    /// Returns true if any mechanic occupies the given position
+    fn position_occupied(&self, position: &coords::Position) -> bool {
+        self.occupancy.is_occupied(position)
+    }
+
+    /// Converts an edit requested by the map editor into a world edit and executes it
+    fn apply_editor_action(&mut self, action: EditorAction) {
+        let edit = match action {
+            EditorAction::PlaceActor(actor) => WorldEdit::add(WorldInstance::Actor(actor)),
+            EditorAction::RemoveActor(position) => WorldEdit::remove(InstanceKind::Actor, position),
+            EditorAction::PaintObstacle(obstacle) => {
+                WorldEdit::add(WorldInstance::Obstacle(obstacle))
+            }
+            EditorAction::PlaceResource(resource) => {
+                // A resource placed on the origin of another takes its place, keeping its ID
+                let replaces_existing = self
+                    .resource_manager
+                    .instances()
+                    .iter()
+                    .any(|existing| existing.origin() == resource.origin());
+
+                if replaces_existing {
+                    WorldEdit::replace(WorldInstance::Resource(resource))
+                } else {
+                    WorldEdit::add(WorldInstance::Resource(resource))
+                }
+            }
+            EditorAction::EraseRegion(center, radius) => {
+                let region =
+                    self.world_grid_manager
+                        .positions_within(&center, radius, &self.ci_ctx);
+
+                WorldEdit::Batch(String::from("Erase region"), self.region_removals(&region))
+            }
+            EditorAction::FillRegion(center, radius, element) => {
+                // Fill only the empty cells of the region, one obstacle per cell
+                let fills = self
+                    .world_grid_manager
+                    .positions_within(&center, radius, &self.ci_ctx)
+                    .into_iter()
+                    .filter(|position| !self.position_occupied(position))
+                    .map(|position| {
+                        WorldEdit::add(WorldInstance::Obstacle(Obstacle::new(
+                            vec![position],
+                            element,
+                        )))
+                    })
+                    .collect();
+
+                WorldEdit::Batch(String::from("Fill region"), fills)
+            }
+        };
+
+        if let Err(e) = self.execute_edit(edit) {
+            mt_log!(Level::Warning, "{}", e);
+        }
+    }
+
+    /// Returns the edits required to remove every instance touching the given region
+    fn region_removals(&self, region: &[coords::Position]) -> Vec<WorldEdit> {
+        let mut removals = Vec::new();
+
+        for actor in self.actor_manager.instances() {
+            if region.contains(actor.origin()) {
+                removals.push(WorldEdit::remove(InstanceKind::Actor, *actor.origin()));
+            }
+        }
+
+        // Obstacles are removed whole if any of their cells fall within the region
+        for obstacle in self.obstacle_manager.instances() {
+            if ObstacleManager::instance_positions(obstacle)
+                .iter()
+                .any(|position| region.contains(position))
+            {
+                removals.push(WorldEdit::remove(
+                    InstanceKind::Obstacle,
+                    *obstacle.origin(),
+                ));
+            }
+        }
+
+        for resource in self.resource_manager.instances() {
+            if region.contains(resource.origin()) {
+                removals.push(WorldEdit::remove(
+                    InstanceKind::Resource,
+                    *resource.origin(),
+                ));
+            }
+        }
+
+        removals
+    }
+
+    /// Executes the given edit on the world, recording it in the edit history
+    fn execute_edit(&mut self, edit: WorldEdit) -> Result<(), GameStateError> {
+        let edit_description = edit.to_string();
+        let mut live_world = LiveWorld {
+            actor_manager: &mut self.actor_manager,
+            obstacle_manager: &mut self.obstacle_manager,
+            resource_manager: &mut self.resource_manager,
+            world_grid_manager: &mut self.world_grid_manager,
+            occupancy: &mut self.occupancy,
+            events: self.event_bus.publisher(),
+            ci_ctx: &self.ci_ctx,
+        };
+
+        self.edit_history
+            .execute(edit, &mut live_world)
+            .map_err(|e| GameStateError::EditFailed(edit_description.clone(), e))?;
+        mt_log!(Level::Debug, "Edit '{}' executed", edit_description);
+
+        Ok(())
+    }
+
+    /// Undoes the most recent world edit
+    fn undo_edit(&mut self) {
+        let mut live_world = LiveWorld {
+            actor_manager: &mut self.actor_manager,
+            obstacle_manager: &mut self.obstacle_manager,
+            resource_manager: &mut self.resource_manager,
+            world_grid_manager: &mut self.world_grid_manager,
+            occupancy: &mut self.occupancy,
+            events: self.event_bus.publisher(),
+            ci_ctx: &self.ci_ctx,
+        };
+
+        match self.edit_history.undo(&mut live_world) {
+            Ok(true) => mt_log!(Level::Debug, "Edit undone"),
+            Ok(false) => mt_log!(Level::Debug, "Nothing to undo"),
+            Err(e) => mt_log!(Level::Warning, "Error '{}' while undoing edit", e),
+        }
+    }
+
+    /// Redoes the most recently undone world edit
+    fn redo_edit(&mut self) {
+        let mut live_world = LiveWorld {
+            actor_manager: &mut self.actor_manager,
+            obstacle_manager: &mut self.obstacle_manager,
+            resource_manager: &mut self.resource_manager,
+            world_grid_manager: &mut self.world_grid_manager,
+            occupancy: &mut self.occupancy,
+            events: self.event_bus.publisher(),
+            ci_ctx: &self.ci_ctx,
+        };
+
+        match self.edit_history.redo(&mut live_world) {
+            Ok(true) => mt_log!(Level::Debug, "Edit redone"),
+            Ok(false) => mt_log!(Level::Debug, "Nothing to redo"),
+            Err(e) => mt_log!(Level::Warning, "Error '{}' while redoing edit", e),
+        }
+    }
+
+    fn draw_history_panel(&self, ggez_ctx: &mut GgEzContext) {
+        let history_entries = self.edit_history.describe();
+
+        // Only the most recent entries fit in the panel
+        let first_shown = history_entries
+            .len()
+            .saturating_sub(HISTORY_PANEL_MAX_ENTRIES);
+
+        let mut history_display = ggez_gfx::Text::new((
+            String::from("Edit History:\n"),
+            ggez_gfx::Font::default(),
+            crate::DEFAULT_TEXT_SIZE,
+        ));
+        for (description, applied) in &history_entries[first_shown..] {
+            // Undone entries are greyed-out until redone or discarded
+            let entry_color = if *applied {
+                colors::WHITE
+            } else {
+                colors::GREY
+            };
+            history_display.add(
+                ggez_gfx::TextFragment::new(format!("{}\n", description))
+                    .color(entry_color)
+                    .scale(crate::DEFAULT_TEXT_SIZE),
+            );
+        }
+
+        ggez_gfx::draw(
+            ggez_ctx,
+            &history_display,
+            (HISTORY_PANEL_POS, 0.0, colors::WHITE),
+        )
+        .unwrap();
+    }
+
+    /// Delivers all published game events to the logger, profiler, toasts and statechart
+    fn dispatch_events(&mut self, ggez_ctx: &GgEzContext) {
+        for game_event in self.event_bus.dispatch() {
+            mt_log!(Level::Info, "Game event: {}", game_event);
+
+            self.profiler
+                .mark_event(game_event.marker_label(), ggez_ctx)
+                .unwrap();
+
+            if game_event.is_notable() {
+                self.toasts.push(game_event.to_string(), ggez_ctx);
+            }
+
+            // Each new weather event sweeps across the map as a front
+            if let GameEvent::WeatherChanged(weather_event) = &game_event {
+                self.weather_fronts
+                    .spawn_front(WeatherFront::rand_for_event(
+                        weather_event,
+                        self.ci_ctx.grid_radius(),
+                        &self.ci_ctx,
+                    ));
+            }
+
+            self.offer_event(game_event.statechart_event_id());
+        }
+
+        self.toasts.update(ggez_ctx);
+    }
+
+    /// Sends the event with the given ID to the statechart, if the chart has registered it
+    ///
+    /// The statechart only needs to register the events it is interested in.
+    fn offer_event(&mut self, event_id: &str) {
+        match Event::from(event_id) {
+            Ok(event) => match self.send_to_statechart(&event) {
+                Ok(()) | Err(StateChartError::ReceivedUnregisteredEvent(_)) => {}
+                Err(e) => mt_log!(
+                    Level::Error,
+                    "Error '{}' while processing event '{}'",
+                    e,
+                    event
+                ),
+            },
+            Err(e) => mt_log!(
+                Level::Error,
+                "Error '{}' while parsing Event ID '{}'",
+                e,
+                event_id
+            ),
+        }
+    }
+
+    /// Passes an event to the statechart, recording it for reloads and the overlay if accepted
+    ///
+    /// Fact events are not recorded for reloads, as the current facts are sent to a reloaded chart.
+    fn send_to_statechart(&mut self, event: &Event) -> Result<(), StateChartError> {
+        let prev_active_state_ids: Vec<String> = self
+            .active_state_ids()
+            .iter()
+            .map(|state_id| String::from(*state_id))
+            .collect();
+
+        self.statechart.process_external_event(event)?;
+
+        if !event.id().starts_with(chart_bridge::FACT_EVENT_PREFIX) {
+            self.chart_watcher.record(event);
+        }
+        self.chart_overlay
+            .record_event(&prev_active_state_ids, &event.id());
+
+        Ok(())
+    }
+
+    /// Carries out a command line submitted to the console, printing the outcome back to it
+    ///
+    /// A single word which is not a command is taken to be an event ID.
+    fn run_console_command(&mut self, command_str: &str) {
+        let command_result = match self.run_command(command_str) {
+            Err(GameStateError::GameCommandError(GameCommandError::UnknownCommand(_)))
+                if !command_str.contains(char::is_whitespace) =>
+            {
+                self.run_command(&format!("event {}", command_str))
+            }
+            command_result => command_result,
+        };
+
+        match command_result {
+            Ok(GameCommand::Event(event_id)) => {
+                let active_state_ids = self.active_state_ids().join(", ");
+                self.console.print(format!(
+                    "Event '{}' processed, active states: {}",
+                    event_id, active_state_ids
+                ));
+            }
+            Ok(GameCommand::Save) => {
+                let save_message = format!("Map saved to '{}'", self.map_path.display());
+                self.console.print(save_message);
+            }
+            Ok(command) => self.console.print(format!("Done: {}", command)),
+            Err(e) => self.console.print_error(e.to_string()),
+        }
+    }
+
+    /// Sends any changed game facts to the statechart, then carries out the commands it issued
+    fn sync_statechart(&mut self) {
+        let chart_facts = self.chart_facts();
+        for event_id in self.chart_bridge.update_facts(chart_facts) {
+            self.offer_event(&event_id);
+        }
+
+        // Commands may send events which issue further commands, so keep going until none are left
+        let mut commands_run = 0;
+        loop {
+            let commands = self.chart_bridge.take_commands();
+            if commands.is_empty() {
+                break;
+            }
+
+            for command_str in commands {
+                if commands_run == MAX_CHART_COMMANDS_PER_STEP {
+                    mt_log!(
+                        Level::Warning,
+                        "Statechart issued over {} commands in one step, discarding the rest",
+                        MAX_CHART_COMMANDS_PER_STEP
+                    );
+                    self.chart_bridge.discard_commands();
+                    return;
+                }
+                commands_run += 1;
+
+                if let Err(e) = self.run_command(&command_str) {
+                    mt_log!(
+                        Level::Error,
+                        "Error '{}' while carrying out statechart command '{}'",
+                        e,
+                        command_str
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns the current game facts exposed to the statechart
+    fn chart_facts(&self) -> ChartFacts {
+        let weather_intensity = self
+            .weather_manager
+            .history()
+            .latest()
+            .map_or(0.0, |sample| sample.intensity());
+        let selected_actor = self
+            .selected_actor
+            .and_then(|id| self.actor_manager.instance_by_id(id))
+            .map(|actor| *actor.origin());
+        let resource_states: Vec<_> = self
+            .resource_manager
+            .instances()
+            .iter()
+            .map(|resource| resource.state())
+            .collect();
+
+        ChartFacts::new(
+            (
+                self.weather_manager.active_weather().element(),
+                weather_intensity,
+            ),
+            self.ci_ctx.max_weather_intensity(),
+            selected_actor,
+            self.actor_manager.instances().len(),
+            &resource_states,
+        )
+    }
+
+    /// Re-parses the statechart file and swaps it in, keeping the current chart if parsing fails
+    ///
+    /// The events accepted so far are replayed onto the new chart, followed by the current facts, so
+    /// that it returns to the same active states wherever they still exist. If too many events were
+    /// accepted to replay, the new chart is only given the current facts.
+    fn reload_statechart(&mut self, ggez_ctx: &GgEzContext) {
+        let chart_path = self.chart_watcher.path().to_string_lossy().into_owned();
+
+        let chart_output = self.chart_bridge.output(self.chart_source.new_writer());
+        let mut new_statechart = match StateChart::from(&chart_path, chart_output) {
+            Ok(new_statechart) => new_statechart,
+            Err(e) => {
+                mt_log!(
+                    Level::Error,
+                    "Error '{}' while reloading statechart '{}', keeping the current chart",
+                    e,
+                    chart_path
+                );
+                self.chart_watcher
+                    .set_reload_error(format!("Statechart reload failed: {}", e));
+                return;
+            }
+        };
+
+        let journal_overflowed = self.chart_watcher.journal_overflowed();
+        if journal_overflowed {
+            mt_log!(
+                Level::Warning,
+                "Too many events accepted to replay onto reloaded statechart '{}', restoring only the current facts",
+                chart_path
+            );
+            self.chart_watcher.reset_journal();
+        } else {
+            for event in self.chart_watcher.journal() {
+                if let Err(e) = new_statechart.process_external_event(event) {
+                    mt_log!(
+                        Level::Debug,
+                        "Event '{}' not replayed onto reloaded statechart: {}",
+                        event,
+                        e
+                    );
+                }
+            }
+        }
+
+        // Bring the new chart up to date with the game, as fact events are not journalled
+        for event_id in self.chart_bridge.fact_event_ids() {
+            let fact_result = Event::from(&event_id)
+                .map_err(|e| e.to_string())
+                .and_then(|event| {
+                    new_statechart
+                        .process_external_event(&event)
+                        .map_err(|e| e.to_string())
+                });
+            if let Err(e) = fact_result {
+                mt_log!(
+                    Level::Debug,
+                    "Fact event '{}' not sent to reloaded statechart: {}",
+                    event_id,
+                    e
+                );
+            }
+        }
+
+        // Replayed events repeat their commands, which have already been carried out
+        self.chart_bridge.discard_commands();
+
+        // Report any previously-active states that the new chart could not return to
+        let new_state_ids = new_statechart.active_state_ids();
+        let lost_state_ids: Vec<String> = self
+            .active_state_ids()
+            .iter()
+            .filter(|state_id| !new_state_ids.contains(*state_id))
+            .map(|state_id| String::from(*state_id))
+            .collect();
+
+        self.statechart = new_statechart;
+        self.chart_watcher.clear_reload_error();
+        self.chart_overlay
+            .set_layout(Self::load_chart_layout(self.chart_watcher.path()));
+
+        if lost_state_ids.is_empty() {
+            mt_log!(
+                Level::Info,
+                "Statechart reloaded from '{}'. Active State(s) '{:?}'",
+                chart_path,
+                self.active_state_ids()
+            );
+        } else {
+            mt_log!(
+                Level::Warning,
+                "Statechart reloaded from '{}', but could not restore State(s) '{:?}'. Active State(s) '{:?}'",
+                chart_path,
+                lost_state_ids,
+                self.active_state_ids()
+            );
+        }
+        let reload_message = if journal_overflowed {
+            "Statechart reloaded, but its states could not be restored"
+        } else {
+            "Statechart reloaded"
+        };
+        self.toasts.push(String::from(reload_message), ggez_ctx);
+    }
+
+    /// Loads the statechart from the given source
+    ///
+    /// If the default chart is missing or invalid, the built-in copy is used in its place. A chart
+    /// given explicitly by the player must load, as silently swapping it out would be confusing.
+    fn load_statechart(
+        chart_source: &ChartSource<W>,
+        chart_bridge: &ChartBridge,
+    ) -> Result<BridgedChart<W>, GameStateError> {
+        let chart_path = chart_source.path();
+        if chart_path.exists() {
+            let chart_output = chart_bridge.output(chart_source.new_writer());
+            match Self::parse_statechart(chart_path, chart_output) {
+                Ok(statechart) => return Ok(statechart),
+                Err(e) if chart_source.is_explicit() => return Err(e),
+                Err(e) => mt_log!(
+                    Level::Warning,
+                    "Error '{}' while loading the default statechart, using the built-in chart",
+                    e
+                ),
+            }
+        } else if chart_source.is_explicit() {
+            return Err(GameStateError::StatechartNotFound(chart_path.to_path_buf()));
+        } else {
+            mt_log!(
+                Level::Warning,
+                "No statechart found at '{}', using the built-in chart",
+                chart_path.display()
+            );
+        }
+
+        // The statechart can only be parsed from a file, so write the built-in chart out first
+        let embedded_path = chart_watcher::embedded_chart_path();
+        chart_watcher::write_embedded_chart(&embedded_path).map_err(|e| {
+            GameStateError::EmbeddedStatechartUnavailable(embedded_path.clone(), e.to_string())
+        })?;
+
+        let chart_output = chart_bridge.output(chart_source.new_writer());
+        let parse_result = Self::parse_statechart(&embedded_path, chart_output);
+
+        // The file is only needed for parsing, so it is removed rather than left until exit
+        if let Err(e) = fs::remove_file(&embedded_path) {
+            mt_log!(
+                Level::Warning,
+                "Error '{}' while removing built-in statechart copy '{}'",
+                e,
+                embedded_path.display()
+            );
+        }
+
+        parse_result
+    }
+
+    fn parse_statechart(
+        chart_path: &Path,
+        chart_output: ChartOutput<W>,
+    ) -> Result<BridgedChart<W>, GameStateError> {
+        let statechart = StateChart::from(&chart_path.to_string_lossy(), chart_output)
+            .map_err(|e| GameStateError::InvalidStatechart(chart_path.to_path_buf(), e))?;
+
+        mt_log!(
+            Level::Info,
+            "Statechart loaded from '{}'",
+            chart_path.display()
+        );
+
+        Ok(statechart)
+    }
+
+    /// Scans the structure of the chart at the given path for the overlay, or of the built-in chart
+    /// if there is no file there
+    ///
+    /// The overlay is only a debugging aid, so a chart that cannot be scanned leaves it empty.
+    fn load_chart_layout(chart_path: &Path) -> ChartLayout {
+        let layout_result = if chart_path.exists() {
+            ChartLayout::load(chart_path)
+        } else {
+            ChartLayout::parse(chart_watcher::EMBEDDED_CHART)
+        };
+
+        layout_result.unwrap_or_else(|e| {
+            mt_log!(
+                Level::Warning,
+                "Error '{}' while scanning statechart '{}' for the overlay",
+                e,
+                chart_path.display()
+            );
+            ChartLayout::default()
+        })
+    }
+
+    /// Toggles the map editor by sending the appropriate event to the statechart
+    fn toggle_editor(&mut self) {
+        let event_id = if self.in_editor() {
+            "editor.exit"
+        } else {
+            "editor.enter"
+        };
+
+        self.process_event_id(event_id);
+    }
+
+    /// Processes the event with the given ID, logging any failure
+    fn process_event_id(&mut self, event_id: &str) {
+        match Event::from(event_id) {
+            Ok(event) => self.process_event(&event).unwrap_or_else(|e| {
+                mt_log!(
+                    Level::Error,
+                    "Error '{}' while processing event '{}'",
+                    e,
+                    event
+                )
+            }),
+            Err(e) => mt_log!(
+                Level::Error,
+                "Error '{}' while parsing Event ID '{}'",
+                e,
+                event_id
+            ),
+        }
+    }
+
+    /// Loads the keymap, reporting any conflicting bindings in the log and the console
+    ///
+    /// The built-in keymap is used if there is no keymap file. If the file cannot be loaded, the
+    /// current bindings are kept, or the built-in ones if there are none yet.
+    fn reload_keymap(&mut self) {
+        let keymap_result = if self.keymap_path.exists() {
+            Keymap::load(&self.keymap_path)
+        } else {
+            mt_log!(
+                Level::Info,
+                "No keymap found at '{}', using the built-in keymap",
+                self.keymap_path.display()
+            );
+            Keymap::parse(keymap::EMBEDDED_KEYMAP)
+        };
+
+        let keymap = match keymap_result {
+            Ok(keymap) => keymap,
+            Err(e) => {
+                let load_error = format!(
+                    "Error '{}' while loading keymap '{}'",
+                    e,
+                    self.keymap_path.display()
+                );
+                mt_log!(Level::Error, "{}", load_error);
+                self.console.print_error(load_error);
+
+                if self.initialized {
+                    return;
+                }
+                Keymap::parse(keymap::EMBEDDED_KEYMAP).unwrap_or_default()
+            }
+        };
+
+        for conflict in keymap.conflicts() {
+            mt_log!(Level::Warning, "Keymap conflict: {}", conflict);
+            self.console
+                .print_error(format!("Keymap conflict: {}", conflict));
+        }
+
+        // Bindings for states the chart doesn't have can never be used
+        for state_id in keymap.state_ids() {
+            if self.chart_overlay.layout().node_index(state_id).is_none() {
+                mt_log!(
+                    Level::Warning,
+                    "Keymap binds input in state '{}', which is not in the statechart",
+                    state_id
+                );
+            }
+        }
+
+        self.keymap = keymap;
+        mt_log!(
+            Level::Info,
+            "Keymap loaded with {} conflict(s)",
+            self.keymap.conflicts().len()
+        );
+    }
+
+    /// Performs the given action, bound to a key or mouse button
+    fn perform_bound_action(&mut self, action: BoundAction, ggez_ctx: &GgEzContext) {
+        match action {
+            BoundAction::Named(named_action) => self.perform_named_action(named_action, ggez_ctx),
+            BoundAction::Command(command_str) => {
+                if let Err(e) = self.run_command(&command_str) {
+                    mt_log!(
+                        Level::Error,
+                        "Error '{}' while carrying out bound command '{}'",
+                        e,
+                        command_str
+                    );
+                }
+            }
+            BoundAction::Event(event_id) => self.process_event_id(&event_id),
+        }
+    }
+
+    fn perform_named_action(&mut self, named_action: NamedAction, ggez_ctx: &GgEzContext) {
+        match named_action {
+            // Toggle between mesh and instanced rendering
+            NamedAction::ToggleRenderMode => {
+                self.toggle_render_mode(ggez_ctx);
+            }
+            // Toggle whether obstacles block resource influence
+            NamedAction::ToggleObstacleBlocking => {
+                let blocked_by_obstacles = !self.resource_manager.blocked_by_obstacles();
+                self.resource_manager
+                    .set_blocked_by_obstacles(blocked_by_obstacles);
+                mt_log!(
+                    Level::Debug,
+                    "Obstacles blocking influence: {}",
+                    blocked_by_obstacles
+                );
+            }
+            // Toggle between per-resource and composited influence
+            NamedAction::ToggleInfluenceView => {
+                let influence_view = self.resource_manager.influence_view().toggled();
+                self.resource_manager.set_influence_view(influence_view);
+                mt_log!(Level::Debug, "Influence view set to {}", influence_view);
+            }
+            // Toggle debug display
+            NamedAction::ToggleDebugDisplay => {
+                if self.debug_display {
+                    self.debug_display = false;
+                    mt_log!(Level::Debug, "Debug display disabled");
+                } else {
+                    self.debug_display = true;
+                    mt_log!(Level::Debug, "Debug display enabled");
+                }
+            }
+            // Toggle statechart overlay
+            NamedAction::ToggleChartOverlay => {
+                self.chart_display = !self.chart_display;
+                mt_log!(
+                    Level::Debug,
+                    "Statechart overlay {}",
+                    if self.chart_display {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                );
+            }
+            // Open the console
+            NamedAction::OpenConsole => {
+                self.console.open();
+            }
+            // Pause or resume the game clock
+            NamedAction::TogglePause => {
+                if self.game_clock.toggle_pause() {
+                    mt_log!(Level::Info, "Game paused at {}", self.game_clock);
+                } else {
+                    mt_log!(Level::Info, "Game resumed at {}", self.game_clock);
+                }
+            }
+            // Step the paused game clock forward by a single update
+            NamedAction::StepClock => {
+                if !self.game_clock.step() {
+                    mt_log!(Level::Debug, "Game clock can only be stepped while paused");
+                }
+            }
+            // Slow down or speed up the game clock
+            NamedAction::SlowDown => {
+                mt_log!(
+                    Level::Info,
+                    "Game speed set to {}x",
+                    self.game_clock.slow_down()
+                );
+            }
+            NamedAction::SpeedUp => {
+                mt_log!(
+                    Level::Info,
+                    "Game speed set to {}x",
+                    self.game_clock.speed_up()
+                );
+            }
+            // Toggle the map editor
+            NamedAction::ToggleEditor => {
+                self.toggle_editor();
+            }
+            // Export the current world as an SVG image
+            NamedAction::ExportSvg => {
+                self.export_svg(ggez_ctx);
+            }
+            // Undo/redo world edits
+            NamedAction::Undo => {
+                self.undo_edit();
+            }
+            NamedAction::Redo => {
+                self.redo_edit();
+            }
+            // Reload the keymap, picking up any changes to its file
+            NamedAction::ReloadKeymap => {
+                self.reload_keymap();
+            }
+        }
     }
 
     fn draw_debug_info(&self, ggez_ctx: &mut GgEzContext) {
@@ -229,6 +1391,149 @@ impl<W: Write> SandCastingGameState<W> {
             (DEBUG_POS_STATE, 0.0, colors::YELLOW),
         )
         .unwrap(); //FIXME: NOOOOOO UNWRAP
+
+        // Draw render mode
+        let render_mode_display = ggez_gfx::Text::new((
+            format!(
+                "Render Mode: {}  Influence: {}  Particles: {}/{}",
+                self.render_mode,
+                self.resource_manager.influence_view(),
+                self.weather_particles.len(),
+                self.weather_particles.budget()
+            ),
+            ggez_gfx::Font::default(),
+            crate::DEFAULT_TEXT_SIZE,
+        ));
+        ggez_gfx::draw(
+            ggez_ctx,
+            &render_mode_display,
+            (DEBUG_POS_RENDER_MODE, 0.0, colors::YELLOW),
+        )
+        .unwrap();
+
+        // Draw the error from the last statechart reload if it failed, otherwise the chart's data
+        if let Some(reload_error) = self.chart_watcher.reload_error() {
+            let reload_error_display = ggez_gfx::Text::new((
+                reload_error.as_str(),
+                ggez_gfx::Font::default(),
+                crate::DEFAULT_TEXT_SIZE,
+            ));
+            ggez_gfx::draw(
+                ggez_ctx,
+                &reload_error_display,
+                (DEBUG_POS_STATECHART, 0.0, colors::RED),
+            )
+            .unwrap();
+        } else {
+            let data_strs: Vec<String> = self
+                .chart_bridge
+                .data_members()
+                .iter()
+                .map(|(id, value)| format!("{}={}", id, value))
+                .collect();
+            let chart_data_display = ggez_gfx::Text::new((
+                format!("Chart Data: {}", data_strs.join("  ")),
+                ggez_gfx::Font::default(),
+                crate::DEFAULT_TEXT_SIZE,
+            ));
+            ggez_gfx::draw(
+                ggez_ctx,
+                &chart_data_display,
+                (DEBUG_POS_STATECHART, 0.0, colors::YELLOW),
+            )
+            .unwrap();
+        }
+
+        // Warn that the chart's states will be lost on reload
+        if self.chart_watcher.journal_overflowed() {
+            let journal_display = ggez_gfx::Text::new((
+                "Statechart journal overflowed, reload will reset the chart",
+                ggez_gfx::Font::default(),
+                crate::DEFAULT_TEXT_SIZE,
+            ));
+            ggez_gfx::draw(
+                ggez_ctx,
+                &journal_display,
+                (DEBUG_POS_CHART_JOURNAL, 0.0, colors::ORANGE),
+            )
+            .unwrap();
+        }
+
+        // Draw game clock
+        let clock_display = ggez_gfx::Text::new((
+            format!(
+                "Game Time: {}  Sim Rate: {:.0} Hz",
+                self.game_clock,
+                self.sim_timestep.rate()
+            ),
+            ggez_gfx::Font::default(),
+            crate::DEFAULT_TEXT_SIZE,
+        ));
+        ggez_gfx::draw(
+            ggez_ctx,
+            &clock_display,
+            (DEBUG_POS_CLOCK, 0.0, colors::YELLOW),
+        )
+        .unwrap();
+    }
+
+    /// Advances the simulation by a single fixed step
+    fn step_simulation(&mut self, ggez_ctx: &mut GgEzContext) {
+        // Advance game time, leaving the simulation untouched while the clock is paused
+        let time_step = self.game_clock.tick(self.sim_timestep.step());
+        if time_step > Duration::default() {
+            // Update weather
+            mt_log!(Level::Trace, "Updating weather...");
+            let game_time = self.game_clock.time();
+            self.weather_manager
+                .update_weather(game_time, &self.ci_ctx, ggez_ctx);
+            self.weather_fronts.update(game_time, &self.ci_ctx);
+            self.update_weather_particles(time_step, ggez_ctx);
+        }
+
+        // Deliver any events published since the last step
+        self.dispatch_events(ggez_ctx);
+
+        // Keep the statechart up to date with the game, and let it act on the game in turn
+        self.sync_statechart();
+
+        // Update TPS
+        self.profiler.record_sim_tick(ggez_ctx).unwrap();
+    }
+
+    /// Advances the weather particles by the given game time, marking when the budget caps spawning
+    fn update_weather_particles(&mut self, time_step: Duration, ggez_ctx: &GgEzContext) {
+        let was_budget_reached = self.weather_particles.budget_reached();
+
+        self.weather_particles.update(
+            self.weather_fronts.cells(),
+            self.ci_ctx.max_weather_intensity(),
+            time_step.as_secs_f32(),
+            ggez_ctx,
+        );
+
+        if self.weather_particles.budget_reached() && !was_budget_reached {
+            self.profiler
+                .mark_event(String::from("PARTICLE_BUDGET_REACHED"), ggez_ctx)
+                .unwrap();
+        }
+    }
+
+    /// Switches between mesh and instanced rendering, marking the switch in the profiler
+    fn toggle_render_mode(&mut self, ggez_ctx: &GgEzContext) {
+        self.render_mode = self.render_mode.toggled();
+
+        self.profiler
+            .mark_event(
+                format!(
+                    "RENDER_MODE_{}",
+                    self.render_mode.to_string().to_uppercase()
+                ),
+                ggez_ctx,
+            )
+            .unwrap();
+
+        mt_log!(Level::Info, "Render mode set to {}", self.render_mode);
     }
 }
 
@@ -245,19 +1550,42 @@ impl<W: Write> ggez_event::EventHandler<ggez::error::GameError> for SandCastingG
     fn update(&mut self, ggez_ctx: &mut GgEzContext) -> GgEzGameResult<()> {
         // Check if first-frame initialization is required
         if !self.initialized() {
-            self.initialize(ggez_ctx);
+            self.initialize();
         }
 
-        // Check if we've reached an update
-        while ggez_timer::check_update_time(ggez_ctx, crate::DESIRED_FPS) {
-            // Update weather
-            mt_log!(Level::Trace, "Updating weather...");
-            self.weather_manager.update_weather(&self.ci_ctx, ggez_ctx);
+        // Swap in the statechart if its file has changed
+        if self
+            .chart_watcher
+            .poll(ggez_timer::time_since_start(ggez_ctx))
+        {
+            self.reload_statechart(ggez_ctx);
+        }
 
-            // Update FPS
-            self.profiler.update_fps_stats(ggez_ctx).unwrap();
+        // Spend the real time passed since the last frame in fixed simulation steps
+        let sim_steps = self.sim_timestep.accumulate(ggez_timer::delta(ggez_ctx));
+        if self.sim_timestep.capped() {
+            mt_log!(
+                Level::Debug,
+                "Simulation fell behind, capped at {} steps this frame",
+                sim_steps
+            );
+            self.profiler
+                .mark_event(String::from("SIM_STEPS_CAPPED"), ggez_ctx)
+                .unwrap();
+        }
+        for _step in 0..sim_steps {
+            self.step_simulation(ggez_ctx);
         }
 
+        // Start the overlay's flash for any events accepted this frame
+        self.chart_overlay.update(ggez_ctx);
+
+        // Resume console text input skipped while opening it
+        self.console.update();
+
+        // Update FPS
+        self.profiler.update_fps_stats(ggez_ctx).unwrap();
+
         Ok(())
     }
 
@@ -271,17 +1599,42 @@ impl<W: Write> ggez_event::EventHandler<ggez::error::GameError> for SandCastingG
         let start_time = ggez_timer::time_since_start(ctx);
         let mut draw_timings = Vec::new();
 
-        // Change background color based on game state
-        if self.active_state_ids().contains(&"combat") {
-            ggez_gfx::clear(ctx, colors::RED);
-        } else {
-            ggez_gfx::clear(ctx, colors::BLACK);
-        }
+        // Change background color as set by the statechart
+        ggez_gfx::clear(ctx, self.background.unwrap_or(colors::BLACK));
         draw_timings.push(profiler::StackedTime {
             label: String::from("Clear"),
             time: ggez_timer::time_since_start(ctx),
         });
 
+        // Rebuild only the geometry changed since the last frame
+        self.world_grid_manager
+            .update_geometry(self.render_mode, ctx);
+        self.resource_manager.update_influence(
+            &self.world_grid_manager,
+            &self.obstacle_manager,
+            &self.ci_ctx,
+            ctx,
+        );
+        self.resource_manager
+            .update_geometry(self.render_mode, &self.ci_ctx, ctx);
+        self.obstacle_manager
+            .update_geometry(self.render_mode, &self.ci_ctx, ctx);
+        self.actor_manager
+            .update_geometry(self.render_mode, &self.ci_ctx, ctx);
+        self.weather_fronts.update_geometry(ctx);
+
+        // Draw moving things part-way to the next step, except while paused, where they hold still
+        let render_alpha = if self.game_clock.is_paused() {
+            1.0
+        } else {
+            self.sim_timestep.alpha()
+        };
+        self.weather_particles.update_geometry(render_alpha, ctx);
+        draw_timings.push(profiler::StackedTime {
+            label: String::from("Geometry Update"),
+            time: ggez_timer::time_since_start(ctx),
+        });
+
         // Draw the weather HUD
         self.weather_manager.draw(ctx);
         draw_timings.push(profiler::StackedTime {
@@ -290,33 +1643,83 @@ impl<W: Write> ggez_event::EventHandler<ggez::error::GameError> for SandCastingG
         });
 
         // Draw the hex grid
-        self.world_grid_manager.draw(ctx);
+        self.world_grid_manager.draw(self.render_mode, ctx);
         draw_timings.push(profiler::StackedTime {
             label: String::from("WorldGrid"),
             time: ggez_timer::time_since_start(ctx),
         });
 
+        // Draw weather fronts over the grid
+        self.weather_fronts.draw(ctx);
+        draw_timings.push(profiler::StackedTime {
+            label: String::from("Weather Fronts"),
+            time: ggez_timer::time_since_start(ctx),
+        });
+
         // Draw resources
-        self.resource_manager.draw(ctx);
+        self.resource_manager.draw(self.render_mode, ctx);
         draw_timings.push(profiler::StackedTime {
             label: String::from("Resources"),
             time: ggez_timer::time_since_start(ctx),
         });
 
         // Draw obstacles
-        self.obstacle_manager.draw(ctx);
+        self.obstacle_manager.draw(self.render_mode, ctx);
         draw_timings.push(profiler::StackedTime {
             label: String::from("Obstacles"),
             time: ggez_timer::time_since_start(ctx),
         });
 
         // Draw actors
-        self.actor_manager.draw(ctx);
+        self.actor_manager.draw(self.render_mode, ctx);
         draw_timings.push(profiler::StackedTime {
             label: String::from("Actors"),
             time: ggez_timer::time_since_start(ctx),
         });
 
+        // Draw weather particles over everything in the world
+        self.weather_particles.draw(ctx);
+        draw_timings.push(profiler::StackedTime {
+            label: String::from("Weather Particles"),
+            time: ggez_timer::time_since_start(ctx),
+        });
+
+        // Draw notifications of notable events
+        self.toasts.draw(ctx);
+        draw_timings.push(profiler::StackedTime {
+            label: String::from("Toasts"),
+            time: ggez_timer::time_since_start(ctx),
+        });
+
+        // Draw the map editor HUD
+        if self.in_editor() {
+            self.map_editor.draw(ctx);
+            self.draw_history_panel(ctx);
+            draw_timings.push(profiler::StackedTime {
+                label: String::from("Editor"),
+                time: ggez_timer::time_since_start(ctx),
+            });
+        }
+
+        // Draw the statechart overlay
+        if self.chart_display {
+            self.chart_overlay
+                .draw(&self.statechart.active_state_ids(), ctx);
+            draw_timings.push(profiler::StackedTime {
+                label: String::from("Statechart"),
+                time: ggez_timer::time_since_start(ctx),
+            });
+        }
+
+        // Draw the console over everything but the debug info
+        if self.console.is_open() {
+            self.console.draw(ctx);
+            draw_timings.push(profiler::StackedTime {
+                label: String::from("Console"),
+                time: ggez_timer::time_since_start(ctx),
+            });
+        }
+
         if self.debug_display {
             // Draw performance stats
             self.profiler.draw_fps_stats(ctx);
@@ -371,9 +1774,31 @@ impl<W: Write> ggez_event::EventHandler<ggez::error::GameError> for SandCastingG
                         event_hex_pos
                     );
 
-                    self.world_grid_manager
-                        .toggle_cell_highlight(&event_hex_pos, ggez_ctx)
-                        .unwrap();
+                    // Route the click to the editor while editing, otherwise highlight the cell
+                    if self.in_editor() {
+                        if let Some(action) = self.map_editor.press(event_hex_pos) {
+                            self.apply_editor_action(action);
+                        }
+                    } else {
+                        let mut selected_actor = None;
+                        for (id, entity) in self.entities().at(&event_hex_pos) {
+                            mt_log!(Level::Info, "Selected entity {}: {}", id, entity);
+                            if entity.kind() == InstanceKind::Actor {
+                                selected_actor = Some(id);
+                            }
+                        }
+                        self.selected_actor = selected_actor;
+                        if let Some(cell_weather) = self.weather_at(&event_hex_pos) {
+                            mt_log!(Level::Info, "Selected cell has {}", cell_weather);
+                        }
+                        self.event_bus
+                            .publish(GameEvent::CellSelected(event_hex_pos));
+
+                        let highlight_edit = WorldEdit::ToggleHighlight(event_hex_pos);
+                        if let Err(e) = self.execute_edit(highlight_edit) {
+                            mt_log!(Level::Warning, "{}", e);
+                        }
+                    }
                 } else {
                     mt_log!(
                         Level::Debug,
@@ -384,73 +1809,102 @@ impl<W: Write> ggez_event::EventHandler<ggez::error::GameError> for SandCastingG
                     );
                 }
             }
+            // Perform the action bound to any other button in the active states
             _ => {
-                mt_log!(Level::Warning, "Mouse Event ({:?}) unimplemented!", button);
+                let input = Input::Mouse(ggez_kb::active_mods(ggez_ctx), button);
+                let bound_action = self
+                    .keymap
+                    .action(&input, &self.active_state_ids())
+                    .cloned();
+                match bound_action {
+                    Some(action) => self.perform_bound_action(action, ggez_ctx),
+                    None => mt_log!(Level::Warning, "Mouse Event ({:?}) unbound!", button),
+                }
             }
         }
     }
 
-    fn key_down_event(
+    fn mouse_button_up_event(
         &mut self,
         _ggez_ctx: &mut GgEzContext,
+        button: ggez_mouse::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        // Releasing the left button commits any in-progress editor stroke
+        if button == ggez_mouse::MouseButton::Left && self.in_editor() {
+            if let Some(action) = self.map_editor.release() {
+                self.apply_editor_action(action);
+            }
+        }
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ggez_ctx: &mut GgEzContext,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) {
+        // Only drags within the editor are of interest
+        if !self.map_editor.stroke_active() || !self.in_editor() {
+            return;
+        }
+
+        let event_coords = ggez_mint::Point2 { x, y };
+        if let Ok(event_hex_pos) =
+            HexGridCell::pixel_to_hex_coords(event_coords, &self.ci_ctx, ggez_ctx)
+        {
+            // Ignore positions that fall outside the world grid
+            if self
+                .world_grid_manager
+                .hex_map()
+                .contains_key(&event_hex_pos)
+            {
+                self.map_editor.drag(event_hex_pos, &self.ci_ctx);
+            }
+        }
+    }
+
+    fn key_down_event(
+        &mut self,
+        ggez_ctx: &mut GgEzContext,
         keycode: ggez_kb::KeyCode,
         keymods: ggez_kb::KeyMods,
         repeat: bool,
     ) {
+        // The open console takes every key press, including repeats of held keys such as Backspace
+        if self.console.is_open() {
+            let event_ids = self.chart_overlay.layout().event_ids();
+            if let Some(command_str) = self.console.handle_key(keycode, &event_ids) {
+                self.run_console_command(&command_str);
+            }
+            return;
+        }
+
         // Ignore repeat inputs (for now)
         if repeat {
             return;
         }
 
-        // Otherwise, check the Mod + Key tuple and handle accordingly
-        match (keymods, keycode) {
-            // Toggle debug display
-            (ggez_kb::KeyMods::NONE, ggez_kb::KeyCode::D) => {
-                if self.debug_display {
-                    self.debug_display = false;
-                    mt_log!(Level::Debug, "Debug display disabled");
-                } else {
-                    self.debug_display = true;
-                    mt_log!(Level::Debug, "Debug display enabled");
-                }
-            }
-            // Prompt for an Event ID
-            (ggez_kb::KeyMods::NONE, ggez_kb::KeyCode::E) => {
-                let mut user_event_id = String::new();
-
-                // Get Event ID from user
-                print!("Enter valid Event ID: ");
-                if let Err(e) = std::io::stdout().flush() {
-                    mt_log!(Level::Error, "Error '{}' while flushing StdOut", e);
-                    return;
-                }
-                if let Err(e) = std::io::stdin().read_line(&mut user_event_id) {
-                    mt_log!(Level::Error, "Error '{}' while reading line from StdIn", e);
-                    return;
-                }
+        // Give the editor first refusal on key presses while editing
+        if self.in_editor() && self.map_editor.handle_key(keymods, keycode) {
+            return;
+        }
 
-                // Parse into Event and process
-                match dd_statechart::event::Event::from(user_event_id.trim_end()) {
-                    Ok(user_event) => self.process_event(&user_event).unwrap_or_else(|e| {
-                        mt_log!(
-                            Level::Error,
-                            "Error '{}' while processing event '{}'",
-                            e,
-                            user_event
-                        )
-                    }),
-                    Err(e) => mt_log!(
-                        Level::Error,
-                        "Error '{}' while parsing Event ID '{}'",
-                        e,
-                        user_event_id
-                    ),
-                }
-            }
-            _ => {
+        // Otherwise, perform the action bound to the Mod + Key tuple in the active states
+        let input = Input::Key(keymods, keycode);
+        let bound_action = self
+            .keymap
+            .action(&input, &self.active_state_ids())
+            .cloned();
+        match bound_action {
+            Some(action) => self.perform_bound_action(action, ggez_ctx),
+            None => {
                 mt_log!(
                     Level::Warning,
-                    "Keyboard Event ({:?} + {:?}) unimplemented!",
+                    "Keyboard Event ({:?} + {:?}) unbound!",
                     keymods,
                     keycode
                 );
@@ -458,6 +1912,10 @@ impl<W: Write> ggez_event::EventHandler<ggez::error::GameError> for SandCastingG
         }
     }
 
+    fn text_input_event(&mut self, _ctx: &mut GgEzContext, character: char) {
+        self.console.handle_text(character);
+    }
+
     fn quit_event(&mut self, _ctx: &mut GgEzContext) -> bool {
         mt_log!(Level::Debug, "Quit requested. Shutting down...");
 
@@ -493,6 +1951,42 @@ impl fmt::Display for GameStateError {
             Self::StateChartError(sc_err) => {
                 write!(f, "StateChartError '{}' encountered", sc_err)
             }
+            Self::GameCommandError(cmd_err) => {
+                write!(f, "GameCommandError '{}' encountered", cmd_err)
+            }
+            Self::WeatherManagerError(weather_err) => {
+                write!(f, "WeatherManagerError '{}' encountered", weather_err)
+            }
+            Self::StatechartNotFound(chart_path) => {
+                write!(f, "No statechart found at '{}'", chart_path.display())
+            }
+            Self::InvalidStatechart(chart_path, sc_err) => {
+                write!(
+                    f,
+                    "Statechart '{}' is invalid: {}",
+                    chart_path.display(),
+                    sc_err
+                )
+            }
+            Self::EmbeddedStatechartUnavailable(embedded_path, io_err) => {
+                write!(
+                    f,
+                    "Built-in statechart could not be written to '{}': {}",
+                    embedded_path.display(),
+                    io_err
+                )
+            }
+            Self::EditFailed(edit_description, edit_err) => {
+                write!(f, "Edit '{}' failed: {}", edit_description, edit_err)
+            }
+            Self::MapNotSaved(map_path, map_err) => {
+                write!(
+                    f,
+                    "Map could not be saved to '{}': {}",
+                    map_path.display(),
+                    map_err
+                )
+            }
         }
     }
 }
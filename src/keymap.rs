@@ -0,0 +1,613 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : keymap.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines the keymap file, which binds keys and mouse buttons to actions
+    of the game, game commands and statechart events.
+
+    Keymap files are plain text, one binding per line:
+        bind <state ID> <input> action <action name>
+        bind <state ID> <input> command <game command>
+        bind <state ID> <input> event <event ID>
+    A state ID of '*' binds the input in every state. Bindings for an active
+    state take precedence over those for every state.
+    Inputs are a key or mouse button, optionally preceded by modifiers, e.g.
+    'd', 'ctrl+s', 'shift+mouse.right'. Key names follow ggez's KeyCode.
+    Blank lines and lines beginning with '#' are ignored.
+
+    An input bound more than once in the same state, or which is reserved by
+    the game, is reported as a conflict and its later bindings are ignored.
+    An input bound in every state but reserved in some, such as the map
+    editor's keys, is also reported, though its binding still applies in the
+    other states.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use dd_statechart::event::Event;
+
+use ggez::input::{keyboard as ggez_kb, mouse as ggez_mouse};
+
+use crate::{chart_watcher, map_editor};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Location of the default keymap file, relative to the executable or working directory
+const DEFAULT_KEYMAP_RELATIVE_PATH: &str = "res/default.keymap";
+
+/// Built-in copy of the default keymap, used when the keymap file cannot be found
+pub const EMBEDDED_KEYMAP: &str = include_str!("../res/default.keymap");
+
+/// State ID binding an input in every state
+const ANY_STATE_ID: &str = "*";
+
+/// Modifier names, in the order they are written
+const MODIFIER_NAMES: [(&str, ggez_kb::KeyMods); 4] = [
+    ("ctrl", ggez_kb::KeyMods::CTRL),
+    ("shift", ggez_kb::KeyMods::SHIFT),
+    ("alt", ggez_kb::KeyMods::ALT),
+    ("logo", ggez_kb::KeyMods::LOGO),
+];
+
+/// Keys which may be bound, named in keymap files as their KeyCode in lowercase
+const BINDABLE_KEYS: [ggez_kb::KeyCode; 85] = [
+    ggez_kb::KeyCode::Key1,
+    ggez_kb::KeyCode::Key2,
+    ggez_kb::KeyCode::Key3,
+    ggez_kb::KeyCode::Key4,
+    ggez_kb::KeyCode::Key5,
+    ggez_kb::KeyCode::Key6,
+    ggez_kb::KeyCode::Key7,
+    ggez_kb::KeyCode::Key8,
+    ggez_kb::KeyCode::Key9,
+    ggez_kb::KeyCode::Key0,
+    ggez_kb::KeyCode::A,
+    ggez_kb::KeyCode::B,
+    ggez_kb::KeyCode::C,
+    ggez_kb::KeyCode::D,
+    ggez_kb::KeyCode::E,
+    ggez_kb::KeyCode::F,
+    ggez_kb::KeyCode::G,
+    ggez_kb::KeyCode::H,
+    ggez_kb::KeyCode::I,
+    ggez_kb::KeyCode::J,
+    ggez_kb::KeyCode::K,
+    ggez_kb::KeyCode::L,
+    ggez_kb::KeyCode::M,
+    ggez_kb::KeyCode::N,
+    ggez_kb::KeyCode::O,
+    ggez_kb::KeyCode::P,
+    ggez_kb::KeyCode::Q,
+    ggez_kb::KeyCode::R,
+    ggez_kb::KeyCode::S,
+    ggez_kb::KeyCode::T,
+    ggez_kb::KeyCode::U,
+    ggez_kb::KeyCode::V,
+    ggez_kb::KeyCode::W,
+    ggez_kb::KeyCode::X,
+    ggez_kb::KeyCode::Y,
+    ggez_kb::KeyCode::Z,
+    ggez_kb::KeyCode::Escape,
+    ggez_kb::KeyCode::F1,
+    ggez_kb::KeyCode::F2,
+    ggez_kb::KeyCode::F3,
+    ggez_kb::KeyCode::F4,
+    ggez_kb::KeyCode::F5,
+    ggez_kb::KeyCode::F6,
+    ggez_kb::KeyCode::F7,
+    ggez_kb::KeyCode::F8,
+    ggez_kb::KeyCode::F9,
+    ggez_kb::KeyCode::F10,
+    ggez_kb::KeyCode::F11,
+    ggez_kb::KeyCode::F12,
+    ggez_kb::KeyCode::Insert,
+    ggez_kb::KeyCode::Home,
+    ggez_kb::KeyCode::Delete,
+    ggez_kb::KeyCode::End,
+    ggez_kb::KeyCode::PageDown,
+    ggez_kb::KeyCode::PageUp,
+    ggez_kb::KeyCode::Left,
+    ggez_kb::KeyCode::Up,
+    ggez_kb::KeyCode::Right,
+    ggez_kb::KeyCode::Down,
+    ggez_kb::KeyCode::Back,
+    ggez_kb::KeyCode::Return,
+    ggez_kb::KeyCode::Space,
+    ggez_kb::KeyCode::Tab,
+    ggez_kb::KeyCode::Numpad0,
+    ggez_kb::KeyCode::Numpad1,
+    ggez_kb::KeyCode::Numpad2,
+    ggez_kb::KeyCode::Numpad3,
+    ggez_kb::KeyCode::Numpad4,
+    ggez_kb::KeyCode::Numpad5,
+    ggez_kb::KeyCode::Numpad6,
+    ggez_kb::KeyCode::Numpad7,
+    ggez_kb::KeyCode::Numpad8,
+    ggez_kb::KeyCode::Numpad9,
+    ggez_kb::KeyCode::NumpadEnter,
+    ggez_kb::KeyCode::Apostrophe,
+    ggez_kb::KeyCode::Backslash,
+    ggez_kb::KeyCode::Comma,
+    ggez_kb::KeyCode::Equals,
+    ggez_kb::KeyCode::Grave,
+    ggez_kb::KeyCode::LBracket,
+    ggez_kb::KeyCode::Minus,
+    ggez_kb::KeyCode::Period,
+    ggez_kb::KeyCode::RBracket,
+    ggez_kb::KeyCode::Semicolon,
+    ggez_kb::KeyCode::Slash,
+];
+
+/// Actions of the game which may be bound, by name
+const NAMED_ACTIONS: [(&str, NamedAction); 15] = [
+    ("render_mode", NamedAction::ToggleRenderMode),
+    ("obstacle_blocking", NamedAction::ToggleObstacleBlocking),
+    ("influence_view", NamedAction::ToggleInfluenceView),
+    ("debug", NamedAction::ToggleDebugDisplay),
+    ("chart_overlay", NamedAction::ToggleChartOverlay),
+    ("console", NamedAction::OpenConsole),
+    ("pause", NamedAction::TogglePause),
+    ("step", NamedAction::StepClock),
+    ("slow_down", NamedAction::SlowDown),
+    ("speed_up", NamedAction::SpeedUp),
+    ("editor", NamedAction::ToggleEditor),
+    ("export_svg", NamedAction::ExportSvg),
+    ("undo", NamedAction::Undo),
+    ("redo", NamedAction::Redo),
+    ("reload_keymap", NamedAction::ReloadKeymap),
+];
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// A key or mouse button, along with the modifiers held
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Input {
+    Key(ggez_kb::KeyMods, ggez_kb::KeyCode),
+    Mouse(ggez_kb::KeyMods, ggez_mouse::MouseButton),
+}
+
+/// Actions of the game which are not game commands, as they only make sense when bound to input
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NamedAction {
+    ToggleRenderMode,
+    ToggleObstacleBlocking,
+    ToggleInfluenceView,
+    ToggleDebugDisplay,
+    ToggleChartOverlay,
+    OpenConsole,
+    TogglePause,
+    StepClock,
+    SlowDown,
+    SpeedUp,
+    ToggleEditor,
+    ExportSvg,
+    Undo,
+    Redo,
+    ReloadKeymap,
+}
+
+/// What is done when a bound input is pressed
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundAction {
+    /// Performs the given action of the game
+    Named(NamedAction),
+    /// Carries out the given game command
+    Command(String),
+    /// Sends the event with the given ID to the statechart
+    Event(String),
+}
+
+/// An input bound to an action, in one state or all of them
+struct Binding {
+    line_num: usize,          // Line of the keymap file on which the binding was made
+    state_id: Option<String>, // ID of the state in which the binding applies, or None for all
+    input: Input,             // Key or mouse button bound
+    action: BoundAction,      // Action performed on pressing the input
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeymapConflict {
+    /// An input bound more than once in the same state, with the line of the binding kept
+    AlreadyBound(usize, Input, Option<String>, usize),
+    /// An input which the game handles itself, with what it is used for
+    ReservedInput(usize, Input, &'static str),
+    /// An input bound in every state which the game handles itself in some, with what it is used for
+    ShadowedInput(usize, Input, &'static str),
+}
+
+#[derive(Default)]
+pub struct Keymap {
+    bindings: Vec<Binding>, // Bindings in the order they appear in the file
+    conflicts: Vec<KeymapConflict>, // Conflicting bindings, which were ignored
+}
+
+#[derive(Debug)]
+pub enum KeymapError {
+    // Wrappers
+    IoError(io::Error),
+
+    // Parsing
+    UnknownRecord(usize, String),
+    MalformedRecord(usize, String),
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl Keymap {
+    /// Loads and parses the keymap file at the given path
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, KeymapError> {
+        let contents = fs::read_to_string(path).map_err(KeymapError::IoError)?;
+
+        Self::parse(&contents)
+    }
+
+    /// Parses keymap file contents, noting any conflicting bindings
+    pub fn parse(contents: &str) -> Result<Self, KeymapError> {
+        let mut keymap = Self::default();
+
+        for (i, line) in contents.lines().enumerate() {
+            // Line numbers are 1-based for error reporting
+            let line_num = i + 1;
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["bind", state_id, input_str, kind, action_fields @ ..] => {
+                    let input = parse_input(input_str).ok_or_else(|| {
+                        KeymapError::MalformedRecord(
+                            line_num,
+                            format!("unknown input '{}'", input_str),
+                        )
+                    })?;
+                    let action = parse_action(kind, action_fields, line_num)?;
+                    let state_id = match *state_id {
+                        ANY_STATE_ID => None,
+                        state_id => Some(String::from(state_id)),
+                    };
+
+                    keymap.add_binding(Binding {
+                        line_num,
+                        state_id,
+                        input,
+                        action,
+                    });
+                }
+                ["bind", ..] => {
+                    return Err(KeymapError::MalformedRecord(
+                        line_num,
+                        String::from("bind requires a state ID, an input and an action"),
+                    ));
+                }
+                [record, ..] => {
+                    return Err(KeymapError::UnknownRecord(line_num, String::from(*record)));
+                }
+                [] => {}
+            }
+        }
+
+        Ok(keymap)
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn conflicts(&self) -> &Vec<KeymapConflict> {
+        &self.conflicts
+    }
+
+    /// Returns the IDs of the states with bindings of their own, without repeats
+    pub fn state_ids(&self) -> Vec<&str> {
+        let mut state_ids: Vec<&str> = self
+            .bindings
+            .iter()
+            .filter_map(|binding| binding.state_id.as_deref())
+            .collect();
+        state_ids.sort_unstable();
+        state_ids.dedup();
+
+        state_ids
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the action bound to the given input in the given active states, if any
+    ///
+    /// Bindings for an active state are preferred over those for every state.
+    pub fn action(&self, input: &Input, active_state_ids: &[&str]) -> Option<&BoundAction> {
+        let mut input_bindings = self
+            .bindings
+            .iter()
+            .filter(|binding| binding.input == *input);
+
+        input_bindings
+            .clone()
+            .find(|binding| {
+                binding
+                    .state_id
+                    .as_deref()
+                    .map_or(false, |state_id| active_state_ids.contains(&state_id))
+            })
+            .or_else(|| input_bindings.find(|binding| binding.state_id.is_none()))
+            .map(|binding| &binding.action)
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Adds the given binding, unless it conflicts with an earlier one or a reserved input
+    fn add_binding(&mut self, binding: Binding) {
+        if let Some(reserved_use) = reserved_use(&binding.input) {
+            self.conflicts.push(KeymapConflict::ReservedInput(
+                binding.line_num,
+                binding.input,
+                reserved_use,
+            ));
+            return;
+        }
+
+        // Editor keys are only reserved while editing, so are kept for the other states
+        if let Some(editor_use) = editor_use(&binding.input) {
+            match &binding.state_id {
+                Some(state_id) if state_id == map_editor::EDITOR_STATE_ID => {
+                    self.conflicts.push(KeymapConflict::ReservedInput(
+                        binding.line_num,
+                        binding.input,
+                        editor_use,
+                    ));
+                    return;
+                }
+                Some(_state_id) => {}
+                None => {
+                    self.conflicts.push(KeymapConflict::ShadowedInput(
+                        binding.line_num,
+                        binding.input,
+                        editor_use,
+                    ));
+                }
+            }
+        }
+
+        if let Some(existing_binding) = self.bindings.iter().find(|existing_binding| {
+            existing_binding.input == binding.input && existing_binding.state_id == binding.state_id
+        }) {
+            self.conflicts.push(KeymapConflict::AlreadyBound(
+                binding.line_num,
+                binding.input,
+                binding.state_id,
+                existing_binding.line_num,
+            ));
+            return;
+        }
+
+        self.bindings.push(binding);
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keymods = match self {
+            Self::Key(keymods, _keycode) => keymods,
+            Self::Mouse(keymods, _button) => keymods,
+        };
+        for (modifier_name, modifier) in MODIFIER_NAMES.iter() {
+            if keymods.contains(*modifier) {
+                write!(f, "{}+", modifier_name)?;
+            }
+        }
+
+        match self {
+            Self::Key(_keymods, keycode) => {
+                write!(f, "{}", format!("{:?}", keycode).to_lowercase())
+            }
+            Self::Mouse(_keymods, ggez_mouse::MouseButton::Other(button_num)) => {
+                write!(f, "mouse.{}", button_num)
+            }
+            Self::Mouse(_keymods, button) => {
+                write!(f, "mouse.{}", format!("{:?}", button).to_lowercase())
+            }
+        }
+    }
+}
+
+impl fmt::Display for NamedAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let action_name = NAMED_ACTIONS
+            .iter()
+            .find(|(_action_name, action)| action == self)
+            .map_or("unknown", |(action_name, _action)| action_name);
+
+        write!(f, "{}", action_name)
+    }
+}
+
+impl fmt::Display for BoundAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Named(named_action) => write!(f, "action {}", named_action),
+            Self::Command(command_str) => write!(f, "command {}", command_str),
+            Self::Event(event_id) => write!(f, "event {}", event_id),
+        }
+    }
+}
+
+impl fmt::Display for KeymapConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::AlreadyBound(line_num, input, state_id, bound_line_num) => {
+                write!(
+                    f,
+                    "Line {}: '{}' is already bound in {} on line {}",
+                    line_num,
+                    input,
+                    state_id.as_ref().map_or_else(
+                        || String::from("every state"),
+                        |state_id| format!("state '{}'", state_id)
+                    ),
+                    bound_line_num
+                )
+            }
+            Self::ReservedInput(line_num, input, reserved_use) => {
+                write!(
+                    f,
+                    "Line {}: '{}' is reserved for {}",
+                    line_num, input, reserved_use
+                )
+            }
+            Self::ShadowedInput(line_num, input, reserved_use) => {
+                write!(
+                    f,
+                    "Line {}: '{}' is reserved for {}, so is not bound there",
+                    line_num, input, reserved_use
+                )
+            }
+        }
+    }
+}
+
+impl Error for KeymapError {}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IoError(io_err) => {
+                write!(f, "IoError '{}' encountered", io_err)
+            }
+            Self::UnknownRecord(line_num, record) => {
+                write!(f, "Line {}: Unknown record type '{}'", line_num, record)
+            }
+            Self::MalformedRecord(line_num, reason) => {
+                write!(f, "Line {}: Malformed record, {}", line_num, reason)
+            }
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the path of the default keymap
+pub fn default_keymap_path() -> PathBuf {
+    chart_watcher::default_resource_path(DEFAULT_KEYMAP_RELATIVE_PATH)
+}
+
+/// Parses an input from its modifiers and key or mouse button, joined by '+'
+fn parse_input(input_str: &str) -> Option<Input> {
+    let input_str = input_str.to_lowercase();
+    let mut parts: Vec<&str> = input_str.split('+').collect();
+    let button_str = parts.pop()?;
+
+    let mut keymods = ggez_kb::KeyMods::NONE;
+    for part in parts {
+        let (_modifier_name, modifier) = MODIFIER_NAMES
+            .iter()
+            .find(|(modifier_name, _modifier)| *modifier_name == part)?;
+        keymods |= *modifier;
+    }
+
+    if let Some(mouse_str) = button_str.strip_prefix("mouse.") {
+        let button = match mouse_str {
+            "left" => ggez_mouse::MouseButton::Left,
+            "right" => ggez_mouse::MouseButton::Right,
+            "middle" => ggez_mouse::MouseButton::Middle,
+            other_str => ggez_mouse::MouseButton::Other(other_str.parse::<u16>().ok()?),
+        };
+
+        return Some(Input::Mouse(keymods, button));
+    }
+
+    BINDABLE_KEYS
+        .iter()
+        .find(|keycode| format!("{:?}", keycode).to_lowercase() == button_str)
+        .map(|keycode| Input::Key(keymods, *keycode))
+}
+
+fn parse_action(
+    kind: &str,
+    action_fields: &[&str],
+    line_num: usize,
+) -> Result<BoundAction, KeymapError> {
+    let malformed = |reason: String| KeymapError::MalformedRecord(line_num, reason);
+
+    match (kind, action_fields) {
+        ("action", [action_name]) => NAMED_ACTIONS
+            .iter()
+            .find(|(name, _action)| name == action_name)
+            .map(|(_name, action)| BoundAction::Named(*action))
+            .ok_or_else(|| malformed(format!("unknown action '{}'", action_name))),
+        ("command", [_keyword, ..]) => Ok(BoundAction::Command(action_fields.join(" "))),
+        ("event", [event_id]) => Event::from(event_id)
+            .map(|_event| BoundAction::Event(String::from(*event_id)))
+            .map_err(|e| malformed(format!("invalid event ID '{}': {}", event_id, e))),
+        ("action", _) | ("event", _) => Err(malformed(format!("{} requires a single name", kind))),
+        ("command", _) => Err(malformed(String::from("command requires a game command"))),
+        (kind, _) => Err(malformed(format!("unknown binding kind '{}'", kind))),
+    }
+}
+
+/// Returns what the given input is used for, if the game handles it itself in every state
+fn reserved_use(input: &Input) -> Option<&'static str> {
+    match input {
+        Input::Mouse(_keymods, ggez_mouse::MouseButton::Left) => {
+            Some("selecting and editing cells")
+        }
+        _ => None,
+    }
+}
+
+/// Returns what the given input is used for, if the map editor handles it itself
+fn editor_use(input: &Input) -> Option<&'static str> {
+    match input {
+        Input::Key(keymods, keycode) if *keymods == ggez_kb::KeyMods::NONE => {
+            map_editor::EDITOR_KEYS
+                .iter()
+                .find(|(editor_keycode, _editor_use)| editor_keycode == keycode)
+                .map(|(_editor_keycode, editor_use)| *editor_use)
+        }
+        _ => None,
+    }
+}
@@ -19,7 +19,7 @@ Purpose:
 
 \* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
 
-use std::env;
+use std::{env, path::PathBuf, process, time::Duration};
 
 use cast_iron::{
     ability::{aspect::*, Ability},
@@ -33,21 +33,53 @@ use ggez::{
     ContextBuilder as GgEzContextBuilder,
 };
 
-use mt_logger::{mt_log, mt_new, Level, OutputStream};
+use mt_logger::{mt_flush, mt_log, mt_new, Level, OutputStream};
 
 ///
 // Module Declarations
 ///
+pub mod chart_bridge;
+
+pub mod chart_layout;
+
+pub mod chart_watcher;
+use chart_watcher::ChartSource;
+
+pub mod console;
+
+pub mod edit_history;
+
+pub mod event_bus;
+
+pub mod fixed_timestep;
+
 pub mod game_assets;
-use game_assets::colors;
+use game_assets::{colors, hex_batch::RenderMode};
+
+pub mod game_clock;
+
+pub mod game_command;
 
 pub mod game_managers;
+use game_managers::occupancy::ResourceRule;
 
 pub mod game_state;
 use game_state::SandCastingGameState;
 
+pub mod keymap;
+
+pub mod map_editor;
+
+pub mod map_file;
+use map_file::MapFile;
+
 pub mod profiler;
 
+pub mod svg_export;
+
+pub mod weather_timeline;
+use weather_timeline::WeatherTimeline;
+
 
 ///////////////////////////////////////////////////////////////////////////////
 //  Constants
@@ -56,7 +88,6 @@ pub mod profiler;
 /* Window Appearance */
 const DEFAULT_WINDOW_SIZE_X: f32 = 1000.0;
 const DEFAULT_WINDOW_SIZE_Y: f32 = 1000.0;
-const DESIRED_FPS: u32 = 60;
 
 const DEFAULT_TEXT_SIZE: f32 = 16.0;
 const DEFAULT_LINE_WIDTH: f32 = 2.0;
@@ -90,6 +121,31 @@ const DEFAULT_MAX_WEATHER_INTENSITY: f64 = 256.0;
 /// Default maximum duration for a weather event (in seconds)
 const DEFAULT_MAX_WEATHER_DURATION: f64 = 10.0;
 
+/// Default number of upcoming weather events kept in the forecast
+const DEFAULT_WEATHER_FORECAST_LENGTH: usize = 4;
+
+/// Default time by which consecutive weather events overlap (in seconds)
+const DEFAULT_WEATHER_TRANSITION_OVERLAP: f64 = 1.0;
+
+/// Default length of game time shown in the weather intensity history (in seconds)
+const DEFAULT_WEATHER_HISTORY_WINDOW: f64 = 30.0;
+
+/// Default maximum number of live weather particles
+const DEFAULT_MAX_WEATHER_PARTICLES: usize = 1500;
+
+
+/* Simulation */
+/// Default number of simulation steps per second
+const DEFAULT_SIM_RATE: u32 = 60;
+
+/// Maximum number of simulation steps taken per frame, beyond which time is dropped
+const MAX_SIM_STEPS_PER_FRAME: u32 = 8;
+
+
+/* Editing */
+/// Default maximum number of world edits that can be undone
+const DEFAULT_MAX_EDIT_HISTORY: usize = 100;
+
 
 fn main() -> ! {
     //OPT: *DESIGN* Replace this with pattern from once_cell example
@@ -112,9 +168,24 @@ fn main() -> ! {
         profiler::Instance::disabled()
     };
 
+    // Use the requested grid radius if one was specified
+    let grid_radius = match arg_value(&args, "-radius").map(|radius_str| radius_str.parse()) {
+        Some(Ok(radius)) => radius,
+        Some(Err(e)) => {
+            mt_log!(
+                Level::Error,
+                "Invalid grid radius ({}), using default of {}.",
+                e,
+                DEFAULT_GRID_RADIUS
+            );
+            DEFAULT_GRID_RADIUS
+        }
+        None => DEFAULT_GRID_RADIUS,
+    };
+
     // Create CastIron game context
     let ci_ctx = CastIronContextBuilder::default()
-        .grid_radius(DEFAULT_GRID_RADIUS)
+        .grid_radius(grid_radius)
         .max_obstacle_len(DEFAULT_MAX_OBSTACLE_LENGTH)
         .max_rand_attempts(DEFAULT_MAX_RAND_ATTEMPTS)
         .max_resource_radius(DEFAULT_MAX_RESOURCE_RADIUS)
@@ -124,6 +195,101 @@ fn main() -> ! {
 
     mt_log!(Level::Debug, "CastIron context created.");
 
+    // Run headless subcommands before any window is created
+    if args.get(1).map(String::as_str) == Some("export-svg") {
+        std::process::exit(svg_export::run_export_svg(&args[2..], &ci_ctx));
+    }
+
+    // Load the starting map if one was specified, and save edits back to the same file
+    let (starting_map, map_path) = match arg_value(&args, "-map") {
+        Some(map_path) => match MapFile::load(map_path, &ci_ctx) {
+            Ok(map) => {
+                mt_log!(Level::Info, "Map loaded from '{}'.", map_path);
+                (Some(map), PathBuf::from(map_path))
+            }
+            Err(e) => {
+                mt_log!(
+                    Level::Error,
+                    "Error '{}' while loading map '{}', falling back to a random map.",
+                    e,
+                    map_path
+                );
+                (None, PathBuf::from(map_path))
+            }
+        },
+        None => (None, PathBuf::from(map_file::DEFAULT_MAP_PATH)),
+    };
+
+    // Draw with instancing if specified, for comparison against meshes in profiling runs
+    let render_mode = if args.contains(&String::from("-instanced")) {
+        RenderMode::Instanced
+    } else {
+        RenderMode::Mesh
+    };
+
+    // Set which entities may share a cell with a resource, if specified
+    let resource_rule = match arg_value(&args, "-resources").map(|rule_str| rule_str.parse()) {
+        Some(Ok(resource_rule)) => resource_rule,
+        Some(Err(e)) => {
+            mt_log!(Level::Error, "{}, using default.", e);
+            ResourceRule::default()
+        }
+        None => ResourceRule::default(),
+    };
+
+    // Set how long consecutive weather events overlap, if specified
+    let weather_overlap =
+        match arg_value(&args, "-weather-overlap").map(|overlap_str| overlap_str.parse::<f64>()) {
+            Some(Ok(overlap)) if overlap.is_finite() && overlap >= 0.0 => overlap,
+            Some(_invalid) => {
+                mt_log!(
+                    Level::Error,
+                    "Invalid weather overlap, using default of {}s.",
+                    DEFAULT_WEATHER_TRANSITION_OVERLAP
+                );
+                DEFAULT_WEATHER_TRANSITION_OVERLAP
+            }
+            None => DEFAULT_WEATHER_TRANSITION_OVERLAP,
+        };
+
+    // Set the simulation rate, independent of the render rate, if specified
+    let sim_rate = match arg_value(&args, "-sim-rate").map(|rate_str| rate_str.parse::<u32>()) {
+        Some(Ok(sim_rate)) if sim_rate > 0 => sim_rate,
+        Some(_invalid) => {
+            mt_log!(
+                Level::Error,
+                "Invalid simulation rate, using default of {} Hz.",
+                DEFAULT_SIM_RATE
+            );
+            DEFAULT_SIM_RATE
+        }
+        None => DEFAULT_SIM_RATE,
+    };
+
+    // Load a scripted weather timeline if one was specified, in place of random weather
+    let weather_timeline = match arg_value(&args, "-weather-timeline") {
+        Some(timeline_path) => match WeatherTimeline::load(timeline_path, &ci_ctx) {
+            Ok(timeline) => {
+                mt_log!(
+                    Level::Info,
+                    "Weather timeline loaded from '{}'.",
+                    timeline_path
+                );
+                Some(timeline)
+            }
+            Err(e) => {
+                mt_log!(
+                    Level::Error,
+                    "Error '{}' while loading weather timeline '{}', falling back to random weather.",
+                    e,
+                    timeline_path
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
     // Initialize Abilities
     let null_abil: Ability = Ability::new_name_only("Null");
 
@@ -164,10 +330,63 @@ fn main() -> ! {
         .unwrap();
     mt_log!(Level::Info, "ggez context, event loop created.");
 
-    // Use built context to create a GGEZ Event Handler instance
-    let sand_casting_game_state =
-        SandCastingGameState::new(&profiler_original, &ci_ctx, &mut ggez_ctx, std::io::stdout().lock());
+    // Load the statechart from the given path, or from the default location
+    let chart_path = arg_value(&args, "-statechart")
+        .or_else(|| arg_value(&args, "--statechart"))
+        .map(PathBuf::from);
+    let chart_source = ChartSource::new(chart_path, || std::io::stdout().lock());
+
+    // Use built context to create a GGEZ Event Handler instance, explaining any failure to start
+    let mut sand_casting_game_state = match SandCastingGameState::new(
+        &profiler_original,
+        &ci_ctx,
+        &mut ggez_ctx,
+        chart_source,
+        starting_map,
+        map_path,
+        render_mode,
+    ) {
+        Ok(game_state) => game_state,
+        Err(e) => {
+            mt_log!(Level::Error, "Failed to start: {}", e);
+            eprintln!("Sand Casting could not start: {}", e);
+
+            // Flush the log before exiting, so the failure is recorded
+            let _ = mt_flush!();
+            process::exit(1);
+        }
+    };
+    sand_casting_game_state.set_resource_rule(resource_rule);
+    sand_casting_game_state
+        .weather_manager()
+        .set_transition_overlap(Duration::from_secs_f64(weather_overlap));
+    sand_casting_game_state.sim_timestep().set_rate(sim_rate);
+    if let Some(keymap_path) = arg_value(&args, "-keymap") {
+        sand_casting_game_state.set_keymap_path(PathBuf::from(keymap_path));
+    }
+    if let Some(svg_path) = arg_value(&args, "-svg") {
+        sand_casting_game_state.set_svg_path(PathBuf::from(svg_path));
+    }
+    sand_casting_game_state.set_svg_show_coords(args.contains(&String::from("-svg-grid")));
+    if let Some(timeline) = weather_timeline {
+        let game_time = sand_casting_game_state.game_clock().time();
+        sand_casting_game_state
+            .weather_manager()
+            .play_timeline(timeline, game_time, &ci_ctx);
+    }
 
     // Run the game!
     ggez_event::run(ggez_ctx, ggez_event_loop, sand_casting_game_state);
 }
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the value following the given flag in the command line arguments, if present
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|flag_idx| args.get(flag_idx + 1))
+}
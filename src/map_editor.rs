@@ -0,0 +1,413 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : map_editor.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines the in-game map editor, which translates user input into edits
+    of the world while the statechart is in the 'editor' state.
+
+    The editor itself does not own any mechanics. It produces EditorActions
+    which are applied to the game managers by the game state.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::fmt;
+
+use cast_iron::{
+    actor::Actor,
+    context::Context as CastIronContext,
+    coords,
+    element::Element,
+    mechanics::{
+        obstacle::Obstacle,
+        resource::{Resource, State},
+    },
+};
+
+use ggez::{
+    graphics as ggez_gfx, input::keyboard as ggez_kb, mint as ggez_mint, Context as GgEzContext,
+};
+
+use mt_logger::{mt_log, Level};
+
+use crate::{
+    game_assets::{colors, hex_grid_cell::HexGridCell},
+    map_file,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// ID of the statechart state in which the editor is active
+pub const EDITOR_STATE_ID: &str = "editor";
+
+//FIXME: These probably should be relative to window size
+// Position of editor HUD text in window
+const EDITOR_HUD_POS: ggez_mint::Point2<f32> = ggez_mint::Point2 { x: 0.0, y: 900.0 };
+
+/// Elements available for placement, in cycling order
+pub const PLACEABLE_ELEMENTS: [Element; 8] = [
+    Element::Fire,
+    Element::Ice,
+    Element::Wind,
+    Element::Water,
+    Element::Electric,
+    Element::Earth,
+    Element::Light,
+    Element::Dark,
+];
+
+/// Resource states available for placement, in cycling order
+const PLACEABLE_STATES: [State; 6] = [
+    State::Depleted,
+    State::Low,
+    State::Partial,
+    State::High,
+    State::Full,
+    State::Overflow,
+];
+
+/// Maximum radius of the erase/fill brush (in cells)
+const MAX_BRUSH_RADIUS: usize = 5;
+
+/// Keys handled by the editor when pressed without modifiers, with what each is used for
+pub const EDITOR_KEYS: [(ggez_kb::KeyCode, &str); 14] = [
+    (ggez_kb::KeyCode::Key1, "selecting the editor tool"),
+    (ggez_kb::KeyCode::Key2, "selecting the editor tool"),
+    (ggez_kb::KeyCode::Key3, "selecting the editor tool"),
+    (ggez_kb::KeyCode::Key4, "selecting the editor tool"),
+    (ggez_kb::KeyCode::Key5, "selecting the editor tool"),
+    (ggez_kb::KeyCode::Key6, "selecting the editor tool"),
+    (ggez_kb::KeyCode::LBracket, "selecting the editor element"),
+    (ggez_kb::KeyCode::RBracket, "selecting the editor element"),
+    (
+        ggez_kb::KeyCode::Left,
+        "selecting the editor resource state",
+    ),
+    (
+        ggez_kb::KeyCode::Right,
+        "selecting the editor resource state",
+    ),
+    (ggez_kb::KeyCode::Down, "sizing editor resources"),
+    (ggez_kb::KeyCode::Up, "sizing editor resources"),
+    (ggez_kb::KeyCode::Minus, "sizing the editor brush"),
+    (ggez_kb::KeyCode::Equals, "sizing the editor brush"),
+];
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EditorTool {
+    PlaceActor,
+    RemoveActor,
+    PaintObstacle,
+    PlaceResource,
+    EraseRegion,
+    FillRegion,
+}
+
+/// Edits requested by the editor, to be applied to the world by the game state
+pub enum EditorAction {
+    PlaceActor(Actor),
+    RemoveActor(coords::Position),
+    PaintObstacle(Obstacle),
+    PlaceResource(Resource),
+    EraseRegion(coords::Position, usize),
+    FillRegion(coords::Position, usize, Element),
+}
+
+pub struct MapEditor {
+    tool: EditorTool,              // Currently-selected tool
+    element_idx: usize,            // Index of selected element in PLACEABLE_ELEMENTS
+    state_idx: usize,              // Index of selected resource state in PLACEABLE_STATES
+    resource_radius: usize,        // Radius of placed resources (in cells)
+    brush_radius: usize,           // Radius of the erase/fill brush (in cells)
+    stroke: Vec<coords::Position>, // Cells covered by the in-progress obstacle stroke
+    stroke_active: bool,           // Flag indicating if a drag is in progress
+    placed_actor_count: usize,     // Number of actors placed, used for naming
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl MapEditor {
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn tool(&self) -> EditorTool {
+        self.tool
+    }
+
+    pub fn element(&self) -> Element {
+        PLACEABLE_ELEMENTS[self.element_idx]
+    }
+
+    pub fn resource_state(&self) -> State {
+        PLACEABLE_STATES[self.state_idx]
+    }
+
+    pub fn resource_radius(&self) -> usize {
+        self.resource_radius
+    }
+
+    pub fn brush_radius(&self) -> usize {
+        self.brush_radius
+    }
+
+    pub fn stroke_active(&self) -> bool {
+        self.stroke_active
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Handles editor key bindings, returning true if the key was consumed
+    ///
+    /// The keys handled here must match EDITOR_KEYS, so that keymaps binding them are reported.
+    pub fn handle_key(&mut self, keymods: ggez_kb::KeyMods, keycode: ggez_kb::KeyCode) -> bool {
+        if keymods != ggez_kb::KeyMods::NONE {
+            return false;
+        }
+
+        match keycode {
+            // Tool selection
+            ggez_kb::KeyCode::Key1 => self.select_tool(EditorTool::PlaceActor),
+            ggez_kb::KeyCode::Key2 => self.select_tool(EditorTool::RemoveActor),
+            ggez_kb::KeyCode::Key3 => self.select_tool(EditorTool::PaintObstacle),
+            ggez_kb::KeyCode::Key4 => self.select_tool(EditorTool::PlaceResource),
+            ggez_kb::KeyCode::Key5 => self.select_tool(EditorTool::EraseRegion),
+            ggez_kb::KeyCode::Key6 => self.select_tool(EditorTool::FillRegion),
+
+            // Element selection
+            ggez_kb::KeyCode::LBracket => {
+                self.element_idx =
+                    (self.element_idx + PLACEABLE_ELEMENTS.len() - 1) % PLACEABLE_ELEMENTS.len();
+            }
+            ggez_kb::KeyCode::RBracket => {
+                self.element_idx = (self.element_idx + 1) % PLACEABLE_ELEMENTS.len();
+            }
+
+            // Resource state selection
+            ggez_kb::KeyCode::Left => {
+                self.state_idx =
+                    (self.state_idx + PLACEABLE_STATES.len() - 1) % PLACEABLE_STATES.len();
+            }
+            ggez_kb::KeyCode::Right => {
+                self.state_idx = (self.state_idx + 1) % PLACEABLE_STATES.len();
+            }
+
+            // Resource radius
+            ggez_kb::KeyCode::Down => {
+                self.resource_radius = self.resource_radius.saturating_sub(1).max(1);
+            }
+            ggez_kb::KeyCode::Up => {
+                self.resource_radius =
+                    (self.resource_radius + 1).min(crate::DEFAULT_MAX_RESOURCE_RADIUS);
+            }
+
+            // Brush radius
+            ggez_kb::KeyCode::Minus => {
+                self.brush_radius = self.brush_radius.saturating_sub(1);
+            }
+            ggez_kb::KeyCode::Equals => {
+                self.brush_radius = (self.brush_radius + 1).min(MAX_BRUSH_RADIUS);
+            }
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Handles a mouse press on the given cell
+    pub fn press(&mut self, position: coords::Position) -> Option<EditorAction> {
+        match self.tool {
+            EditorTool::PlaceActor => {
+                self.placed_actor_count += 1;
+                let mut actor =
+                    Actor::new_name_only(&format!("Editor Actor {}", self.placed_actor_count));
+                actor.set_origin(position);
+
+                Some(EditorAction::PlaceActor(actor))
+            }
+            EditorTool::RemoveActor => Some(EditorAction::RemoveActor(position)),
+            EditorTool::PaintObstacle => {
+                // Obstacles are committed once the drag is released
+                self.stroke.clear();
+                self.stroke.push(position);
+                self.stroke_active = true;
+
+                None
+            }
+            EditorTool::PlaceResource => Some(EditorAction::PlaceResource(Resource::new(
+                self.element(),
+                self.resource_state(),
+                position,
+                self.resource_radius,
+            ))),
+            EditorTool::EraseRegion => Some(EditorAction::EraseRegion(position, self.brush_radius)),
+            EditorTool::FillRegion => Some(EditorAction::FillRegion(
+                position,
+                self.brush_radius,
+                self.element(),
+            )),
+        }
+    }
+
+    /// Handles the mouse being dragged over the given cell
+    pub fn drag(&mut self, position: coords::Position, ci_ctx: &CastIronContext) {
+        if !self.stroke_active {
+            return;
+        }
+
+        let last_position = match self.stroke.last() {
+            Some(last_position) => *last_position,
+            None => return,
+        };
+        if last_position == position {
+            return;
+        }
+
+        // Fill in any cells skipped by a fast drag so the stroke stays contiguous
+        for line_position in HexGridCell::hex_line(&last_position, &position, ci_ctx)
+            .into_iter()
+            .skip(1)
+        {
+            // Obstacles may not cross themselves, so end the stroke extension at the first revisit
+            if self.stroke.contains(&line_position) {
+                break;
+            }
+
+            self.stroke.push(line_position);
+        }
+    }
+
+    /// Handles the mouse being released
+    pub fn release(&mut self) -> Option<EditorAction> {
+        if !self.stroke_active {
+            return None;
+        }
+
+        self.stroke_active = false;
+        let positions: Vec<coords::Position> = self.stroke.drain(..).collect();
+
+        mt_log!(
+            Level::Debug,
+            "Obstacle stroke released covering {} cell(s)",
+            positions.len()
+        );
+
+        Some(EditorAction::PaintObstacle(Obstacle::new(
+            positions,
+            self.element(),
+        )))
+    }
+
+    /// Draws the editor HUD and any in-progress stroke
+    pub fn draw(&self, ggez_ctx: &mut GgEzContext) {
+        // Draw in-progress stroke
+        if !self.stroke.is_empty() {
+            let mut mesh_builder = ggez_gfx::MeshBuilder::new();
+            for position in &self.stroke {
+                let stroke_hex =
+                    HexGridCell::new_from_hex_coords(position, crate::HEX_RADIUS_VERTEX, ggez_ctx);
+                stroke_hex.add_to_mesh(colors::HILITE_STD, colors::DARKGREY, &mut mesh_builder);
+            }
+
+            let stroke_mesh = mesh_builder.build(ggez_ctx).unwrap();
+            ggez_gfx::draw(ggez_ctx, &stroke_mesh, ggez_gfx::DrawParam::default()).unwrap();
+        }
+
+        // Draw editor HUD text
+        let hud_str = format!(
+            "EDITOR | Tool: {} | Element: {} | Resource: {} r{} | Brush: r{}\n\
+             [1-6] Tool  [ [ ] ] Element  [Left/Right] State  [Up/Down] Radius  [-/=] Brush  [Ctrl+S] Save",
+            self.tool,
+            map_file::element_to_str(self.element()),
+            map_file::state_to_str(self.resource_state()),
+            self.resource_radius,
+            self.brush_radius
+        );
+        let hud_display =
+            ggez_gfx::Text::new((hud_str, ggez_gfx::Font::default(), crate::DEFAULT_TEXT_SIZE));
+        ggez_gfx::draw(ggez_ctx, &hud_display, (EDITOR_HUD_POS, 0.0, colors::CYAN)).unwrap();
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    fn select_tool(&mut self, tool: EditorTool) {
+        // Abandon any in-progress stroke when switching tools
+        self.stroke.clear();
+        self.stroke_active = false;
+
+        self.tool = tool;
+        mt_log!(Level::Debug, "Editor tool changed to {}", tool);
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+/*  *  *  *  *  *  *  *  *  *\
+ *         MapEditor        *
+\*  *  *  *  *  *  *  *  *  */
+
+impl Default for MapEditor {
+    fn default() -> Self {
+        Self {
+            tool: EditorTool::PlaceActor,
+            element_idx: 0,
+            state_idx: 4, // Full
+            resource_radius: 1,
+            brush_radius: 1,
+            stroke: Vec::new(),
+            stroke_active: false,
+            placed_actor_count: 0,
+        }
+    }
+}
+
+
+/*  *  *  *  *  *  *  *  *  *\
+ *        EditorTool        *
+\*  *  *  *  *  *  *  *  *  */
+
+impl fmt::Display for EditorTool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PlaceActor => write!(f, "Place Actor"),
+            Self::RemoveActor => write!(f, "Remove Actor"),
+            Self::PaintObstacle => write!(f, "Paint Obstacle"),
+            Self::PlaceResource => write!(f, "Place Resource"),
+            Self::EraseRegion => write!(f, "Erase Region"),
+            Self::FillRegion => write!(f, "Fill Region"),
+        }
+    }
+}
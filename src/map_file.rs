@@ -0,0 +1,629 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : map_file.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines the on-disk format for Sand Casting world maps, along with functions
+    for saving and loading them.
+
+    Map files are plain text, one record per line:
+        resource <element> <state> <radius> <x> <y> <z>
+        obstacle <element> <x> <y> <z> [<x> <y> <z> ...]
+        actor <x> <y> <z> <name>
+        weather <element> <peak intensity> <duration in seconds>
+    Weather records are scheduled, in order, as the first events of the forecast.
+    Blank lines and lines beginning with '#' are ignored.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{collections::HashSet, error::Error, fmt, fs, io, path::Path, time::Duration};
+
+use cast_iron::{
+    actor::Actor,
+    context::Context as CastIronContext,
+    coords,
+    element::{Element, Elemental},
+    hex_directions,
+    mechanics::{
+        obstacle::Obstacle,
+        resource::{Resource, State},
+    },
+    Plottable,
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    game_managers::{
+        entity::{EntityId, InstanceKind},
+        occupancy::{OccupancyIndex, ResourceRule},
+        weather_event::WeatherEvent,
+        world_grid_manager::WorldGridManager,
+    },
+    map_editor,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Default location of the map file written by the editor
+pub const DEFAULT_MAP_PATH: &str = "./res/editor.map";
+
+/// Header written at the top of every map file
+const MAP_FILE_HEADER: &str = "# Sand Casting map file v1";
+
+/// Number of each kind of mechanic placed in a generated map
+const GENERATED_INSTANCE_COUNT: usize = 3;
+
+/// Resource states that may be chosen for generated resources
+const GENERATED_STATES: [State; 5] = [
+    State::Low,
+    State::Partial,
+    State::High,
+    State::Full,
+    State::Overflow,
+];
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// Contents of a loaded map file
+#[derive(Default)]
+pub struct MapFile {
+    actors: Vec<Actor>,
+    obstacles: Vec<Obstacle>,
+    resources: Vec<Resource>,
+    weather: Vec<WeatherEvent>,
+}
+
+#[derive(Debug)]
+pub enum MapFileError {
+    // Wrappers
+    IoError(io::Error),
+
+    // Parsing
+    UnknownRecord(usize, String),
+    MalformedRecord(usize, String),
+    InvalidPosition(usize, String),
+
+    // Saving
+    UnsetElement(&'static str),
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl MapFile {
+    /// Loads and parses the map file at the given path
+    pub fn load<P: AsRef<Path>>(path: P, ci_ctx: &CastIronContext) -> Result<Self, MapFileError> {
+        let contents = fs::read_to_string(path).map_err(MapFileError::IoError)?;
+
+        Self::parse(&contents, ci_ctx)
+    }
+
+    /// Parses map file contents
+    pub fn parse(contents: &str, ci_ctx: &CastIronContext) -> Result<Self, MapFileError> {
+        let mut map_file = Self::default();
+
+        for (i, line) in contents.lines().enumerate() {
+            // Line numbers are 1-based for error reporting
+            let line_num = i + 1;
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("resource") => {
+                    let fields: Vec<&str> = fields.collect();
+                    if fields.len() != 6 {
+                        return Err(MapFileError::MalformedRecord(
+                            line_num,
+                            String::from("resource requires element, state, radius and position"),
+                        ));
+                    }
+
+                    let element = parse_element(fields[0], line_num)?;
+                    let state = parse_state(fields[1], line_num)?;
+                    let radius = fields[2].parse::<usize>().map_err(|_e| {
+                        MapFileError::MalformedRecord(
+                            line_num,
+                            format!("invalid resource radius '{}'", fields[2]),
+                        )
+                    })?;
+                    let origin = parse_position(&fields[3..6], line_num, ci_ctx)?;
+
+                    map_file
+                        .resources
+                        .push(Resource::new(element, state, origin, radius));
+                }
+                Some("obstacle") => {
+                    let fields: Vec<&str> = fields.collect();
+                    if fields.len() < 4 || (fields.len() - 1) % 3 != 0 {
+                        return Err(MapFileError::MalformedRecord(
+                            line_num,
+                            String::from("obstacle requires an element and one or more positions"),
+                        ));
+                    }
+
+                    let element = parse_element(fields[0], line_num)?;
+                    let mut positions = Vec::new();
+                    for position_fields in fields[1..].chunks(3) {
+                        positions.push(parse_position(position_fields, line_num, ci_ctx)?);
+                    }
+
+                    map_file.obstacles.push(Obstacle::new(positions, element));
+                }
+                Some("actor") => {
+                    let fields: Vec<&str> = fields.collect();
+                    if fields.len() < 4 {
+                        return Err(MapFileError::MalformedRecord(
+                            line_num,
+                            String::from("actor requires a position and a name"),
+                        ));
+                    }
+
+                    let origin = parse_position(&fields[0..3], line_num, ci_ctx)?;
+                    let mut actor = Actor::new_name_only(&fields[3..].join(" "));
+                    actor.set_origin(origin);
+
+                    map_file.actors.push(actor);
+                }
+                Some("weather") => {
+                    let fields: Vec<&str> = fields.collect();
+                    if fields.len() != 3 {
+                        return Err(MapFileError::MalformedRecord(
+                            line_num,
+                            String::from("weather requires element, peak intensity and duration"),
+                        ));
+                    }
+
+                    let element = parse_element(fields[0], line_num)?;
+                    let peak_intensity = fields[1]
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|peak| *peak >= 0.0 && *peak <= ci_ctx.max_weather_intensity())
+                        .ok_or_else(|| {
+                            MapFileError::MalformedRecord(
+                                line_num,
+                                format!("invalid weather peak intensity '{}'", fields[1]),
+                            )
+                        })?;
+                    let duration = fields[2]
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|secs| secs.is_finite() && *secs > 0.0)
+                        .ok_or_else(|| {
+                            MapFileError::MalformedRecord(
+                                line_num,
+                                format!("invalid weather duration '{}'", fields[2]),
+                            )
+                        })?;
+
+                    map_file.weather.push(WeatherEvent::new(
+                        element,
+                        peak_intensity,
+                        Duration::from_secs_f64(duration),
+                    ));
+                }
+                Some(record) => {
+                    return Err(MapFileError::UnknownRecord(line_num, String::from(record)));
+                }
+                None => {}
+            }
+        }
+
+        Ok(map_file)
+    }
+
+    /// Generates a random map reproducibly from the given seed
+    ///
+    /// Placement follows the same occupancy rules as the game, with the given resource rule.
+    pub fn generate(
+        seed: u64,
+        grid_radius: usize,
+        resource_rule: ResourceRule,
+        ci_ctx: &CastIronContext,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let grid_positions = WorldGridManager::grid_positions(grid_radius, ci_ctx);
+        let grid_lookup: HashSet<coords::Position> = grid_positions.iter().copied().collect();
+        let sides: Vec<hex_directions::Side> =
+            hex_directions::Provider::new(hex_directions::Side::North).collect();
+
+        let mut map_file = Self::default();
+        let mut occupancy = OccupancyIndex::new();
+        occupancy.set_resource_rule(resource_rule);
+
+        // Picks a random cell the given kind may occupy, giving up after the configured number of attempts
+        let rand_free_position =
+            |rng: &mut StdRng, occupancy: &OccupancyIndex, kind: InstanceKind| {
+                (0..ci_ctx.max_rand_attempts())
+                    .map(|_attempt| *grid_positions.choose(rng).unwrap())
+                    .find(|position| occupancy.check(kind, &[*position], None).is_ok())
+            };
+
+        for _i in 0..GENERATED_INSTANCE_COUNT {
+            if let Some(origin) = rand_free_position(&mut rng, &occupancy, InstanceKind::Resource) {
+                occupancy.insert(InstanceKind::Resource, EntityId::next(), &[origin]);
+                map_file.resources.push(Resource::new(
+                    *map_editor::PLACEABLE_ELEMENTS.choose(&mut rng).unwrap(),
+                    *GENERATED_STATES.choose(&mut rng).unwrap(),
+                    origin,
+                    rng.gen_range(1, crate::DEFAULT_MAX_RESOURCE_RADIUS + 1),
+                ));
+            }
+        }
+
+        for _i in 0..GENERATED_INSTANCE_COUNT {
+            if let Some(origin) = rand_free_position(&mut rng, &occupancy, InstanceKind::Obstacle) {
+                // Random-walk from the origin, stopping early if the walk boxes itself in
+                let length = rng.gen_range(1, crate::DEFAULT_MAX_OBSTACLE_LENGTH + 1);
+                let mut positions = vec![origin];
+                while positions.len() < length {
+                    let cur_position = *positions.last().unwrap();
+                    let next_position = sides
+                        .choose_multiple(&mut rng, sides.len())
+                        .filter_map(|side| {
+                            let mut candidate = cur_position;
+                            if candidate
+                                .translate(&coords::Translation::from(*side), ci_ctx)
+                                .is_ok()
+                            {
+                                Some(candidate)
+                            } else {
+                                None
+                            }
+                        })
+                        .find(|candidate| {
+                            grid_lookup.contains(candidate)
+                                && !positions.contains(candidate)
+                                && occupancy
+                                    .check(InstanceKind::Obstacle, &[*candidate], None)
+                                    .is_ok()
+                        });
+
+                    match next_position {
+                        Some(next_position) => positions.push(next_position),
+                        None => break,
+                    }
+                }
+
+                occupancy.insert(InstanceKind::Obstacle, EntityId::next(), &positions);
+                map_file.obstacles.push(Obstacle::new(
+                    positions,
+                    *map_editor::PLACEABLE_ELEMENTS.choose(&mut rng).unwrap(),
+                ));
+            }
+        }
+
+        for i in 0..GENERATED_INSTANCE_COUNT {
+            if let Some(origin) = rand_free_position(&mut rng, &occupancy, InstanceKind::Actor) {
+                occupancy.insert(InstanceKind::Actor, EntityId::next(), &[origin]);
+                let mut actor = Actor::new_name_only(&format!("Generated Actor {}", i + 1));
+                actor.set_origin(origin);
+                map_file.actors.push(actor);
+            }
+        }
+
+        map_file
+    }
+
+    /// Writes the given mechanics out to a map file at the given path
+    pub fn save<P: AsRef<Path>>(
+        path: P,
+        actors: &[Actor],
+        obstacles: &[Obstacle],
+        resources: &[Resource],
+        weather: &[WeatherEvent],
+    ) -> Result<(), MapFileError> {
+        let contents = Self::format_contents(actors, obstacles, resources, weather)?;
+
+        fs::write(path, contents).map_err(MapFileError::IoError)
+    }
+
+    /// Formats the given mechanics as map file contents
+    ///
+    /// Mechanics without an element cannot be loaded back, so they are refused rather than saved.
+    pub fn format_contents(
+        actors: &[Actor],
+        obstacles: &[Obstacle],
+        resources: &[Resource],
+        weather: &[WeatherEvent],
+    ) -> Result<String, MapFileError> {
+        let mut contents = format!("{}\n", MAP_FILE_HEADER);
+
+        for resource in resources {
+            contents.push_str(&format!(
+                "resource {} {} {} {}\n",
+                saved_element_str(resource.element(), "resource")?,
+                state_to_str(resource.state()),
+                resource.radius(),
+                position_to_string(resource.origin())
+            ));
+        }
+
+        for obstacle in obstacles {
+            let positions: Vec<String> = obstacle
+                .positions()
+                .iter()
+                .map(position_to_string)
+                .collect();
+            contents.push_str(&format!(
+                "obstacle {} {}\n",
+                saved_element_str(obstacle.element(), "obstacle")?,
+                positions.join(" ")
+            ));
+        }
+
+        for actor in actors {
+            contents.push_str(&format!(
+                "actor {} {}\n",
+                position_to_string(actor.origin()),
+                actor.name()
+            ));
+        }
+
+        for event in weather {
+            contents.push_str(&format!(
+                "weather {} {} {}\n",
+                saved_element_str(event.element(), "weather")?,
+                event.peak_intensity(),
+                event.duration().as_secs_f64()
+            ));
+        }
+
+        Ok(contents)
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn actors(&self) -> &Vec<Actor> {
+        &self.actors
+    }
+
+    pub fn obstacles(&self) -> &Vec<Obstacle> {
+        &self.obstacles
+    }
+
+    pub fn resources(&self) -> &Vec<Resource> {
+        &self.resources
+    }
+
+    pub fn weather(&self) -> &Vec<WeatherEvent> {
+        &self.weather
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Consumes the map file, returning its actors, obstacles, resources and scheduled weather
+    pub fn into_parts(self) -> (Vec<Actor>, Vec<Obstacle>, Vec<Resource>, Vec<WeatherEvent>) {
+        (self.actors, self.obstacles, self.resources, self.weather)
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Error for MapFileError {}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IoError(io_err) => {
+                write!(f, "IoError '{}' encountered", io_err)
+            }
+            Self::UnknownRecord(line_num, record) => {
+                write!(f, "Line {}: Unknown record type '{}'", line_num, record)
+            }
+            Self::MalformedRecord(line_num, reason) => {
+                write!(f, "Line {}: Malformed record, {}", line_num, reason)
+            }
+            Self::InvalidPosition(line_num, coords) => {
+                write!(f, "Line {}: Invalid position '{}'", line_num, coords)
+            }
+            Self::UnsetElement(record) => {
+                write!(f, "Cannot save {} with an unset element", record)
+            }
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+pub fn element_to_str(element: Element) -> &'static str {
+    match element {
+        Element::Unset => "unset",
+        Element::Fire => "fire",
+        Element::Ice => "ice",
+        Element::Wind => "wind",
+        Element::Water => "water",
+        Element::Electric => "electric",
+        Element::Earth => "earth",
+        Element::Light => "light",
+        Element::Dark => "dark",
+    }
+}
+
+pub fn element_from_str(element_str: &str) -> Option<Element> {
+    match element_str {
+        "fire" => Some(Element::Fire),
+        "ice" => Some(Element::Ice),
+        "wind" => Some(Element::Wind),
+        "water" => Some(Element::Water),
+        "electric" => Some(Element::Electric),
+        "earth" => Some(Element::Earth),
+        "light" => Some(Element::Light),
+        "dark" => Some(Element::Dark),
+        _ => None,
+    }
+}
+
+pub fn state_to_str(state: State) -> &'static str {
+    match state {
+        State::Depleted => "depleted",
+        State::Low => "low",
+        State::Partial => "partial",
+        State::High => "high",
+        State::Full => "full",
+        State::Overflow => "overflow",
+    }
+}
+
+pub fn state_from_str(state_str: &str) -> Option<State> {
+    match state_str {
+        "depleted" => Some(State::Depleted),
+        "low" => Some(State::Low),
+        "partial" => Some(State::Partial),
+        "high" => Some(State::High),
+        "full" => Some(State::Full),
+        "overflow" => Some(State::Overflow),
+        _ => None,
+    }
+}
+
+fn position_to_string(position: &coords::Position) -> String {
+    format!("{} {} {}", position.x(), position.y(), position.z())
+}
+
+/// Returns the element as written to a map file, refusing an unset element as it cannot be loaded
+fn saved_element_str(element: Element, record: &'static str) -> Result<&'static str, MapFileError> {
+    match element {
+        Element::Unset => Err(MapFileError::UnsetElement(record)),
+        element => Ok(element_to_str(element)),
+    }
+}
+
+fn parse_element(element_str: &str, line_num: usize) -> Result<Element, MapFileError> {
+    element_from_str(element_str).ok_or_else(|| {
+        MapFileError::MalformedRecord(line_num, format!("unknown element '{}'", element_str))
+    })
+}
+
+fn parse_state(state_str: &str, line_num: usize) -> Result<State, MapFileError> {
+    state_from_str(state_str).ok_or_else(|| {
+        MapFileError::MalformedRecord(line_num, format!("unknown resource state '{}'", state_str))
+    })
+}
+
+fn parse_position(
+    fields: &[&str],
+    line_num: usize,
+    ci_ctx: &CastIronContext,
+) -> Result<coords::Position, MapFileError> {
+    let invalid_position = || MapFileError::InvalidPosition(line_num, fields.join(" "));
+
+    let mut components = [0; 3];
+    for (component, field) in components.iter_mut().zip(fields) {
+        *component = field.parse::<i32>().map_err(|_e| invalid_position())?;
+    }
+
+    coords::Position::new(components[0], components[1], components[2], ci_ctx)
+        .map_err(|_e| invalid_position())
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Unit Tests
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, time::Duration};
+
+    use cast_iron::{
+        actor::Actor,
+        context::Context as CastIronContext,
+        coords,
+        element::Element,
+        mechanics::{
+            obstacle::Obstacle,
+            resource::{Resource, State},
+        },
+    };
+
+    use crate::{
+        game_managers::weather_event::WeatherEvent,
+        map_file::{MapFile, MapFileError},
+    };
+
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    fn position(x: i32, y: i32, z: i32) -> Result<coords::Position, Box<dyn Error>> {
+        coords::Position::new(x, y, z, &CastIronContext::default())
+            .ok()
+            .ok_or_else(|| "Invalid test position".into())
+    }
+
+
+    #[test]
+    fn save_parse_round_trip_test() -> TestResult {
+        let ci_ctx = CastIronContext::default();
+
+        let mut actor = Actor::new_name_only("Test Actor");
+        actor.set_origin(position(1, -1, 0)?);
+        let obstacle = Obstacle::new(vec![position(0, 1, -1)?, position(0, 2, -2)?], Element::Ice);
+        let resource = Resource::new(Element::Fire, State::High, position(-1, 1, 0)?, 2);
+        let weather = WeatherEvent::new(Element::Wind, 0.5, Duration::from_secs(30));
+
+        let contents = MapFile::format_contents(&[actor], &[obstacle], &[resource], &[weather])?;
+        let map_file = MapFile::parse(&contents, &ci_ctx)?;
+        assert_eq!(map_file.actors().len(), 1);
+        assert_eq!(map_file.obstacles().len(), 1);
+        assert_eq!(map_file.resources().len(), 1);
+        assert_eq!(map_file.weather().len(), 1);
+
+        // Saving the parsed map again gives the same contents
+        let resaved_contents = MapFile::format_contents(
+            map_file.actors(),
+            map_file.obstacles(),
+            map_file.resources(),
+            map_file.weather(),
+        )?;
+        assert_eq!(resaved_contents, contents);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unset_element_not_saved_test() -> TestResult {
+        let obstacle = Obstacle::new(vec![position(0, 0, 0)?], Element::Unset);
+
+        match MapFile::format_contents(&[], &[obstacle], &[], &[]) {
+            Err(MapFileError::UnsetElement("obstacle")) => Ok(()),
+            other => Err(format!("Unexpected result {:?}", other).into()),
+        }
+    }
+}
@@ -91,6 +91,9 @@ impl MetricsReceiver {
                     profiler::MetricContainer::StackedDrawTime(timestamp, stacked_times) => {
                         Self::add_stacked_times_to_csv(timestamp, stacked_times, 7, file_handle)
                     }
+                    profiler::MetricContainer::AvgTps(timestamp, avg_tps) => {
+                        Self::add_f64_to_csv(timestamp, avg_tps, 0, file_handle);
+                    }
                 };
             }
         }
@@ -33,6 +33,9 @@ use crate::game_assets::colors;
 //  Named Constants
 ///////////////////////////////////////////////////////////////////////////////
 
+/// Length of the window over which sim ticks are counted to find the tick rate
+const TICK_RATE_WINDOW: Duration = Duration::from_secs(1);
+
 /// Placeholder for bound Durations
 pub const PLACEHOLDER_DURATION: Duration = Duration::from_secs(0);
 
@@ -72,6 +75,9 @@ pub struct Instance {
 struct CachedMetrics {
     pub avg_fps: f64,
     pub peak_fps: f64,
+    pub avg_tps: f64,
+    pub tick_window_start: Duration, // Time at which the current sim tick count began
+    pub tick_window_count: u32,      // Sim ticks counted since the window began
 }
 
 /// Enumeration for the various kinds of performance metrics that can be recorded.
@@ -81,6 +87,7 @@ pub enum MetricContainer {
     FrameDeltaTime(Duration, f64),
     EventMarker(Duration, String),
     StackedDrawTime(Duration, Vec<StackedTime>),
+    AvgTps(Duration, f64),
 }
 
 pub struct StackedTime {
@@ -122,6 +129,11 @@ impl Instance {
         self.cached_metrics.peak_fps
     }
 
+    /// Returns the average number of simulation ticks per second
+    pub fn avg_tps(&self) -> f64 {
+        self.cached_metrics.avg_tps
+    }
+
 
     /*  *  *  *  *  *  *  *
      *  Utility Methods   *
@@ -158,6 +170,21 @@ impl Instance {
             (peak_fps_pos, 0.0, colors::GREEN),
         )
         .unwrap();
+
+        // Draw avg. simulation ticks per second
+        let avg_tps_pos = ggez_mint::Point2 { x: 0.0, y: 40.0 };
+        let avg_tps_str = format!("Avg. TPS: {:.0}", self.cached_metrics.avg_tps);
+        let avg_tps_display = ggez_gfx::Text::new((
+            avg_tps_str,
+            ggez_gfx::Font::default(),
+            crate::DEFAULT_TEXT_SIZE,
+        ));
+        ggez_gfx::draw(
+            ggez_ctx,
+            &avg_tps_display,
+            (avg_tps_pos, 0.0, colors::GREEN),
+        )
+        .unwrap();
     }
 
     pub fn update_fps_stats(
@@ -184,6 +211,38 @@ impl Instance {
         }
     }
 
+    /// Counts a simulation tick, updating the average tick rate at the end of each window
+    pub fn record_sim_tick(
+        &mut self,
+        ggez_ctx: &GgEzContext,
+    ) -> Result<(), mpsc::SendError<MetricContainer>> {
+        // Get elapsed time
+        let elapsed_time = ggez_timer::time_since_start(ggez_ctx);
+
+        self.cached_metrics.tick_window_count += 1;
+
+        // Wait until the window has closed before updating the cached avg. TPS
+        let window_length = elapsed_time
+            .checked_sub(self.cached_metrics.tick_window_start)
+            .unwrap_or_default();
+        if window_length < TICK_RATE_WINDOW {
+            return Ok(());
+        }
+
+        self.cached_metrics.avg_tps =
+            f64::from(self.cached_metrics.tick_window_count) / window_length.as_secs_f64();
+        self.cached_metrics.tick_window_start = elapsed_time;
+        self.cached_metrics.tick_window_count = 0;
+
+        if self.enabled {
+            // Pack up TPS in a container and send
+            let metric = MetricContainer::AvgTps(elapsed_time, self.cached_metrics.avg_tps);
+            self.sender.send_metric(metric)
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn send_frame_delta(
         &self,
         ggez_ctx: &GgEzContext,
@@ -248,6 +307,7 @@ impl MetricContainer {
             MetricContainer::FrameDeltaTime(_dur, _val) => String::from("frame_delta.csv"),
             MetricContainer::EventMarker(_dur, _label) => String::from("event_marker.csv"),
             MetricContainer::StackedDrawTime(_dur, _vec) => String::from("stacked_draw_time.csv"),
+            MetricContainer::AvgTps(_dur, _val) => String::from("avg_tps.csv"),
         }
     }
 }
@@ -292,6 +352,7 @@ impl From<&MetricContainer> for usize {
             MetricContainer::FrameDeltaTime(_dur, _val) => 1,
             MetricContainer::EventMarker(_dur, _label) => 2,
             MetricContainer::StackedDrawTime(_dur, _vec) => 3,
+            MetricContainer::AvgTps(_dur, _val) => 4,
         }
     }
 }
@@ -304,6 +365,7 @@ impl From<usize> for MetricContainer {
             3 => {
                 MetricContainer::StackedDrawTime(PLACEHOLDER_DURATION, PLACEHOLDER_STACKED_DRAW_VEC)
             }
+            4 => MetricContainer::AvgTps(PLACEHOLDER_DURATION, PLACEHOLDER_F64),
             _ => panic!(
                 "Invalid value ({}) for usize -> MetricContainer conversion",
                 src
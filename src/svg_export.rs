@@ -0,0 +1,471 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : svg_export.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Renders the world to an SVG image without requiring a window or GPU.
+
+    Also provides the 'export-svg' command line subcommand:
+        sand_casting export-svg [--seed <n>] [--map <path>] [--grid] <out.svg>
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{collections::HashSet, fmt::Write as FmtWrite, fs, io, path::Path};
+
+use cast_iron::{
+    actor::Actor,
+    context::Context as CastIronContext,
+    coords,
+    element::Elemental,
+    hex_directions,
+    mechanics::{obstacle::Obstacle, resource::Resource},
+    Plottable,
+};
+
+use ggez::{graphics as ggez_gfx, mint as ggez_mint};
+
+use crate::{
+    game_assets::{colors, hex_grid_cell::HexGridCell},
+    game_managers::{
+        actor_manager::ActorManager, influence::InfluenceArea, obstacle_manager::ObstacleManager,
+        occupancy::ResourceRule, resource_manager::ResourceManager,
+        world_grid_manager::WorldGridManager, DrawableMechanic,
+    },
+    map_file::MapFile,
+};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Named Constants
+///////////////////////////////////////////////////////////////////////////////
+
+/// Font size of coordinate grid labels
+const COORD_LABEL_SIZE: f32 = 7.0;
+
+/// Usage string for the export-svg subcommand
+const EXPORT_SVG_USAGE: &str =
+    "Usage: sand_casting export-svg [--seed <n>] [--map <path>] [--grid] <out.svg>";
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+pub struct SvgExporter {
+    width: f32,        // Width of the image in pixels
+    height: f32,       // Height of the image in pixels
+    show_coords: bool, // Flag indicating if each cell should be labelled with its coordinates
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl SvgExporter {
+    /// Fully-qualified constructor
+    pub fn new(width: f32, height: f32, show_coords: bool) -> Self {
+        Self {
+            width,
+            height,
+            show_coords,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Renders the current state of the world managers to an SVG document
+    pub fn render_world(
+        &self,
+        world_grid_manager: &WorldGridManager,
+        actor_manager: &ActorManager,
+        obstacle_manager: &ObstacleManager,
+        resource_manager: &ResourceManager,
+    ) -> String {
+        let cells: Vec<(coords::Position, bool)> = world_grid_manager
+            .hex_map()
+            .iter()
+            .map(|(position, hex_cell)| (*position, hex_cell.highlighted()))
+            .collect();
+
+        self.render(
+            &cells,
+            actor_manager.instances(),
+            obstacle_manager.instances(),
+            resource_manager.instances(),
+            resource_manager.influence_areas(),
+        )
+    }
+
+    /// Renders the given grid cells and mechanics to an SVG document
+    ///
+    /// Each cell is given as its position and whether it is highlighted. Cells are drawn sorted by
+    /// position, so the same world gives the same document whatever order they are given in.
+    /// Influence areas are matched to resources by index.
+    pub fn render(
+        &self,
+        cells: &[(coords::Position, bool)],
+        actors: &[Actor],
+        obstacles: &[Obstacle],
+        resources: &[Resource],
+        influence_areas: &[InfluenceArea],
+    ) -> String {
+        let mut svg = String::new();
+
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            svg_rgb(colors::BLACK)
+        )
+        .unwrap();
+
+        // Draw the base grid
+        let mut sorted_cells: Vec<&(coords::Position, bool)> = cells.iter().collect();
+        sorted_cells.sort_by_key(|(position, _highlighted)| position_key(position));
+        writeln!(svg, r#"<g id="grid">"#).unwrap();
+        for (position, highlighted) in sorted_cells {
+            let hex_cell = self.hex_cell(position);
+            let fill_color = if *highlighted {
+                colors::HILITE_STD
+            } else {
+                colors::TRANSPARENT
+            };
+            push_hex(&mut svg, &hex_cell, fill_color, crate::DEFAULT_LINE_COLOR);
+        }
+        writeln!(svg, "</g>").unwrap();
+
+        // Draw resources and their influenced cells
+        writeln!(svg, r#"<g id="resources">"#).unwrap();
+        for (resource_idx, resource) in resources.iter().enumerate() {
+            let resource_color = colors::from_resource(resource);
+
+            // Influenced cells come from the matching influence area, if one was given, and are
+            // sorted as the area is unordered, to keep the output reproducible
+            if let Some(influence_area) = influence_areas.get(resource_idx) {
+                let mut influenced_cells: Vec<(&coords::Position, &usize)> =
+                    influence_area.cells().iter().collect();
+                influenced_cells.sort_by_key(|(position, _distance)| position_key(position));
+
+                for (position, distance) in influenced_cells {
+                    if *distance == 0 {
+                        continue;
+                    }
+
+                    let mut radial_color = resource_color;
+                    radial_color.a =
+                        HexGridCell::radial_alpha(resource_color.a, resource.radius(), *distance);
+                    push_hex(
+                        &mut svg,
+                        &self.hex_cell(position),
+                        radial_color,
+                        colors::WHITE,
+                    );
+                }
+            }
+
+            push_hex(
+                &mut svg,
+                &self.hex_cell(resource.origin()),
+                resource_color,
+                colors::WHITE,
+            );
+        }
+        writeln!(svg, "</g>").unwrap();
+
+        // Draw obstacles
+        writeln!(svg, r#"<g id="obstacles">"#).unwrap();
+        for obstacle in obstacles {
+            let obstacle_color = colors::from_element(obstacle.element());
+            let obstacle_positions = obstacle.positions();
+
+            for (i, obstacle_pos) in obstacle_positions.iter().enumerate() {
+                let cur_hex = self.hex_cell(obstacle_pos);
+                push_hex(&mut svg, &cur_hex, obstacle_color, colors::DARKGREY);
+
+                // Paint over the side shared with the previous cell so the obstacle reads as one piece
+                if i > 0 {
+                    let prev_obstacle_pos = &obstacle_positions[i - 1];
+                    let direction =
+                        hex_directions::Side::from(obstacle_pos.delta_to(prev_obstacle_pos));
+                    let (first_vertex, second_vertex) =
+                        hex_directions::Side::get_adjacent_vertices(direction);
+                    let start = cur_hex.vertices()[usize::from(first_vertex)];
+                    let end = cur_hex.vertices()[usize::from(second_vertex)];
+
+                    writeln!(
+                        svg,
+                        r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{}" stroke-width="{}"/>"#,
+                        start.x,
+                        start.y,
+                        end.x,
+                        end.y,
+                        svg_rgb(obstacle_color),
+                        crate::DEFAULT_LINE_WIDTH
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        writeln!(svg, "</g>").unwrap();
+
+        // Draw actors
+        writeln!(svg, r#"<g id="actors">"#).unwrap();
+        for actor in actors {
+            let actor_center = self.hex_cell(actor.origin()).center();
+            writeln!(
+                svg,
+                r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}"/>"#,
+                actor_center.x,
+                actor_center.y,
+                crate::HEX_RADIUS_VERTEX / 2.0,
+                svg_rgb(colors::GREEN)
+            )
+            .unwrap();
+        }
+        writeln!(svg, "</g>").unwrap();
+
+        // Label each cell with its coordinates if requested
+        if self.show_coords {
+            writeln!(
+                svg,
+                r#"<g id="coords" font-family="monospace" font-size="{}" text-anchor="middle" fill="{}">"#,
+                COORD_LABEL_SIZE,
+                svg_rgb(colors::GREY)
+            )
+            .unwrap();
+            for (position, _highlighted) in cells {
+                let cell_center = self.hex_cell(position).center();
+                writeln!(
+                    svg,
+                    r#"<text x="{:.2}" y="{:.2}">{},{},{}</text>"#,
+                    cell_center.x,
+                    cell_center.y + COORD_LABEL_SIZE / 3.0,
+                    position.x(),
+                    position.y(),
+                    position.z()
+                )
+                .unwrap();
+            }
+            writeln!(svg, "</g>").unwrap();
+        }
+
+        writeln!(svg, "</svg>").unwrap();
+
+        svg
+    }
+
+    /// Renders the given mechanics to an SVG file at the given path
+    pub fn export<P: AsRef<Path>>(
+        &self,
+        path: P,
+        cells: &[(coords::Position, bool)],
+        actors: &[Actor],
+        obstacles: &[Obstacle],
+        resources: &[Resource],
+        influence_areas: &[InfluenceArea],
+    ) -> Result<(), io::Error> {
+        fs::write(
+            path,
+            self.render(cells, actors, obstacles, resources, influence_areas),
+        )
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *   Helper Methods   *
+    \*  *  *  *  *  *  *  */
+
+    fn hex_cell(&self, position: &coords::Position) -> HexGridCell {
+        let grid_center = ggez_mint::Point2 {
+            x: self.width / 2.0,
+            y: self.height / 2.0,
+        };
+
+        HexGridCell::new_from_hex_coords_about(position, crate::HEX_RADIUS_VERTEX, grid_center)
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Utility Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Entry point for the 'export-svg' subcommand, returning the process exit code
+pub fn run_export_svg(args: &[String], ci_ctx: &CastIronContext) -> i32 {
+    let mut seed: Option<u64> = None;
+    let mut map_path: Option<&String> = None;
+    let mut show_coords = false;
+    let mut out_path: Option<&String> = None;
+
+    // Parse subcommand arguments
+    let mut arg_iter = args.iter();
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "--seed" => match arg_iter.next().map(|seed_str| seed_str.parse::<u64>()) {
+                Some(Ok(seed_val)) => seed = Some(seed_val),
+                _ => {
+                    eprintln!(
+                        "--seed requires an unsigned integer value\n{}",
+                        EXPORT_SVG_USAGE
+                    );
+                    return 2;
+                }
+            },
+            "--map" => match arg_iter.next() {
+                Some(path) => map_path = Some(path),
+                None => {
+                    eprintln!("--map requires a path\n{}", EXPORT_SVG_USAGE);
+                    return 2;
+                }
+            },
+            "--grid" => show_coords = true,
+            // Ignore the logging/profiling flags handled by main
+            "-log" | "-debug" | "-trace" | "-profile" => {}
+            _ if out_path.is_none() => out_path = Some(arg),
+            _ => {
+                eprintln!("Unexpected argument '{}'\n{}", arg, EXPORT_SVG_USAGE);
+                return 2;
+            }
+        }
+    }
+
+    let out_path = match out_path {
+        Some(out_path) => out_path,
+        None => {
+            eprintln!("No output path given\n{}", EXPORT_SVG_USAGE);
+            return 2;
+        }
+    };
+
+    // Load the requested map, or generate one from the seed
+    let map = match map_path {
+        Some(map_path) => match MapFile::load(map_path, ci_ctx) {
+            Ok(map) => map,
+            Err(e) => {
+                eprintln!("Error '{}' while loading map '{}'", e, map_path);
+                return 1;
+            }
+        },
+        None => MapFile::generate(
+            seed.unwrap_or_else(rand::random),
+            ci_ctx.grid_radius(),
+            ResourceRule::default(),
+            ci_ctx,
+        ),
+    };
+
+    let cells: Vec<(coords::Position, bool)> =
+        WorldGridManager::grid_positions(ci_ctx.grid_radius(), ci_ctx)
+            .into_iter()
+            .map(|position| (position, false))
+            .collect();
+    let grid_positions: HashSet<coords::Position> = cells
+        .iter()
+        .map(|(position, _highlighted)| *position)
+        .collect();
+
+    // Influence is clipped to the grid, but not blocked by obstacles, as in the game's default
+    let influence_areas: Vec<InfluenceArea> = map
+        .resources()
+        .iter()
+        .map(|resource| {
+            InfluenceArea::compute(
+                resource,
+                |position| grid_positions.contains(position),
+                &HashSet::new(),
+                ci_ctx,
+            )
+        })
+        .collect();
+
+    let exporter = SvgExporter::new(
+        crate::DEFAULT_WINDOW_SIZE_X,
+        crate::DEFAULT_WINDOW_SIZE_Y,
+        show_coords,
+    );
+    match exporter.export(
+        out_path,
+        &cells,
+        map.actors(),
+        map.obstacles(),
+        map.resources(),
+        &influence_areas,
+    ) {
+        Ok(()) => {
+            println!("Map exported to '{}'", out_path);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error '{}' while writing '{}'", e, out_path);
+            1
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns a key by which positions are sorted into a stable order
+fn position_key(position: &coords::Position) -> (i32, i32, i32) {
+    (position.x(), position.y(), position.z())
+}
+
+/// Formats the RGB components of a color for use in an SVG attribute
+fn svg_rgb(color: ggez_gfx::Color) -> String {
+    format!(
+        "rgb({},{},{})",
+        (color.r * 255.0).round() as u8,
+        (color.g * 255.0).round() as u8,
+        (color.b * 255.0).round() as u8
+    )
+}
+
+/// Appends a filled and outlined hexagon to the SVG document
+fn push_hex(
+    svg: &mut String,
+    hex_cell: &HexGridCell,
+    fill_color: ggez_gfx::Color,
+    outline_color: ggez_gfx::Color,
+) {
+    let points: Vec<String> = hex_cell
+        .vertices()
+        .iter()
+        .map(|vertex| format!("{:.2},{:.2}", vertex.x, vertex.y))
+        .collect();
+
+    writeln!(
+        svg,
+        r#"<polygon points="{}" fill="{}" fill-opacity="{:.3}" stroke="{}" stroke-opacity="{:.3}" stroke-width="{}"/>"#,
+        points.join(" "),
+        svg_rgb(fill_color),
+        fill_color.a.max(0.0),
+        svg_rgb(outline_color),
+        outline_color.a,
+        crate::DEFAULT_LINE_WIDTH
+    )
+    .unwrap();
+}
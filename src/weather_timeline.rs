@@ -0,0 +1,333 @@
+/* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * *\
+Filename : weather_timeline.rs
+
+Copyright (C) 2020 CJ McAllister
+    This program is free software; you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation; either version 3 of the License, or
+    (at your option) any later version.
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+    You should have received a copy of the GNU General Public License
+    along with this program; if not, write to the Free Software Foundation,
+    Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301  USA
+
+Purpose:
+    Defines the on-disk format for scripted weather timelines, along with the
+    playback state used to step through them.
+
+    Timeline files are plain text, one record per line:
+        event <start in seconds> <element> <duration in seconds> <peak intensity>
+        then <loop | random>
+    Events must be listed in order of their start time, and may not overlap.
+    The optional 'then' record sets what follows the last event: the timeline
+    either starts over, or hands over to random weather (the default).
+    Blank lines and lines beginning with '#' are ignored.
+
+\* * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * */
+
+use std::{error::Error, fmt, fs, io, path::Path, time::Duration};
+
+use cast_iron::context::Context as CastIronContext;
+
+use crate::{game_managers::weather_event::WeatherEvent, map_file};
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Data Structures
+///////////////////////////////////////////////////////////////////////////////
+
+/// What follows the last event of a timeline
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TimelineEnd {
+    Loop,   // Start the timeline over
+    Random, // Hand over to random weather
+}
+
+/// Contents of a loaded weather timeline file
+pub struct WeatherTimeline {
+    events: Vec<WeatherEvent>, // Scripted events, in order, timed from the start of the timeline
+    end: TimelineEnd,          // What follows the last event
+}
+
+/// Position of playback through a weather timeline
+pub struct TimelinePlayback {
+    timeline: WeatherTimeline,
+    origin: Duration, // Game time at which the current pass through the timeline began
+    next_index: usize, // Index of the next event to be played
+    passes: usize,    // Number of completed passes through the timeline
+}
+
+#[derive(Debug)]
+pub enum WeatherTimelineError {
+    // Wrappers
+    IoError(io::Error),
+
+    // Parsing
+    UnknownRecord(usize, String),
+    MalformedRecord(usize, String),
+    OverlappingEvent(usize, usize),
+
+    // Validation
+    NoEvents,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Object Implementation
+///////////////////////////////////////////////////////////////////////////////
+
+impl WeatherTimeline {
+    /// Loads and parses the timeline file at the given path
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        ci_ctx: &CastIronContext,
+    ) -> Result<Self, WeatherTimelineError> {
+        let contents = fs::read_to_string(path).map_err(WeatherTimelineError::IoError)?;
+
+        Self::parse(&contents, ci_ctx)
+    }
+
+    /// Parses timeline file contents
+    pub fn parse(contents: &str, ci_ctx: &CastIronContext) -> Result<Self, WeatherTimelineError> {
+        let mut events: Vec<WeatherEvent> = Vec::new();
+        let mut end = None;
+
+        // Line number of the last event, for reporting overlaps
+        let mut last_event_line_num = 0;
+
+        for (i, line) in contents.lines().enumerate() {
+            // Line numbers are 1-based for error reporting
+            let line_num = i + 1;
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("event") => {
+                    let fields: Vec<&str> = fields.collect();
+                    if fields.len() != 4 {
+                        return Err(WeatherTimelineError::MalformedRecord(
+                            line_num,
+                            String::from(
+                                "event requires start, element, duration and peak intensity",
+                            ),
+                        ));
+                    }
+
+                    let start = parse_secs(fields[0], "start", line_num, |secs| secs >= 0.0)?;
+                    let element = map_file::element_from_str(fields[1]).ok_or_else(|| {
+                        WeatherTimelineError::MalformedRecord(
+                            line_num,
+                            format!("unknown element '{}'", fields[1]),
+                        )
+                    })?;
+                    let duration = parse_secs(fields[2], "duration", line_num, |secs| secs > 0.0)?;
+                    let peak_intensity = fields[3]
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|peak| *peak >= 0.0 && *peak <= ci_ctx.max_weather_intensity())
+                        .ok_or_else(|| {
+                            WeatherTimelineError::MalformedRecord(
+                                line_num,
+                                format!("invalid peak intensity '{}'", fields[3]),
+                            )
+                        })?;
+
+                    // Events must follow one another, so playback never has two scripted events at once
+                    if let Some(last_event) = events.last() {
+                        if start < last_event.end() {
+                            return Err(WeatherTimelineError::OverlappingEvent(
+                                line_num,
+                                last_event_line_num,
+                            ));
+                        }
+                    }
+
+                    events.push(
+                        WeatherEvent::new(element, peak_intensity, duration).starting_at(start),
+                    );
+                    last_event_line_num = line_num;
+                }
+                Some("then") => {
+                    if end.is_some() {
+                        return Err(WeatherTimelineError::MalformedRecord(
+                            line_num,
+                            String::from("timeline end may only be given once"),
+                        ));
+                    }
+
+                    end = match fields.collect::<Vec<&str>>().as_slice() {
+                        ["loop"] => Some(TimelineEnd::Loop),
+                        ["random"] => Some(TimelineEnd::Random),
+                        _ => {
+                            return Err(WeatherTimelineError::MalformedRecord(
+                                line_num,
+                                String::from("then requires either 'loop' or 'random'"),
+                            ));
+                        }
+                    };
+                }
+                Some(record) => {
+                    return Err(WeatherTimelineError::UnknownRecord(
+                        line_num,
+                        String::from(record),
+                    ));
+                }
+                None => {}
+            }
+        }
+
+        if events.is_empty() {
+            return Err(WeatherTimelineError::NoEvents);
+        }
+
+        Ok(Self {
+            events,
+            end: end.unwrap_or(TimelineEnd::Random),
+        })
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn events(&self) -> &Vec<WeatherEvent> {
+        &self.events
+    }
+
+    pub fn end(&self) -> TimelineEnd {
+        self.end
+    }
+
+    /// Returns the time from the start of the timeline to the end of its last event
+    pub fn length(&self) -> Duration {
+        self.events
+            .last()
+            .map(WeatherEvent::end)
+            .unwrap_or_default()
+    }
+}
+
+impl TimelinePlayback {
+    /// Fully-qualified constructor - plays the given timeline from the given game time
+    pub fn new(timeline: WeatherTimeline, origin: Duration) -> Self {
+        Self {
+            timeline,
+            origin,
+            next_index: 0,
+            passes: 0,
+        }
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Accessor Methods  *
+    \*  *  *  *  *  *  *  */
+
+    pub fn timeline(&self) -> &WeatherTimeline {
+        &self.timeline
+    }
+
+    /// Returns the number of completed passes through the timeline
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+
+    /*  *  *  *  *  *  *  *\
+     *  Utility Methods   *
+    \*  *  *  *  *  *  *  */
+
+    /// Returns the next scripted event, timed in game time, or None once a non-looping timeline has finished
+    pub fn next_event(&mut self) -> Option<WeatherEvent> {
+        if self.next_index >= self.timeline.events.len() {
+            match self.timeline.end {
+                TimelineEnd::Loop => {
+                    self.origin += self.timeline.length();
+                    self.next_index = 0;
+                    self.passes += 1;
+                }
+                TimelineEnd::Random => return None,
+            }
+        }
+
+        let event = self.timeline.events[self.next_index];
+        self.next_index += 1;
+
+        Some(event.starting_at(self.origin + event.start()))
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Trait Implementations
+///////////////////////////////////////////////////////////////////////////////
+
+impl Error for WeatherTimelineError {}
+
+impl fmt::Display for TimelineEnd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Loop => write!(f, "loop"),
+            Self::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl fmt::Display for WeatherTimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::IoError(io_err) => {
+                write!(f, "IoError '{}' encountered", io_err)
+            }
+            Self::UnknownRecord(line_num, record) => {
+                write!(f, "Line {}: Unknown record type '{}'", line_num, record)
+            }
+            Self::MalformedRecord(line_num, reason) => {
+                write!(f, "Line {}: Malformed record, {}", line_num, reason)
+            }
+            Self::OverlappingEvent(line_num, prev_line_num) => {
+                write!(
+                    f,
+                    "Line {}: Event starts before the event on line {} has ended",
+                    line_num, prev_line_num
+                )
+            }
+            Self::NoEvents => {
+                write!(f, "Timeline contains no events")
+            }
+        }
+    }
+}
+
+
+///////////////////////////////////////////////////////////////////////////////
+//  Helper Functions
+///////////////////////////////////////////////////////////////////////////////
+
+/// Parses a number of seconds, accepting it only if it is finite and passes the given check
+fn parse_secs<F: Fn(f64) -> bool>(
+    secs_str: &str,
+    field_name: &str,
+    line_num: usize,
+    is_valid: F,
+) -> Result<Duration, WeatherTimelineError> {
+    secs_str
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && is_valid(*secs))
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| {
+            WeatherTimelineError::MalformedRecord(
+                line_num,
+                format!("invalid {} '{}'", field_name, secs_str),
+            )
+        })
+}